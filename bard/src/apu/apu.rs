@@ -0,0 +1,232 @@
+//! # apu.rs
+//!
+//!  Author: Paul Hazen
+//! Created: 2026-10-19
+//! License: MIT (see LICENSE file)
+//!
+//! ## Description
+//! The 2A03's audio processing unit: two pulse channels, a triangle, noise,
+//! the frame counter that clocks their envelopes and counters, and the mixer.
+//!
+//! The delta modulation channel only supports direct loads through $4011.
+//! Sample playback needs DMA reads from the CPU bus and is not emulated.
+use super::{Noise, Pulse, Triangle};
+use crate::region::Region;
+
+const DMC_LOAD: u16 = 0x4011;
+
+/* $4015 - Status
+ * --- | Bit | Function
+ *     |  0  | Pulse 1 enabled / length counter active
+ *     |  1  | Pulse 2 enabled / length counter active
+ *     |  2  | Triangle enabled / length counter active
+ *     |  3  | Noise enabled / length counter active
+ *     |  6  | Frame interrupt (read only, cleared by the read)
+ */
+const STATUS_PULSE_1: u8 = 0b0000_0001;
+const STATUS_PULSE_2: u8 = 0b0000_0010;
+const STATUS_TRIANGLE: u8 = 0b0000_0100;
+const STATUS_NOISE: u8 = 0b0000_1000;
+const STATUS_FRAME_IRQ: u8 = 0b0100_0000;
+
+/* $4017 - Frame counter
+ * --- | Bit | Function
+ *     |  6  | Inhibit the frame interrupt
+ *     |  7  | Mode: 0 = 4 step, 1 = 5 step
+ */
+const FRAME_IRQ_INHIBIT: u8 = 0b0100_0000;
+const FRAME_FIVE_STEP: u8 = 0b1000_0000;
+
+/// CPU cycles at which the frame counter clocks the channels (NTSC). The
+/// 4 step sequence ends at the fourth, the 5 step sequence at the fifth.
+const FRAME_STEPS: [u32; 5] = [7_457, 14_913, 22_371, 29_829, 37_281];
+/// The frame counter's steps on PAL, which keep it near 200 Hz on the
+/// slower CPU.
+const PAL_FRAME_STEPS: [u32; 5] = [8_313, 16_627, 24_939, 33_253, 41_565];
+
+/// The 7 bit output level of the delta modulation channel.
+const DMC_LEVEL_MASK: u8 = 0b0111_1111;
+
+pub struct Apu {
+    pulse: [Pulse; 2],
+    triangle: Triangle,
+    noise: Noise,
+    dmc_level: u8,
+
+    five_step: bool,
+    irq_inhibit: bool,
+    frame_irq: bool,
+    frame_steps: &'static [u32; 5],
+    frame_cycle: u32,
+    /// Pulse timers run at half the CPU clock.
+    odd_cycle: bool,
+}
+
+impl Default for Apu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Apu {
+    pub const CHANNELS_START: u16 = 0x4000;
+    pub const CHANNELS_END: u16 = 0x4013;
+    pub const STATUS: u16 = 0x4015;
+    pub const FRAME_COUNTER: u16 = 0x4017;
+
+    pub fn new() -> Self {
+        Self {
+            pulse: [Pulse::new(true), Pulse::new(false)],
+            triangle: Triangle::default(),
+            noise: Noise::default(),
+            dmc_level: 0,
+            five_step: false,
+            irq_inhibit: false,
+            frame_irq: false,
+            frame_steps: &FRAME_STEPS,
+            frame_cycle: 0,
+            odd_cycle: false,
+        }
+    }
+
+    /// Changes the frame counter and noise rates to the console region's. The
+    /// Dendy's APU keeps NTSC rates.
+    pub fn set_region(&mut self, region: Region) {
+        self.frame_steps = if region == Region::Pal { &PAL_FRAME_STEPS } else { &FRAME_STEPS };
+        self.noise.set_region(region);
+    }
+
+    /// Writes one of the APU registers ($4000-$4013, $4015, $4017).
+    pub fn write_register(&mut self, address: u16, value: u8) {
+        let register = address & 0x03;
+
+        match address {
+            0x4000..=0x4003 => self.pulse[0].write(register, value),
+            0x4004..=0x4007 => self.pulse[1].write(register, value),
+            0x4008..=0x400B => self.triangle.write(register, value),
+            0x400C..=0x400F => self.noise.write(register, value),
+            DMC_LOAD => self.dmc_level = value & DMC_LEVEL_MASK,
+            Self::STATUS => {
+                self.pulse[0].length.set_enabled(value & STATUS_PULSE_1 != 0);
+                self.pulse[1].length.set_enabled(value & STATUS_PULSE_2 != 0);
+                self.triangle.length.set_enabled(value & STATUS_TRIANGLE != 0);
+                self.noise.length.set_enabled(value & STATUS_NOISE != 0);
+            }
+            Self::FRAME_COUNTER => {
+                self.five_step = value & FRAME_FIVE_STEP != 0;
+                self.irq_inhibit = value & FRAME_IRQ_INHIBIT != 0;
+                if self.irq_inhibit {
+                    self.frame_irq = false;
+                }
+
+                // The 5 step mode clocks everything as soon as it is selected
+                self.frame_cycle = 0;
+                if self.five_step {
+                    self.clock_quarter_frame();
+                    self.clock_half_frame();
+                }
+            }
+            _ => {} // The rest of the DMC is not emulated
+        }
+    }
+
+    /// Reads $4015, which reports the active channels and clears the frame
+    /// interrupt.
+    pub fn read_status(&mut self) -> u8 {
+        let mut status = 0;
+        for (active, bit) in [
+            (self.pulse[0].length.is_active(), STATUS_PULSE_1),
+            (self.pulse[1].length.is_active(), STATUS_PULSE_2),
+            (self.triangle.length.is_active(), STATUS_TRIANGLE),
+            (self.noise.length.is_active(), STATUS_NOISE),
+            (self.frame_irq, STATUS_FRAME_IRQ),
+        ] {
+            if active {
+                status |= bit;
+            }
+        }
+
+        self.frame_irq = false;
+        status
+    }
+
+    /// Advances the APU by one CPU cycle.
+    pub fn clock(&mut self) {
+        self.triangle.clock_timer();
+        self.noise.clock_timer();
+        if self.odd_cycle {
+            self.pulse[0].clock_timer();
+            self.pulse[1].clock_timer();
+        }
+        self.odd_cycle = !self.odd_cycle;
+
+        self.frame_cycle += 1;
+        let last_step = if self.five_step { 4 } else { 3 };
+        match self.frame_steps.iter().position(|&cycle| cycle == self.frame_cycle) {
+            Some(0) | Some(2) => self.clock_quarter_frame(),
+            Some(1) => {
+                self.clock_quarter_frame();
+                self.clock_half_frame();
+            }
+            Some(step) if step == last_step => {
+                self.clock_quarter_frame();
+                self.clock_half_frame();
+                if !self.five_step && !self.irq_inhibit {
+                    self.frame_irq = true;
+                }
+                self.frame_cycle = 0;
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether the frame counter is asserting the CPU's IRQ line.
+    pub fn irq_pending(&self) -> bool {
+        self.frame_irq
+    }
+
+    /// The output level of each channel: pulse 1, pulse 2, triangle and noise
+    /// (0-15), then the delta modulation channel (0-127).
+    pub fn channel_levels(&self) -> [u8; 5] {
+        [
+            self.pulse[0].output(),
+            self.pulse[1].output(),
+            self.triangle.output(),
+            self.noise.output(),
+            self.dmc_level,
+        ]
+    }
+
+    /// Mixes the channels the way the 2A03's resistor network does.
+    ///
+    /// # Returns
+    ///
+    /// * The output level, from 0.0 to about 1.0.
+    pub fn output(&self) -> f32 {
+        let [pulse_1, pulse_2, triangle, noise, dmc] = self.channel_levels().map(f32::from);
+
+        let pulse = pulse_1 + pulse_2;
+        let pulse_out = if pulse == 0.0 { 0.0 } else { 95.88 / (8128.0 / pulse + 100.0) };
+
+        let tnd = triangle / 8227.0 + noise / 12241.0 + dmc / 22638.0;
+        let tnd_out = if tnd == 0.0 { 0.0 } else { 159.79 / (1.0 / tnd + 100.0) };
+
+        pulse_out + tnd_out
+    }
+
+    fn clock_quarter_frame(&mut self) {
+        self.pulse[0].envelope.clock();
+        self.pulse[1].envelope.clock();
+        self.noise.envelope.clock();
+        self.triangle.clock_linear();
+    }
+
+    fn clock_half_frame(&mut self) {
+        for pulse in &mut self.pulse {
+            pulse.length.clock();
+            pulse.clock_sweep();
+        }
+        self.triangle.length.clock();
+        self.noise.length.clock();
+    }
+}
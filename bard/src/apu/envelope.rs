@@ -0,0 +1,65 @@
+//! # envelope.rs
+//!
+//!  Author: Paul Hazen
+//! Created: 2026-10-19
+//! License: MIT (see LICENSE file)
+//!
+//! ## Description
+//! The volume envelope of the pulse and noise channels - either a constant
+//! volume or a decay from 15 to 0 that can loop.
+
+/* $4000 / $4004 / $400C - Envelope
+ * --- | Bit | Function
+ *     | 0-3 | Constant volume, or the decay period
+ *     |  4  | Constant volume
+ *     |  5  | Loop the decay (also halts the length counter)
+ */
+const ENVELOPE_PERIOD_MASK: u8 = 0b0000_1111;
+const ENVELOPE_CONSTANT: u8 = 0b0001_0000;
+const ENVELOPE_LOOP: u8 = 0b0010_0000;
+
+#[derive(Default)]
+pub struct Envelope {
+    period: u8,
+    constant: bool,
+    looping: bool,
+    start: bool,
+    divider: u8,
+    decay: u8,
+}
+
+impl Envelope {
+    pub fn write(&mut self, value: u8) {
+        self.period = value & ENVELOPE_PERIOD_MASK;
+        self.constant = value & ENVELOPE_CONSTANT != 0;
+        self.looping = value & ENVELOPE_LOOP != 0;
+    }
+
+    /// Restarts the decay, done when the channel's length is written.
+    pub fn restart(&mut self) {
+        self.start = true;
+    }
+
+    /// Clocked on quarter frames.
+    pub fn clock(&mut self) {
+        if self.start {
+            self.start = false;
+            self.decay = 15;
+            self.divider = self.period;
+        } else if self.divider > 0 {
+            self.divider -= 1;
+        } else {
+            self.divider = self.period;
+            if self.decay > 0 {
+                self.decay -= 1;
+            } else if self.looping {
+                self.decay = 15;
+            }
+        }
+    }
+
+    /// The current volume, 0-15.
+    pub fn volume(&self) -> u8 {
+        if self.constant { self.period } else { self.decay }
+    }
+}
@@ -0,0 +1,52 @@
+//! # length_counter.rs
+//!
+//!  Author: Paul Hazen
+//! Created: 2026-10-19
+//! License: MIT (see LICENSE file)
+//!
+//! ## Description
+//! The length counter shared by the pulse, triangle and noise channels. It
+//! silences a channel after a set number of half frames unless halted.
+
+/// Lengths selected by the upper five bits of a channel's fourth register.
+const LENGTH_TABLE: [u8; 32] = [
+    10, 254, 20, 2, 40, 4, 80, 6, 160, 8, 60, 10, 14, 12, 26, 14,
+    12, 16, 24, 18, 48, 20, 96, 22, 192, 24, 72, 26, 16, 28, 32, 30,
+];
+
+#[derive(Default)]
+pub struct LengthCounter {
+    counter: u8,
+    enabled: bool,
+    pub halted: bool,
+}
+
+impl LengthCounter {
+    /// Enables or disables the channel through $4015. Disabling clears the
+    /// counter straight away.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.counter = 0;
+        }
+    }
+
+    /// Loads the counter from the 5 bit index written to the channel.
+    pub fn load(&mut self, index: u8) {
+        if self.enabled {
+            self.counter = LENGTH_TABLE[(index & 0x1F) as usize];
+        }
+    }
+
+    /// Clocked on half frames.
+    pub fn clock(&mut self) {
+        if !self.halted && self.counter > 0 {
+            self.counter -= 1;
+        }
+    }
+
+    /// Whether the channel is still sounding.
+    pub fn is_active(&self) -> bool {
+        self.counter > 0
+    }
+}
@@ -0,0 +1,13 @@
+mod apu;
+mod envelope;
+mod length_counter;
+mod noise;
+mod pulse;
+mod triangle;
+
+pub use apu::Apu;
+use envelope::Envelope;
+use length_counter::LengthCounter;
+use noise::Noise;
+use pulse::Pulse;
+use triangle::Triangle;
@@ -0,0 +1,100 @@
+//! # noise.rs
+//!
+//!  Author: Paul Hazen
+//! Created: 2026-10-19
+//! License: MIT (see LICENSE file)
+//!
+//! ## Description
+//! The noise channel at $400C-$400F, a 15 bit linear feedback shift register
+//! clocked at one of 16 rates.
+use super::{Envelope, LengthCounter};
+use crate::region::Region;
+
+/// $400C bit 5 - halt the length counter.
+const HALT_LENGTH: u8 = 0b0010_0000;
+
+/* $400E - Mode and period
+ * --- | Bit | Function
+ *     | 0-3 | Period index
+ *     |  7  | Mode: take feedback from bit 6 for a short, metallic loop
+ */
+const PERIOD_MASK: u8 = 0b0000_1111;
+const MODE_SHORT: u8 = 0b1000_0000;
+
+/// Timer periods in CPU cycles (NTSC).
+const PERIOD_TABLE: [u16; 16] = [4, 8, 16, 32, 64, 96, 128, 160, 202, 254, 380, 508, 762, 1016, 2034, 4068];
+/// Timer periods in CPU cycles (PAL), shorter to suit the slower CPU.
+const PAL_PERIOD_TABLE: [u16; 16] = [4, 8, 14, 30, 60, 88, 118, 148, 188, 236, 354, 472, 708, 944, 1890, 3778];
+
+pub struct Noise {
+    pub envelope: Envelope,
+    pub length: LengthCounter,
+    short_mode: bool,
+    periods: &'static [u16; 16],
+    period: u16,
+    timer: u16,
+    shift_register: u16,
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Self {
+            envelope: Envelope::default(),
+            length: LengthCounter::default(),
+            short_mode: false,
+            periods: &PERIOD_TABLE,
+            period: PERIOD_TABLE[0],
+            timer: 0,
+            shift_register: 1, // Loaded with 1 at power on
+        }
+    }
+}
+
+impl Noise {
+    /// Chooses the period table of the console's region. The Dendy uses the
+    /// NTSC table.
+    pub fn set_region(&mut self, region: Region) {
+        self.periods = if region == Region::Pal { &PAL_PERIOD_TABLE } else { &PERIOD_TABLE };
+    }
+
+    /// Writes one of the channel's registers, `register` being 0-3.
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register {
+            0 => {
+                self.length.halted = value & HALT_LENGTH != 0;
+                self.envelope.write(value);
+            }
+            1 => {} // Unused
+            2 => {
+                self.short_mode = value & MODE_SHORT != 0;
+                self.period = self.periods[(value & PERIOD_MASK) as usize];
+            }
+            _ => {
+                self.length.load(value >> 3);
+                self.envelope.restart();
+            }
+        }
+    }
+
+    /// Clocked every CPU cycle.
+    pub fn clock_timer(&mut self) {
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+
+        self.timer = self.period - 1;
+        let tap = if self.short_mode { 6 } else { 1 };
+        let feedback = (self.shift_register ^ (self.shift_register >> tap)) & 0x01;
+        self.shift_register = (self.shift_register >> 1) | (feedback << 14);
+    }
+
+    /// The channel's output, 0-15.
+    pub fn output(&self) -> u8 {
+        if !self.length.is_active() || self.shift_register & 0x01 != 0 {
+            return 0;
+        }
+
+        self.envelope.volume()
+    }
+}
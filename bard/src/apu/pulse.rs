@@ -0,0 +1,156 @@
+//! # pulse.rs
+//!
+//!  Author: Paul Hazen
+//! Created: 2026-10-19
+//! License: MIT (see LICENSE file)
+//!
+//! ## Description
+//! The two pulse (square wave) channels at $4000-$4007. They differ only in
+//! how the sweep unit negates the period.
+use super::{Envelope, LengthCounter};
+
+/* $4000 / $4004 - Duty and envelope
+ * --- | Bit | Function
+ *     | 0-5 | Envelope (see `Envelope`)
+ *     |  5  | Halt the length counter
+ *     | 6-7 | Duty cycle
+ */
+const HALT_LENGTH: u8 = 0b0010_0000;
+const DUTY_SHIFT: u8 = 6;
+
+/* $4001 / $4005 - Sweep
+ * --- | Bit | Function
+ *     | 0-2 | Shift count
+ *     |  3  | Negate
+ *     | 4-6 | Divider period
+ *     |  7  | Enabled
+ */
+const SWEEP_SHIFT_MASK: u8 = 0b0000_0111;
+const SWEEP_NEGATE: u8 = 0b0000_1000;
+const SWEEP_PERIOD_MASK: u8 = 0b0111_0000;
+const SWEEP_ENABLED: u8 = 0b1000_0000;
+
+/// The 8 step waveforms selected by the duty bits.
+const DUTY_TABLE: [[u8; 8]; 4] = [
+    [0, 1, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 0, 0, 0, 0, 0],
+    [0, 1, 1, 1, 1, 0, 0, 0],
+    [1, 0, 0, 1, 1, 1, 1, 1],
+];
+
+/// Periods below this are too high pitched to play and mute the channel.
+const MIN_PERIOD: u16 = 8;
+const MAX_PERIOD: u16 = 0x7FF;
+
+pub struct Pulse {
+    /// Pulse 1 negates with the ones' complement, pulse 2 with the two's.
+    ones_complement: bool,
+    pub envelope: Envelope,
+    pub length: LengthCounter,
+    duty: u8,
+    step: u8,
+    period: u16,
+    timer: u16,
+    sweep_enabled: bool,
+    sweep_period: u8,
+    sweep_negate: bool,
+    sweep_shift: u8,
+    sweep_divider: u8,
+    sweep_reload: bool,
+}
+
+impl Pulse {
+    pub fn new(ones_complement: bool) -> Self {
+        Self {
+            ones_complement,
+            envelope: Envelope::default(),
+            length: LengthCounter::default(),
+            duty: 0,
+            step: 0,
+            period: 0,
+            timer: 0,
+            sweep_enabled: false,
+            sweep_period: 0,
+            sweep_negate: false,
+            sweep_shift: 0,
+            sweep_divider: 0,
+            sweep_reload: false,
+        }
+    }
+
+    /// Writes one of the channel's four registers, `register` being 0-3.
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register {
+            0 => {
+                self.duty = value >> DUTY_SHIFT;
+                self.length.halted = value & HALT_LENGTH != 0;
+                self.envelope.write(value);
+            }
+            1 => {
+                self.sweep_enabled = value & SWEEP_ENABLED != 0;
+                self.sweep_period = (value & SWEEP_PERIOD_MASK) >> 4;
+                self.sweep_negate = value & SWEEP_NEGATE != 0;
+                self.sweep_shift = value & SWEEP_SHIFT_MASK;
+                self.sweep_reload = true;
+            }
+            2 => self.period = (self.period & 0x0700) | value as u16,
+            _ => {
+                self.period = (self.period & 0x00FF) | ((value as u16 & 0x07) << 8);
+                self.length.load(value >> 3);
+                self.envelope.restart();
+                self.step = 0;
+            }
+        }
+    }
+
+    /// Clocked every other CPU cycle.
+    pub fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.period;
+            self.step = (self.step + 1) % 8;
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    /// Clocked on half frames.
+    pub fn clock_sweep(&mut self) {
+        let target = self.target_period();
+        if self.sweep_divider == 0 && self.sweep_enabled && self.sweep_shift > 0 && !self.is_muted(target) {
+            self.period = target;
+        }
+
+        if self.sweep_divider == 0 || self.sweep_reload {
+            self.sweep_divider = self.sweep_period;
+            self.sweep_reload = false;
+        } else {
+            self.sweep_divider -= 1;
+        }
+    }
+
+    /// The channel's output, 0-15.
+    pub fn output(&self) -> u8 {
+        if !self.length.is_active() || self.is_muted(self.target_period()) {
+            return 0;
+        }
+
+        DUTY_TABLE[self.duty as usize][self.step as usize] * self.envelope.volume()
+    }
+
+    fn target_period(&self) -> u16 {
+        let change = self.period >> self.sweep_shift;
+        if !self.sweep_negate {
+            self.period + change
+        } else if self.ones_complement {
+            self.period.saturating_sub(change + 1)
+        } else {
+            self.period.saturating_sub(change)
+        }
+    }
+
+    /// The sweep unit mutes the channel when the period is out of range, even
+    /// while sweeping is disabled.
+    fn is_muted(&self, target: u16) -> bool {
+        self.period < MIN_PERIOD || target > MAX_PERIOD
+    }
+}
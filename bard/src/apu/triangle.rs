@@ -0,0 +1,86 @@
+//! # triangle.rs
+//!
+//!  Author: Paul Hazen
+//! Created: 2026-10-19
+//! License: MIT (see LICENSE file)
+//!
+//! ## Description
+//! The triangle channel at $4008-$400B. It has no volume control, only a
+//! linear counter in addition to the length counter.
+use super::LengthCounter;
+
+/* $4008 - Linear counter
+ * --- | Bit | Function
+ *     | 0-6 | Reload value
+ *     |  7  | Control: keep reloading (also halts the length counter)
+ */
+const LINEAR_RELOAD_MASK: u8 = 0b0111_1111;
+const LINEAR_CONTROL: u8 = 0b1000_0000;
+
+const SEQUENCE: [u8; 32] = [
+    15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0,
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15,
+];
+
+#[derive(Default)]
+pub struct Triangle {
+    pub length: LengthCounter,
+    control: bool,
+    linear_reload_value: u8,
+    linear_counter: u8,
+    linear_reload: bool,
+    step: u8,
+    period: u16,
+    timer: u16,
+}
+
+impl Triangle {
+    /// Writes one of the channel's registers, `register` being 0-3.
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register {
+            0 => {
+                self.control = value & LINEAR_CONTROL != 0;
+                self.length.halted = self.control;
+                self.linear_reload_value = value & LINEAR_RELOAD_MASK;
+            }
+            1 => {} // Unused
+            2 => self.period = (self.period & 0x0700) | value as u16,
+            _ => {
+                self.period = (self.period & 0x00FF) | ((value as u16 & 0x07) << 8);
+                self.length.load(value >> 3);
+                self.linear_reload = true;
+            }
+        }
+    }
+
+    /// Clocked every CPU cycle. The sequence only moves while both counters
+    /// are running, which holds the output level when the channel stops.
+    pub fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.period;
+            if self.length.is_active() && self.linear_counter > 0 {
+                self.step = (self.step + 1) % 32;
+            }
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    /// Clocked on quarter frames.
+    pub fn clock_linear(&mut self) {
+        if self.linear_reload {
+            self.linear_counter = self.linear_reload_value;
+        } else if self.linear_counter > 0 {
+            self.linear_counter -= 1;
+        }
+
+        if !self.control {
+            self.linear_reload = false;
+        }
+    }
+
+    /// The channel's output, 0-15.
+    pub fn output(&self) -> u8 {
+        SEQUENCE[self.step as usize]
+    }
+}
@@ -0,0 +1,135 @@
+//! # archive.rs
+//!
+//!  Author: Paul Hazen
+//! Created: 2026-10-19
+//! License: MIT (see LICENSE file)
+//!
+//! ## Description
+//! Unpacks ROMs stored in .zip and .gz containers in memory. Containers are
+//! recognised by their magic bytes rather than the file extension.
+use std::io::{Cursor, Read};
+
+use flate2::read::GzDecoder;
+use zip::ZipArchive;
+
+use super::CartridgeError;
+
+const ZIP_MAGIC: [u8; 4] = [0x50, 0x4B, 0x03, 0x04];
+const ZIP_EMPTY_MAGIC: [u8; 4] = [0x50, 0x4B, 0x05, 0x06];
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+/// The most data unpacked from a container, well beyond any real ROM, so
+/// that a crafted size or a decompression bomb cannot exhaust memory.
+const MAX_UNPACKED_SIZE: u64 = 0x0800_0000;
+
+/// File extensions of the images the emulator can run.
+pub const ROM_EXTENSIONS: [&str; 4] = ["nes", "unf", "fds", "nsf"];
+
+/// Whether the data is a zip archive.
+pub fn is_zip(bytes: &[u8]) -> bool {
+    bytes.starts_with(&ZIP_MAGIC) || bytes.starts_with(&ZIP_EMPTY_MAGIC)
+}
+
+/// Whether the data is a gzip stream.
+pub fn is_gzip(bytes: &[u8]) -> bool {
+    bytes.starts_with(&GZIP_MAGIC)
+}
+
+/// Whether the data is stored in a container this module can unpack.
+pub fn is_archive(bytes: &[u8]) -> bool {
+    is_zip(bytes) || is_gzip(bytes)
+}
+
+/// Returns the ROM image held in the data.
+///
+/// # Arguments
+///
+/// * `bytes` - The contents of a ROM file, which may be zipped or gzipped.
+/// * `entry_name` - The zip entry to load. When `None` the archive must hold
+///   exactly one ROM.
+///
+/// # Returns
+///
+/// * `Ok(Vec<u8>)` with the unpacked ROM, or a copy of `bytes` if it is not
+///   stored in a container.
+/// * `Err(CartridgeError::NoRomInArchive)` if a zip holds no ROM.
+/// * `Err(CartridgeError::MultipleRomsInArchive)` if a zip holds several ROMs
+///   and no entry was named.
+/// * `Err(CartridgeError::CorruptArchive)` if the container cannot be read, or
+///   unpacks to more than any ROM could hold.
+pub fn unpack(bytes: &[u8], entry_name: Option<&str>) -> Result<Vec<u8>, CartridgeError> {
+    if is_zip(bytes) {
+        unpack_zip(bytes, entry_name)
+    } else if is_gzip(bytes) {
+        read_limited(GzDecoder::new(bytes))
+    } else {
+        Ok(bytes.to_vec())
+    }
+}
+
+/// Lists the ROM entries in a zip archive, in the order they are stored.
+pub fn rom_entries(bytes: &[u8]) -> Result<Vec<String>, CartridgeError> {
+    let mut archive = open_zip(bytes)?;
+    let mut entries = vec![];
+
+    for index in 0..archive.len() {
+        let entry = archive.by_index(index).map_err(corrupt)?;
+
+        if entry.is_file() && is_rom_name(entry.name()) {
+            entries.push(entry.name().to_string());
+        }
+    }
+
+    Ok(entries)
+}
+
+fn unpack_zip(bytes: &[u8], entry_name: Option<&str>) -> Result<Vec<u8>, CartridgeError> {
+    let name = match entry_name {
+        Some(name) => name.to_string(),
+        None => {
+            let mut entries = rom_entries(bytes)?;
+            match entries.len() {
+                0 => return Err(CartridgeError::NoRomInArchive),
+                1 => entries.remove(0),
+                _ => return Err(CartridgeError::MultipleRomsInArchive(entries)),
+            }
+        }
+    };
+
+    let mut archive = open_zip(bytes)?;
+    let mut entry = match archive.by_name(&name) {
+        Ok(entry) => entry,
+        Err(zip::result::ZipError::FileNotFound) => return Err(CartridgeError::NoRomInArchive),
+        Err(error) => return Err(corrupt(error)),
+    };
+
+    read_limited(&mut entry)
+}
+
+/// Reads an unpacked stream to its end, giving up once it is larger than any
+/// ROM. The buffer grows as data arrives rather than trusting a declared size.
+fn read_limited<R: Read>(reader: R) -> Result<Vec<u8>, CartridgeError> {
+    let mut data = vec![];
+    reader
+        .take(MAX_UNPACKED_SIZE + 1)
+        .read_to_end(&mut data)
+        .map_err(|error| CartridgeError::CorruptArchive(error.to_string()))?;
+
+    if data.len() as u64 > MAX_UNPACKED_SIZE {
+        return Err(CartridgeError::CorruptArchive("unpacks to more data than a ROM can hold".to_string()));
+    }
+
+    Ok(data)
+}
+
+fn open_zip(bytes: &[u8]) -> Result<ZipArchive<Cursor<&[u8]>>, CartridgeError> {
+    ZipArchive::new(Cursor::new(bytes)).map_err(corrupt)
+}
+
+fn corrupt(error: zip::result::ZipError) -> CartridgeError {
+    CartridgeError::CorruptArchive(error.to_string())
+}
+
+fn is_rom_name(name: &str) -> bool {
+    let extension = name.rsplit_once('.').map(|(_, extension)| extension.to_ascii_lowercase());
+    extension.is_some_and(|extension| ROM_EXTENSIONS.contains(&extension.as_str()))
+}
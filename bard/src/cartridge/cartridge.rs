@@ -0,0 +1,302 @@
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use super::{archive, fds, nsf, patch, unif, CartridgeError, CartridgeHeader, cartridge_header::{HEADER_SIZE, TRAINER_SIZE}};
+
+/// Represents an NES cartridge.
+#[derive(Clone)]
+pub struct Cartridge {
+    /// The parsed NES cartridge header containing metadata.
+    pub header: CartridgeHeader,
+    /// The program (PRG) ROM data, stored in a boxed slice.
+    pub prg_rom: Vec<u8>,
+    /// The character (CHR) ROM data, stored in a boxed slice.
+    pub chr_rom: Vec<u8>,
+    /// The 512 byte trainer loaded to $7000-$71FF, empty if the ROM has none.
+    pub trainer: Vec<u8>,
+    /// NES 2.0 miscellaneous ROM data stored after the CHR-ROM, empty if none.
+    pub misc_rom: Vec<u8>,
+    /// The disk sides of a Famicom Disk System image, `None` for cartridges.
+    pub disk: Option<fds::FdsImage>,
+    /// The header of an NSF or NSFe sound file, `None` for games.
+    pub nsf: Option<nsf::NsfHeader>,
+}
+
+impl Cartridge {
+    /// Loads an NES cartridge from a specified file path. Zipped and gzipped
+    /// ROMs are unpacked in memory, and an IPS, UPS or BPS patch with the same
+    /// name as the ROM is applied.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_path` - A string slice representing the path to the NES ROM file.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Cartridge)` if the file is successfully read and parsed.
+    /// * `Err(CartridgeError)` if the file cannot be read, is not an NES ROM,
+    ///   is shorter than the header says it should be, or cannot be patched.
+    pub fn load_from_file(file_path: &str) -> Result<Self, CartridgeError> {
+        let patch_path = patch::find_patch(Path::new(file_path));
+        Self::load(file_path, None, patch_path.as_deref())
+    }
+
+    /// Loads a named ROM from a zip archive holding several. A patch with the
+    /// same name as the archive is applied.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_path` - The path of the zip archive.
+    /// * `entry_name` - The name of the ROM within the archive.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Cartridge)` if the entry is successfully unpacked and parsed.
+    /// * `Err(CartridgeError)` if the archive or entry cannot be read.
+    pub fn load_archive_entry(file_path: &str, entry_name: &str) -> Result<Self, CartridgeError> {
+        let patch_path = patch::find_patch(Path::new(file_path));
+        Self::load(file_path, Some(entry_name), patch_path.as_deref())
+    }
+
+    /// Loads an NES cartridge, choosing the archive entry and patch explicitly.
+    /// No patch is looked for beside the ROM.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_path` - The path of the ROM or archive.
+    /// * `entry_name` - The ROM to load from a zip archive holding several.
+    /// * `patch_path` - An IPS, UPS or BPS patch to apply, if any.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Cartridge)` if the ROM is successfully read, patched and parsed.
+    /// * `Err(CartridgeError)` if any of the files cannot be read, the patch
+    ///   does not match the ROM, or the result is not a valid NES ROM.
+    pub fn load(file_path: &str, entry_name: Option<&str>, patch_path: Option<&Path>) -> Result<Self, CartridgeError> {
+        let bytes = fs::read(file_path)?;
+        let rom = archive::unpack(&bytes, entry_name)?;
+
+        match patch_path {
+            Some(patch_path) => Self::from_bytes_with_patch(&rom, &fs::read(patch_path)?),
+            None => Self::from_image(&rom),
+        }
+    }
+
+    /// Loads the FDS BIOS into the PRG-ROM of a disk image. Does nothing for
+    /// cartridges.
+    ///
+    /// # Arguments
+    ///
+    /// * `rom_path` - The path of the disk image, `disksys.rom` is looked for
+    ///   beside it.
+    /// * `bios_path` - The BIOS to use instead of searching for one.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` if the BIOS is loaded or the ROM is not a disk image.
+    /// * `Err(CartridgeError::MissingBios)` if no BIOS could be found.
+    /// * `Err(CartridgeError)` if the BIOS cannot be read or is too short.
+    pub fn load_bios(&mut self, rom_path: &str, bios_path: Option<&Path>) -> Result<(), CartridgeError> {
+        if self.disk.is_none() {
+            return Ok(());
+        }
+
+        let bios_path = fds::find_bios(Path::new(rom_path), bios_path).ok_or(CartridgeError::MissingBios)?;
+        self.prg_rom = fds::read_bios(&bios_path)?;
+        self.header.prg_rom_bytes = self.prg_rom.len();
+
+        Ok(())
+    }
+
+    /// Loads an NES cartridge from a ROM image held in memory. Zipped and
+    /// gzipped ROMs are unpacked.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The contents of an iNES / NES 2.0 file.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Cartridge)` if the data is successfully parsed.
+    /// * `Err(CartridgeError)` if the data is not an NES ROM or is truncated.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CartridgeError> {
+        if archive::is_archive(bytes) {
+            let rom = archive::unpack(bytes, None)?;
+            return Self::from_image(&rom);
+        }
+
+        Self::from_image(bytes)
+    }
+
+    /// Loads an NES cartridge from a ROM image and a patch held in memory.
+    /// The header is parsed from the patched image.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The contents of an iNES / NES 2.0 file, which may be zipped
+    ///   or gzipped.
+    /// * `patch` - The contents of an IPS, UPS or BPS file.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Cartridge)` if the ROM is successfully patched and parsed.
+    /// * `Err(CartridgeError)` if the patch is invalid or does not match the
+    ///   ROM, or the patched data is not an NES ROM.
+    pub fn from_bytes_with_patch(bytes: &[u8], patch: &[u8]) -> Result<Self, CartridgeError> {
+        let rom = archive::unpack(bytes, None)?;
+        let patched = patch::apply(&rom, patch)?;
+        Self::from_image(&patched)
+    }
+
+    /// Loads an NES cartridge from any source of bytes. Zipped and gzipped
+    /// ROMs are unpacked.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - A reader positioned at the start of the ROM.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Cartridge)` if the data is successfully read and parsed.
+    /// * `Err(CartridgeError)` if reading fails, the data is not an NES ROM,
+    ///   or it is shorter than the header says it should be.
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Self, CartridgeError> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes)?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Parses an uncompressed image, which may be iNES / NES 2.0, UNIF, FDS
+    /// or NSF.
+    fn from_image(bytes: &[u8]) -> Result<Self, CartridgeError> {
+        if unif::is_unif(bytes) {
+            return unif::parse(bytes);
+        }
+
+        if fds::is_fds(bytes) {
+            return fds::parse(bytes);
+        }
+
+        if nsf::is_nsf(bytes) {
+            return nsf::parse(bytes);
+        }
+
+        Self::parse(bytes)
+    }
+
+    /// Parses an uncompressed iNES / NES 2.0 image.
+    fn parse<R: Read>(mut reader: R) -> Result<Self, CartridgeError> {
+        // Read and validate the NES header
+        let header = Self::load_header(&mut reader)?;
+
+        // The trainer (if present) sits between the header and PRG ROM
+        let trainer = if header.has_trainer {
+            Self::read_section(&mut reader, TRAINER_SIZE, "trainer")?
+        } else {
+            vec![]
+        };
+
+        // Read PRG and CHR ROM data based on header values
+        let prg_rom = Self::read_section(&mut reader, header.prg_rom_bytes, "PRG-ROM")?;
+        let chr_rom = Self::read_section(&mut reader, header.chr_rom_bytes, "CHR-ROM")?;
+
+        // Miscellaneous ROMs take up the rest of the file
+        let mut misc_rom = vec![];
+        if header.misc_rom_count > 0 {
+            reader.read_to_end(&mut misc_rom)?;
+        }
+
+        // Return the loaded Cartridge
+        Ok(Self {
+            header,
+            prg_rom,
+            chr_rom,
+            trainer,
+            misc_rom,
+            disk: None,
+            nsf: None,
+        })
+    }
+
+    /// Reads the next `length` bytes of the file.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The reader positioned at the start of the section.
+    /// * `length` - The number of bytes the header says the section holds.
+    /// * `section` - The name of the section, used in the error.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<u8>)` containing the section's data.
+    /// * `Err(CartridgeError::Truncated)` if the file ends early.
+    fn read_section<R: Read>(reader: &mut R, length: usize, section: &'static str) -> Result<Vec<u8>, CartridgeError> {
+        // The buffer grows as data is read, so a header claiming more than
+        // the file holds cannot reserve memory for it
+        let mut data = Vec::new();
+        reader.take(length as u64).read_to_end(&mut data)?;
+
+        if data.len() < length {
+            return Err(CartridgeError::Truncated {
+                section,
+                expected: length,
+                found: data.len(),
+            });
+        }
+
+        Ok(data)
+    }
+
+    pub fn get_chr_rom(&self) -> Vec<u8> {
+        self.chr_rom.clone()
+    }
+
+    /// Reads a byte from PRG ROM
+    /// 
+    /// # Arguments
+    /// 
+    /// * `address` - The address from the cpu to read from the PRG-ROM
+    /// 
+    /// # Returns
+    /// 
+    /// * `u8` The byte at the given address.
+    pub fn read_prg_rom(&self, address: u16) -> u8 {
+        if address < 0x8000 {
+            return 0xFF; // Outside valid PRG-ROM range
+        }
+    
+        let prg_rom_size = self.prg_rom.len();
+        let prg_rom_offset = (address - 0x8000) as usize;
+    
+        if prg_rom_size == 0x4000 {
+            // 16KB ROM - Mirror it to $C000-$FFFF
+            self.prg_rom[prg_rom_offset % 0x4000]
+        } else if prg_rom_size == 0x8000 {
+            // 32KB ROM - Directly map it
+            self.prg_rom[prg_rom_offset]
+        } else {
+            0xFF // Invalid read
+        }
+    }
+
+    /// Reads and validates the NES file header.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The reader positioned at the start of the ROM.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(CartridgeHeader)` containing the metadata parsed from the iNES / NES 2.0 header.
+    /// * `Err(CartridgeError)` if the header is missing, short or invalid.
+    fn load_header<R: Read>(reader: &mut R) -> Result<CartridgeHeader, CartridgeError> {
+        let section = Self::read_section(reader, HEADER_SIZE, "header")?;
+
+        let mut buffer = [0u8; HEADER_SIZE];
+        buffer.copy_from_slice(&section);
+
+        // Validate NES header signature and extract header information
+        CartridgeHeader::parse(&buffer).ok_or(CartridgeError::InvalidHeader)
+    }
+}
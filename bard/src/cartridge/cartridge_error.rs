@@ -0,0 +1,96 @@
+//! # cartridge_error.rs
+//!
+//!  Author: Paul Hazen
+//! Created: 2026-10-19
+//! License: MIT (see LICENSE file)
+//!
+//! ## Description
+//! The ways loading a cartridge can fail.
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum CartridgeError {
+    /// The ROM could not be read.
+    Io(io::Error),
+    /// The data is not an iNES, NES 2.0 or UNIF image.
+    InvalidHeader,
+    /// The data ends before a section the header describes.
+    Truncated {
+        /// The section that was cut short ("header", "trainer", "PRG-ROM" or "CHR-ROM").
+        section: &'static str,
+        /// The number of bytes the header says the section holds.
+        expected: usize,
+        /// The number of bytes that were actually left.
+        found: usize,
+    },
+    /// The archive does not contain a .nes, .unf, .fds or .nsf file.
+    NoRomInArchive,
+    /// The archive contains several ROMs, one must be chosen by name.
+    MultipleRomsInArchive(Vec<String>),
+    /// The archive could not be unpacked.
+    CorruptArchive(String),
+    /// The patch is not a valid IPS, UPS or BPS file.
+    InvalidPatch(String),
+    /// A UPS or BPS checksum did not match.
+    PatchChecksumMismatch {
+        /// The checksum that failed ("source", "target" or "patch").
+        checksum: &'static str,
+        /// The CRC32 stored in the patch.
+        expected: u32,
+        /// The CRC32 that was calculated.
+        found: u32,
+    },
+    /// The ROM database is not a NES 2.0 or No-Intro XML file.
+    InvalidDatabase(String),
+    /// No mapper implements the UNIF board.
+    UnsupportedBoard(String),
+    /// No mapper implements the iNES mapper number.
+    UnsupportedMapper(u16),
+    /// A disk image needs the FDS BIOS, which could not be found.
+    MissingBios,
+}
+
+impl fmt::Display for CartridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CartridgeError::Io(error) => write!(f, "Could not read ROM: {}", error),
+            CartridgeError::InvalidHeader => write!(f, "Not a valid NES file."),
+            CartridgeError::Truncated { section, expected, found } => write!(
+                f,
+                "ROM file is truncated: header expects {} bytes of {}, but only {} remain.",
+                expected, section, found
+            ),
+            CartridgeError::NoRomInArchive => write!(f, "The archive does not contain a ROM."),
+            CartridgeError::MultipleRomsInArchive(entries) => {
+                write!(f, "The archive contains several ROMs: {}", entries.join(", "))
+            }
+            CartridgeError::CorruptArchive(reason) => write!(f, "The archive is corrupt: {}", reason),
+            CartridgeError::InvalidPatch(reason) => write!(f, "The patch is invalid: {}", reason),
+            CartridgeError::PatchChecksumMismatch { checksum, expected, found } => write!(
+                f,
+                "The patch's {} CRC32 does not match: expected {:08X}, found {:08X}.",
+                checksum, expected, found
+            ),
+            CartridgeError::InvalidDatabase(reason) => write!(f, "The ROM database is invalid: {}", reason),
+            CartridgeError::UnsupportedBoard(board) => write!(f, "The UNIF board {} is not supported.", board),
+            CartridgeError::UnsupportedMapper(mapper) => write!(f, "Mapper {} is not supported.", mapper),
+            CartridgeError::MissingBios => write!(f, "Disk images need the FDS BIOS (disksys.rom)."),
+        }
+    }
+}
+
+impl std::error::Error for CartridgeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CartridgeError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CartridgeError {
+    fn from(error: io::Error) -> Self {
+        CartridgeError::Io(error)
+    }
+}
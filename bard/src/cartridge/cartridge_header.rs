@@ -0,0 +1,422 @@
+//! # cartridge_header.rs
+//!
+//!  Author: Paul Hazen
+//! Created: 2026-10-19
+//! License: MIT (see LICENSE file)
+//!
+//! ## Description
+//! Parses the 16 byte header at the start of an iNES / NES 2.0 file into the
+//! metadata needed to build the board: ROM and RAM sizes, mapper, mirroring
+//! and the console the game was made for.
+
+/*
+    iNES / NES 2.0 Header Layout
+    ----------------------------------------------------------------
+    | Byte | Function                                              |
+    ----------------------------------------------------------------
+    |  0-3 | "NES" followed by $1A                                 |
+    |   4  | PRG-ROM size LSB (16 KB units)                        |
+    |   5  | CHR-ROM size LSB (8 KB units)                         |
+    |   6  | Flags 6 - mirroring, battery, trainer, mapper D3..D0  |
+    |   7  | Flags 7 - console type, NES 2.0 id, mapper D7..D4     |
+    |   8  | Mapper D11..D8, submapper (NES 2.0)                   |
+    |      | PRG-RAM size in 8 KB units (iNES)                     |
+    |   9  | PRG-/CHR-ROM size MSB (NES 2.0), TV system (iNES)     |
+    |  10  | PRG-RAM / PRG-NVRAM shift counts (NES 2.0)            |
+    |  11  | CHR-RAM / CHR-NVRAM shift counts (NES 2.0)            |
+    |  12  | CPU/PPU timing (NES 2.0)                              |
+    |  13  | Vs. System type / extended console type (NES 2.0)     |
+    |  14  | Miscellaneous ROM count (NES 2.0)                     |
+    |  15  | Default expansion device (NES 2.0)                    |
+    ----------------------------------------------------------------
+ */
+pub const HEADER_SIZE: usize = 16;
+pub const TRAINER_SIZE: usize = 512;
+const HEADER_MAGIC: [u8; 4] = [0x4E, 0x45, 0x53, 0x1A];
+
+/*
+    Flags 6
+    ----------------------------------------------------------------
+    | Bit | Function                                               |
+    ----------------------------------------------------------------
+    |  0  | Hard-wired mirroring (0 = horizontal, 1 = vertical)    |
+    |  1  | Battery backed PRG-RAM / other persistent memory       |
+    |  2  | 512 byte trainer at $7000-$71FF                        |
+    |  3  | Four-screen VRAM on the cartridge                      |
+    | 7-4 | Mapper number D3..D0                                   |
+    ----------------------------------------------------------------
+ */
+const FLAGS_6_VERTICAL_MIRRORING: u8 = 0b0000_0001;
+const FLAGS_6_BATTERY: u8 = 0b0000_0010;
+const FLAGS_6_TRAINER: u8 = 0b0000_0100;
+const FLAGS_6_FOUR_SCREEN: u8 = 0b0000_1000;
+
+/*
+    Flags 7
+    ----------------------------------------------------------------
+    | Bit | Function                                               |
+    ----------------------------------------------------------------
+    | 1-0 | Console type (NES, Vs. System, PlayChoice-10, ext.)    |
+    | 3-2 | Header format (%10 = NES 2.0)                          |
+    | 7-4 | Mapper number D7..D4                                   |
+    ----------------------------------------------------------------
+ */
+const FLAGS_7_CONSOLE_TYPE_MASK: u8 = 0b0000_0011;
+const FLAGS_7_FORMAT_MASK: u8 = 0b0000_1100;
+const FLAGS_7_FORMAT_NES_20: u8 = 0b0000_1000;
+
+pub const PRG_ROM_UNIT: usize = 16_384;
+pub const CHR_ROM_UNIT: usize = 8_192;
+/// The most units a NES 2.0 size count can give before the exponent form
+/// takes over. Larger sizes are not real ROMs.
+const MAX_ROM_UNITS: usize = 0x0EFF;
+const PRG_RAM_UNIT: usize = 8_192;
+const DEFAULT_CHR_RAM_SIZE: usize = 8_192;
+
+/// Describes how the four logical nametables are laid out over the PPU's
+/// nametable memory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mirroring {
+    /// $2000 = $2400 and $2800 = $2C00 (vertical arrangement, horizontal scrolling).
+    Horizontal,
+    /// $2000 = $2800 and $2400 = $2C00 (horizontal arrangement, vertical scrolling).
+    Vertical,
+    /// All four nametables map to the first page of VRAM.
+    SingleScreenA,
+    /// All four nametables map to the second page of VRAM.
+    SingleScreenB,
+    /// Each nametable has its own memory, supplied by the cartridge.
+    FourScreen,
+}
+
+/// Which revision of the header format the file was written with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeaderFormat {
+    /// An old header whose bytes 7-15 hold garbage (usually a ripper's
+    /// signature such as "DiskDude!"). Only the low nibble of the mapper
+    /// number can be trusted.
+    Archaic,
+    /// The original iNES format.
+    INes,
+    /// The NES 2.0 extension of the iNES format.
+    Nes20,
+    /// Built from the chunks of a UNIF file rather than read from a header.
+    Unif,
+    /// A Famicom Disk System image, run by the RAM adapter (mapper 20).
+    Fds,
+    /// An NSF or NSFe sound file, given a synthetic NSF mapper cartridge.
+    Nsf,
+}
+
+/// The kind of hardware the game was released for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConsoleType {
+    /// Nintendo Entertainment System / Famicom.
+    Nes,
+    /// Nintendo Vs. System arcade hardware.
+    VsSystem,
+    /// Nintendo PlayChoice-10 arcade hardware.
+    PlayChoice10,
+    /// One of the NES 2.0 extended console types (byte 13, bits 0-3).
+    Extended(u8),
+}
+
+/// The PPU fitted to a Vs. System board (NES 2.0 byte 13, bits 0-3). Each
+/// has its own RGB palette, and the RC2C05s also change the PPU registers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VsPpuType {
+    Rp2c03b,
+    Rp2c03g,
+    /// RP2C04-0001 to RP2C04-0004, which scramble the palette order.
+    Rp2c04(u8),
+    Rc2c03b,
+    Rc2c03c,
+    /// RC2C05-01 to RC2C05-05, which swap $2000 / $2001 and identify
+    /// themselves in the low bits of $2002.
+    Rc2c05(u8),
+}
+
+/// The Vs. System board variant (NES 2.0 byte 13, bits 4-7). Some games
+/// check for a protection chip on their board.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VsHardwareType {
+    Unisystem,
+    UnisystemRbiBaseball,
+    UnisystemTkoBoxing,
+    UnisystemSuperXevious,
+    UnisystemIceClimberJapan,
+    DualSystem,
+    DualSystemRaidOnBungelingBay,
+}
+
+/// The PPU and board of a Vs. System game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VsSystemType {
+    pub ppu: VsPpuType,
+    pub hardware: VsHardwareType,
+}
+
+impl VsSystemType {
+    /// Decodes NES 2.0 byte 13. Unknown values fall back to the RP2C03B and
+    /// the plain Unisystem.
+    pub fn from_byte(value: u8) -> Self {
+        let ppu = match value & 0x0F {
+            0x1 => VsPpuType::Rp2c03g,
+            0x2..=0x5 => VsPpuType::Rp2c04((value & 0x0F) - 1),
+            0x6 => VsPpuType::Rc2c03b,
+            0x7 => VsPpuType::Rc2c03c,
+            0x8..=0xC => VsPpuType::Rc2c05((value & 0x0F) - 7),
+            _ => VsPpuType::Rp2c03b,
+        };
+        let hardware = match value >> 4 {
+            1 => VsHardwareType::UnisystemRbiBaseball,
+            2 => VsHardwareType::UnisystemTkoBoxing,
+            3 => VsHardwareType::UnisystemSuperXevious,
+            4 => VsHardwareType::UnisystemIceClimberJapan,
+            5 => VsHardwareType::DualSystem,
+            6 => VsHardwareType::DualSystemRaidOnBungelingBay,
+            _ => VsHardwareType::Unisystem,
+        };
+
+        Self { ppu, hardware }
+    }
+}
+
+impl Default for VsSystemType {
+    fn default() -> Self {
+        Self::from_byte(0)
+    }
+}
+
+/// The CPU/PPU timing the game expects.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimingRegion {
+    /// RP2C02 ("NTSC NES").
+    Ntsc,
+    /// RP2C07 ("Licensed PAL NES").
+    Pal,
+    /// The game runs correctly on any region.
+    MultiRegion,
+    /// UA6538 ("Dendy").
+    Dendy,
+}
+
+/// Stores metadata from an NES cartridge header.
+#[derive(Clone)]
+pub struct CartridgeHeader {
+    /// The header revision the file was written with.
+    pub format: HeaderFormat,
+    /// The size of the PRG ROM in 16 KB units.
+    pub prg_rom_size: u16,
+    /// The size of the CHR ROM in 8 KB units.
+    pub chr_rom_size: u16,
+    /// The size of the PRG ROM in bytes.
+    pub prg_rom_bytes: usize,
+    /// The size of the CHR ROM in bytes.
+    pub chr_rom_bytes: usize,
+    /// The mapper number (0-4095).
+    pub mapper_id: u16,
+    /// The NES 2.0 submapper number, 0 when not specified.
+    pub submapper_id: u8,
+    /// The hard-wired nametable mirroring.
+    pub mirroring: Mirroring,
+    /// Whether the cartridge has battery backed memory.
+    pub has_battery: bool,
+    /// Whether a 512 byte trainer precedes the PRG ROM.
+    pub has_trainer: bool,
+    /// Volatile PRG-RAM size in bytes.
+    pub prg_ram_size: usize,
+    /// Non-volatile (battery backed) PRG-RAM size in bytes.
+    pub prg_nvram_size: usize,
+    /// Volatile CHR-RAM size in bytes.
+    pub chr_ram_size: usize,
+    /// Non-volatile (battery backed) CHR-RAM size in bytes.
+    pub chr_nvram_size: usize,
+    /// The hardware the game was released for.
+    pub console_type: ConsoleType,
+    /// The PPU and board of a Vs. System game, `None` for other consoles.
+    pub vs_system: Option<VsSystemType>,
+    /// The CPU/PPU timing the game expects.
+    pub timing: TimingRegion,
+    /// The NES 2.0 default expansion device id, 0 when unspecified.
+    pub expansion_device: u8,
+    /// The number of NES 2.0 miscellaneous ROMs stored after the CHR-ROM.
+    pub misc_rom_count: u8,
+    /// The board name from a UNIF file, `None` for iNES / NES 2.0 files.
+    pub board: Option<String>,
+    /// The raw contents of the header component of the cartridge.
+    pub buffer: Box<[u8]>,
+}
+
+impl CartridgeHeader {
+    /// Parses a 16 byte iNES or NES 2.0 header.
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer` - The first 16 bytes of the ROM file.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(CartridgeHeader)` if the buffer starts with the "NES\x1A" signature.
+    /// * `None` if the buffer is not an iNES header, or describes ROM sizes no cartridge could have.
+    pub fn parse(buffer: &[u8; HEADER_SIZE]) -> Option<Self> {
+        if !buffer.starts_with(&HEADER_MAGIC) {
+            return None;
+        }
+
+        let format = Self::detect_format(buffer);
+        let flags_6 = buffer[6];
+        let flags_7 = buffer[7];
+
+        let mirroring = if flags_6 & FLAGS_6_FOUR_SCREEN != 0 {
+            Mirroring::FourScreen
+        } else if flags_6 & FLAGS_6_VERTICAL_MIRRORING != 0 {
+            Mirroring::Vertical
+        } else {
+            Mirroring::Horizontal
+        };
+        let has_battery = flags_6 & FLAGS_6_BATTERY != 0;
+
+        let mut header = Self {
+            format,
+            prg_rom_size: buffer[4] as u16,
+            chr_rom_size: buffer[5] as u16,
+            prg_rom_bytes: buffer[4] as usize * PRG_ROM_UNIT,
+            chr_rom_bytes: buffer[5] as usize * CHR_ROM_UNIT,
+            mapper_id: (flags_6 >> 4) as u16,
+            submapper_id: 0,
+            mirroring,
+            has_battery,
+            has_trainer: flags_6 & FLAGS_6_TRAINER != 0,
+            prg_ram_size: 0,
+            prg_nvram_size: 0,
+            chr_ram_size: 0,
+            chr_nvram_size: 0,
+            console_type: ConsoleType::Nes,
+            vs_system: None,
+            timing: TimingRegion::Ntsc,
+            expansion_device: 0,
+            misc_rom_count: 0,
+            board: None,
+            buffer: Box::new(*buffer),
+        };
+
+        match format {
+            HeaderFormat::Nes20 => header.parse_nes_20(buffer)?,
+            HeaderFormat::INes => header.parse_ines(buffer),
+            HeaderFormat::Archaic | HeaderFormat::Unif | HeaderFormat::Fds | HeaderFormat::Nsf => header.apply_ines_ram_defaults(0),
+        }
+
+        if format != HeaderFormat::Archaic {
+            header.console_type = match flags_7 & FLAGS_7_CONSOLE_TYPE_MASK {
+                0 => ConsoleType::Nes,
+                1 => ConsoleType::VsSystem,
+                2 => ConsoleType::PlayChoice10,
+                _ if format == HeaderFormat::Nes20 => ConsoleType::Extended(buffer[13] & 0x0F),
+                _ => ConsoleType::Nes,
+            };
+        }
+
+        // iNES cannot say which PPU a Vs. System game needs
+        if header.console_type == ConsoleType::VsSystem {
+            header.vs_system = Some(if format == HeaderFormat::Nes20 {
+                VsSystemType::from_byte(buffer[13])
+            } else {
+                VsSystemType::default()
+            });
+        }
+
+        Some(header)
+    }
+
+    /// Works out which revision of the format wrote the header.
+    ///
+    /// Bits 2-3 of byte 7 are %10 for NES 2.0. Otherwise bytes 12-15 should be
+    /// zero - if they are not, some tool has written its name over the end of
+    /// the header and nothing from byte 7 onwards can be trusted.
+    fn detect_format(buffer: &[u8; HEADER_SIZE]) -> HeaderFormat {
+        match buffer[7] & FLAGS_7_FORMAT_MASK {
+            FLAGS_7_FORMAT_NES_20 => HeaderFormat::Nes20,
+            0 if buffer[12..16].iter().all(|&byte| byte == 0) => HeaderFormat::INes,
+            _ => HeaderFormat::Archaic,
+        }
+    }
+
+    fn parse_ines(&mut self, buffer: &[u8; HEADER_SIZE]) {
+        self.mapper_id |= (buffer[7] & 0xF0) as u16;
+
+        if buffer[9] & 0x01 != 0 {
+            self.timing = TimingRegion::Pal;
+        }
+
+        self.apply_ines_ram_defaults(buffer[8]);
+    }
+
+    /// iNES has no way to describe RAM precisely, so assume what most boards
+    /// carry: 8 KB of PRG-RAM (byte 8 when given), battery backed if flagged,
+    /// and 8 KB of CHR-RAM on boards without CHR-ROM.
+    pub(super) fn apply_ines_ram_defaults(&mut self, prg_ram_units: u8) {
+        let prg_ram_size = prg_ram_units.max(1) as usize * PRG_RAM_UNIT;
+        if self.has_battery {
+            self.prg_nvram_size = prg_ram_size;
+        } else {
+            self.prg_ram_size = prg_ram_size;
+        }
+
+        if self.chr_rom_bytes == 0 {
+            self.chr_ram_size = DEFAULT_CHR_RAM_SIZE;
+        }
+    }
+
+    fn parse_nes_20(&mut self, buffer: &[u8; HEADER_SIZE]) -> Option<()> {
+        self.mapper_id |= ((buffer[7] & 0xF0) as u16) | (((buffer[8] & 0x0F) as u16) << 8);
+        self.submapper_id = buffer[8] >> 4;
+
+        self.prg_rom_bytes = Self::nes_20_rom_size(buffer[4], buffer[9] & 0x0F, PRG_ROM_UNIT)?;
+        self.chr_rom_bytes = Self::nes_20_rom_size(buffer[5], buffer[9] >> 4, CHR_ROM_UNIT)?;
+        self.prg_rom_size = u16::try_from(self.prg_rom_bytes.div_ceil(PRG_ROM_UNIT)).ok()?;
+        self.chr_rom_size = u16::try_from(self.chr_rom_bytes.div_ceil(CHR_ROM_UNIT)).ok()?;
+
+        self.prg_ram_size = Self::nes_20_ram_size(buffer[10] & 0x0F);
+        self.prg_nvram_size = Self::nes_20_ram_size(buffer[10] >> 4);
+        self.chr_ram_size = Self::nes_20_ram_size(buffer[11] & 0x0F);
+        self.chr_nvram_size = Self::nes_20_ram_size(buffer[11] >> 4);
+
+        self.timing = match buffer[12] & 0x03 {
+            0 => TimingRegion::Ntsc,
+            1 => TimingRegion::Pal,
+            2 => TimingRegion::MultiRegion,
+            _ => TimingRegion::Dendy,
+        };
+
+        self.misc_rom_count = buffer[14] & 0x03;
+        self.expansion_device = buffer[15] & 0x3F;
+        Some(())
+    }
+
+    /// NES 2.0 ROM sizes are a 12 bit unit count, unless the most significant
+    /// nibble is $F, in which case the low byte holds an exponent-multiplier
+    /// pair: size = 2^E * (MM * 2 + 1) for EEEEEEMM. That can describe sizes
+    /// up to 2^63 * 7 bytes, so anything larger than the unit count could
+    /// give is rejected.
+    fn nes_20_rom_size(lsb: u8, msb: u8, unit: usize) -> Option<usize> {
+        let size = if msb == 0x0F {
+            let exponent = (lsb >> 2) as u32;
+            let multiplier = (lsb & 0x03) as usize * 2 + 1;
+            2usize.checked_pow(exponent)?.checked_mul(multiplier)?
+        } else {
+            (((msb as usize) << 8) | lsb as usize) * unit
+        };
+
+        (size <= MAX_ROM_UNITS * unit).then_some(size)
+    }
+
+    /// NES 2.0 RAM sizes are stored as shift counts: 64 << shift, 0 for none.
+    fn nes_20_ram_size(shift: u8) -> usize {
+        if shift == 0 {
+            0
+        } else {
+            64 << shift
+        }
+    }
+}
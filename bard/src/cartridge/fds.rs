@@ -0,0 +1,160 @@
+//! # fds.rs
+//!
+//!  Author: Paul Hazen
+//! Created: 2026-10-19
+//! License: MIT (see LICENSE file)
+//!
+//! ## Description
+//! Loads Famicom Disk System images (`.fds`). The disk sides are kept on the
+//! `Cartridge` and the RAM adapter is presented as mapper 20. The adapter runs
+//! Nintendo's BIOS (`disksys.rom`), which the user has to supply.
+
+/*
+    FDS Image Layout
+    ----------------------------------------------------------------
+    | Offset | Function                                            |
+    ----------------------------------------------------------------
+    |  0-15  | Optional fwNES header: "FDS" $1A, side count, zeros |
+    |   ...  | Disk sides, 65500 bytes each                        |
+    ----------------------------------------------------------------
+
+    Each side is a list of blocks without the gaps and CRCs found on a real
+    disk. The first byte of every block gives its type.
+    ----------------------------------------------------------------
+    | Type | Length   | Function                                   |
+    ----------------------------------------------------------------
+    |  1   | 56       | Disk info, starts "*NINTENDO-HVC*"         |
+    |  2   | 2        | File count                                 |
+    |  3   | 16       | File header, file size at bytes 13-14      |
+    |  4   | 1 + size | File data                                  |
+    ----------------------------------------------------------------
+ */
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::cartridge_header::HEADER_SIZE;
+use super::{Cartridge, CartridgeError, CartridgeHeader, ConsoleType, HeaderFormat, Mirroring, TimingRegion};
+
+/// The number of bytes in one side of an `.fds` image.
+pub const SIDE_SIZE: usize = 65_500;
+/// The size of the RAM adapter's BIOS ROM, mapped at $E000-$FFFF.
+pub const BIOS_SIZE: usize = 0x2000;
+/// The name the BIOS is looked for under when no path is given.
+pub const BIOS_FILE_NAME: &str = "disksys.rom";
+/// The mapper number NES 2.0 assigns to the Famicom Disk System.
+pub const FDS_MAPPER: u16 = 20;
+
+const FWNES_MAGIC: &[u8] = b"FDS\x1A";
+const DISK_INFO_MAGIC: &[u8] = b"\x01*NINTENDO-HVC*";
+
+const PRG_RAM_SIZE: usize = 0x8000;
+const CHR_RAM_SIZE: usize = 0x2000;
+
+/// The sides of a disk image, in the order they appear in the file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FdsImage {
+    pub sides: Vec<Vec<u8>>,
+}
+
+impl FdsImage {
+    /// Splits an `.fds` file into disk sides.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The contents of the image, with or without the fwNES header.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(FdsImage)` if every side starts with a disk info block.
+    /// * `Err(CartridgeError::Truncated)` if the image is shorter than a side.
+    /// * `Err(CartridgeError::InvalidHeader)` if a side is not an FDS disk.
+    pub fn parse(bytes: &[u8]) -> Result<Self, CartridgeError> {
+        let data = if bytes.starts_with(FWNES_MAGIC) { &bytes[HEADER_SIZE.min(bytes.len())..] } else { bytes };
+
+        if data.len() < SIDE_SIZE {
+            return Err(CartridgeError::Truncated { section: "disk side", expected: SIDE_SIZE, found: data.len() });
+        }
+
+        let sides: Vec<Vec<u8>> = data.chunks_exact(SIDE_SIZE).map(<[u8]>::to_vec).collect();
+        if !sides.iter().all(|side| side.starts_with(DISK_INFO_MAGIC)) {
+            return Err(CartridgeError::InvalidHeader);
+        }
+
+        Ok(Self { sides })
+    }
+}
+
+/// Whether the data is an `.fds` image.
+pub fn is_fds(bytes: &[u8]) -> bool {
+    bytes.starts_with(FWNES_MAGIC) || bytes.starts_with(DISK_INFO_MAGIC)
+}
+
+/// Builds a cartridge for a disk image. The PRG-ROM stays empty until the
+/// BIOS is loaded with `Cartridge::load_bios`.
+pub fn parse(bytes: &[u8]) -> Result<Cartridge, CartridgeError> {
+    let image = FdsImage::parse(bytes)?;
+
+    let mut buffer = [0; HEADER_SIZE];
+    let length = bytes.len().min(HEADER_SIZE);
+    buffer[..length].copy_from_slice(&bytes[..length]);
+
+    let header = CartridgeHeader {
+        format: HeaderFormat::Fds,
+        prg_rom_size: 0,
+        chr_rom_size: 0,
+        prg_rom_bytes: 0,
+        chr_rom_bytes: 0,
+        mapper_id: FDS_MAPPER,
+        submapper_id: 0,
+        mirroring: Mirroring::Horizontal, // Selected through $4025 at run time
+        // Writes to the disk are kept in the save file like battery backed RAM
+        has_battery: true,
+        has_trainer: false,
+        prg_ram_size: PRG_RAM_SIZE,
+        prg_nvram_size: 0,
+        chr_ram_size: CHR_RAM_SIZE,
+        chr_nvram_size: 0,
+        console_type: ConsoleType::Nes,
+        vs_system: None,
+        timing: TimingRegion::Ntsc,
+        expansion_device: 0,
+        misc_rom_count: 0,
+        board: None,
+        buffer: Box::new(buffer),
+    };
+
+    Ok(Cartridge {
+        header,
+        prg_rom: vec![],
+        chr_rom: vec![],
+        trainer: vec![],
+        misc_rom: vec![],
+        disk: Some(image),
+        nsf: None,
+    })
+}
+
+/// Finds the BIOS for a disk image: the given path, or else `disksys.rom`
+/// beside the image or in the working directory.
+pub fn find_bios(rom_path: &Path, bios_path: Option<&Path>) -> Option<PathBuf> {
+    if let Some(path) = bios_path {
+        return Some(path.to_path_buf());
+    }
+
+    let beside_rom = rom_path.parent().map(|directory| directory.join(BIOS_FILE_NAME));
+    beside_rom
+        .into_iter()
+        .chain(std::iter::once(PathBuf::from(BIOS_FILE_NAME)))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Reads a BIOS image, which must be at least 8 KB.
+pub fn read_bios(path: &Path) -> Result<Vec<u8>, CartridgeError> {
+    let mut bios = fs::read(path)?;
+    if bios.len() < BIOS_SIZE {
+        return Err(CartridgeError::Truncated { section: "BIOS", expected: BIOS_SIZE, found: bios.len() });
+    }
+
+    bios.truncate(BIOS_SIZE);
+    Ok(bios)
+}
@@ -0,0 +1,16 @@
+pub mod archive;
+mod cartridge;
+mod cartridge_error;
+mod cartridge_header;
+pub mod fds;
+pub mod nsf;
+pub mod patch;
+mod rom_database;
+mod save_file;
+pub mod unif;
+
+pub use cartridge::Cartridge;
+pub use cartridge_error::CartridgeError;
+pub use cartridge_header::{CartridgeHeader, ConsoleType, HeaderFormat, Mirroring, TimingRegion, VsHardwareType, VsPpuType, VsSystemType};
+pub use rom_database::{HeaderCorrection, HeaderOverrides, RomDatabase, RomEntry, RomHashes};
+pub use save_file::SaveFile;
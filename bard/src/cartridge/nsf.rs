@@ -0,0 +1,359 @@
+//! # nsf.rs
+//!
+//!  Author: Paul Hazen
+//! Created: 2026-10-19
+//! License: MIT (see LICENSE file)
+//!
+//! ## Description
+//! Loads NES Sound Format (`.nsf`) and extended NSF (`.nsfe`) files. A sound
+//! file holds a game's music driver and data without the rest of the game, so
+//! it is turned into a synthetic cartridge: the program data is laid out in
+//! 4 KB banks for the NSF mapper (mapper 31), and the init / play addresses are
+//! kept for the player that drives them.
+
+/*
+    NSF Header
+    ----------------------------------------------------------------
+    | Offset | Function                                            |
+    ----------------------------------------------------------------
+    |  0-4   | "NESM" $1A                                          |
+    |   5    | Version                                             |
+    |   6    | Number of songs                                     |
+    |   7    | Starting song (1 based)                             |
+    |  8-9   | Load address                                        |
+    |  A-B   | Init address                                        |
+    |  C-D   | Play address                                        |
+    | E-2D   | Title, NUL padded                                   |
+    | 2E-4D  | Artist, NUL padded                                  |
+    | 4E-6D  | Copyright, NUL padded                               |
+    | 6E-6F  | NTSC play speed in microseconds                     |
+    | 70-77  | Initial banks for $8000-$FFFF, all 0 if unbanked    |
+    | 78-79  | PAL play speed in microseconds                      |
+    |   7A   | Region: bit 0 PAL, bit 1 dual                       |
+    |   7B   | Expansion chips (see `ExpansionChips`)              |
+    | 7C-7F  | NSF2 flags and program length, ignored              |
+    ----------------------------------------------------------------
+
+    NSFe Chunks ("NSFE", then 4 byte little endian length, 4 byte ID, data)
+    ----------------------------------------------------------------
+    | ID   | Function                                              |
+    ----------------------------------------------------------------
+    | INFO | Load, init, play, region, chips, songs, starting song |
+    | DATA | Program data                                          |
+    | BANK | Initial banks                                         |
+    | RATE | NTSC and PAL play speeds                              |
+    | auth | Title, artist, copyright and ripper, NUL terminated   |
+    | tlbl | Track titles, NUL terminated                          |
+    | NEND | End of file                                           |
+    ----------------------------------------------------------------
+    Chunks whose ID starts with a capital letter must be understood to play
+    the file, the rest can be skipped.
+ */
+use bitflags::bitflags;
+
+use super::cartridge_header::{HEADER_SIZE, PRG_ROM_UNIT};
+use super::{Cartridge, CartridgeError, CartridgeHeader, ConsoleType, HeaderFormat, Mirroring, TimingRegion};
+
+/// The mapper the synthetic cartridge is given. Mapper 31 is the iNES
+/// assignment for NSF style bank switching.
+pub const NSF_MAPPER: u16 = 31;
+/// The size of one bank of program data.
+pub const BANK_SIZE: usize = 0x1000;
+
+const NSF_MAGIC: &[u8] = b"NESM\x1A";
+const NSFE_MAGIC: &[u8] = b"NSFE";
+const NSF_HEADER_SIZE: usize = 0x80;
+const CHUNK_HEADER_SIZE: usize = 8;
+const TEXT_FIELD_SIZE: usize = 32;
+
+/// Play speeds used when a file gives none, about 60 and 50 Hz.
+const DEFAULT_NTSC_PLAY_SPEED: u16 = 16_639;
+const DEFAULT_PAL_PLAY_SPEED: u16 = 19_997;
+
+/* Region byte
+ * --- | Bit | Function
+ *     |  0  | PAL tune
+ *     |  1  | Plays on both NTSC and PAL
+ */
+const REGION_PAL: u8 = 0b0000_0001;
+const REGION_DUAL: u8 = 0b0000_0010;
+
+const PRG_RAM_SIZE: usize = 0x2000;
+const CHR_RAM_SIZE: usize = 0x2000;
+
+bitflags! {
+    /// The expansion sound chips a tune was written for.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct ExpansionChips: u8 {
+        const VRC6 = 1 << 0;
+        const VRC7 = 1 << 1;
+        /// Also moves program data into RAM from $6000 to $DFFF.
+        const FDS = 1 << 2;
+        /// Also adds 1 KB of ExRAM at $5C00-$5FF5.
+        const MMC5 = 1 << 3;
+        const NAMCO_163 = 1 << 4;
+        const SUNSOFT_5B = 1 << 5;
+    }
+}
+
+/// What the player needs to know about a sound file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NsfHeader {
+    pub song_count: u8,
+    /// The song played first, counting from 1.
+    pub starting_song: u8,
+    pub load_address: u16,
+    pub init_address: u16,
+    pub play_address: u16,
+    pub title: String,
+    pub artist: String,
+    pub copyright: String,
+    /// Microseconds between calls to the play routine on NTSC.
+    pub ntsc_play_speed: u16,
+    /// Microseconds between calls to the play routine on PAL.
+    pub pal_play_speed: u16,
+    /// The banks selected at $5FF8-$5FFF before a song starts.
+    pub banks: [u8; 8],
+    pub expansion_chips: ExpansionChips,
+    /// Titles of the songs, empty when the file does not name them.
+    pub track_titles: Vec<String>,
+}
+
+impl NsfHeader {
+    /// Whether the tune switches banks. Tunes that do not are loaded at their
+    /// load address instead.
+    pub fn is_bank_switched(&self) -> bool {
+        self.banks.iter().any(|&bank| bank != 0)
+    }
+
+    /// The address the program data is laid out from when the tune does not
+    /// switch banks.
+    pub fn image_base(&self) -> u16 {
+        if self.expansion_chips.contains(ExpansionChips::FDS) { 0x6000 } else { 0x8000 }
+    }
+}
+
+/// Whether the data is an NSF or NSFe file.
+pub fn is_nsf(bytes: &[u8]) -> bool {
+    bytes.starts_with(NSF_MAGIC) || bytes.starts_with(NSFE_MAGIC)
+}
+
+/// Parses a sound file into a synthetic cartridge.
+///
+/// # Arguments
+///
+/// * `bytes` - The contents of an `.nsf` or `.nsfe` file.
+///
+/// # Returns
+///
+/// * `Ok(Cartridge)` with `nsf` set if the file is successfully parsed.
+/// * `Err(CartridgeError::Truncated)` if the header or a chunk is cut short.
+/// * `Err(CartridgeError::InvalidHeader)` if the file has no songs, loads
+///   below its program area or needs a chunk that is not understood.
+pub fn parse(bytes: &[u8]) -> Result<Cartridge, CartridgeError> {
+    let (nsf, data, timing) = if bytes.starts_with(NSFE_MAGIC) { parse_nsfe(bytes)? } else { parse_nsf(bytes)? };
+
+    if nsf.song_count == 0 || data.is_empty() {
+        return Err(CartridgeError::InvalidHeader);
+    }
+    if !nsf.is_bank_switched() && nsf.load_address < nsf.image_base() {
+        return Err(CartridgeError::InvalidHeader);
+    }
+
+    let prg_rom = layout_program(&nsf, data);
+
+    let mut buffer = [0; HEADER_SIZE];
+    let length = bytes.len().min(HEADER_SIZE);
+    buffer[..length].copy_from_slice(&bytes[..length]);
+
+    let header = CartridgeHeader {
+        format: HeaderFormat::Nsf,
+        prg_rom_size: prg_rom.len().div_ceil(PRG_ROM_UNIT) as u16,
+        chr_rom_size: 0,
+        prg_rom_bytes: prg_rom.len(),
+        chr_rom_bytes: 0,
+        mapper_id: NSF_MAPPER,
+        submapper_id: 0,
+        mirroring: Mirroring::Horizontal,
+        has_battery: false,
+        has_trainer: false,
+        prg_ram_size: PRG_RAM_SIZE,
+        prg_nvram_size: 0,
+        chr_ram_size: CHR_RAM_SIZE,
+        chr_nvram_size: 0,
+        console_type: ConsoleType::Nes,
+        vs_system: None,
+        timing,
+        expansion_device: 0,
+        misc_rom_count: 0,
+        board: None,
+        buffer: Box::new(buffer),
+    };
+
+    Ok(Cartridge {
+        header,
+        prg_rom,
+        chr_rom: vec![],
+        trainer: vec![],
+        misc_rom: vec![],
+        disk: None,
+        nsf: Some(nsf),
+    })
+}
+
+/// Reads the fixed 128 byte header of an `.nsf` file.
+fn parse_nsf(bytes: &[u8]) -> Result<(NsfHeader, &[u8], TimingRegion), CartridgeError> {
+    if bytes.len() < NSF_HEADER_SIZE {
+        return Err(CartridgeError::Truncated { section: "header", expected: NSF_HEADER_SIZE, found: bytes.len() });
+    }
+
+    let mut banks = [0; 8];
+    banks.copy_from_slice(&bytes[0x70..0x78]);
+
+    let nsf = NsfHeader {
+        song_count: bytes[0x06],
+        starting_song: bytes[0x07].max(1),
+        load_address: word(bytes, 0x08),
+        init_address: word(bytes, 0x0A),
+        play_address: word(bytes, 0x0C),
+        title: text(&bytes[0x0E..0x0E + TEXT_FIELD_SIZE]),
+        artist: text(&bytes[0x2E..0x2E + TEXT_FIELD_SIZE]),
+        copyright: text(&bytes[0x4E..0x4E + TEXT_FIELD_SIZE]),
+        ntsc_play_speed: play_speed(word(bytes, 0x6E), DEFAULT_NTSC_PLAY_SPEED),
+        pal_play_speed: play_speed(word(bytes, 0x78), DEFAULT_PAL_PLAY_SPEED),
+        banks,
+        expansion_chips: ExpansionChips::from_bits_truncate(bytes[0x7B]),
+        track_titles: vec![],
+    };
+
+    Ok((nsf, &bytes[NSF_HEADER_SIZE..], region_timing(bytes[0x7A])))
+}
+
+/// Reads the chunks of an `.nsfe` file.
+fn parse_nsfe(bytes: &[u8]) -> Result<(NsfHeader, &[u8], TimingRegion), CartridgeError> {
+    let mut nsf = NsfHeader {
+        song_count: 1,
+        starting_song: 1,
+        load_address: 0,
+        init_address: 0,
+        play_address: 0,
+        title: String::new(),
+        artist: String::new(),
+        copyright: String::new(),
+        ntsc_play_speed: DEFAULT_NTSC_PLAY_SPEED,
+        pal_play_speed: DEFAULT_PAL_PLAY_SPEED,
+        banks: [0; 8],
+        expansion_chips: ExpansionChips::empty(),
+        track_titles: vec![],
+    };
+    let mut has_info = false;
+    let mut data: &[u8] = &[];
+    let mut timing = TimingRegion::Ntsc;
+
+    let mut position = NSFE_MAGIC.len();
+    while bytes.len() - position >= CHUNK_HEADER_SIZE {
+        let length = u32::from_le_bytes([bytes[position], bytes[position + 1], bytes[position + 2], bytes[position + 3]]) as usize;
+        let id = &bytes[position + 4..position + 8];
+        position += CHUNK_HEADER_SIZE;
+
+        let found = bytes.len() - position;
+        if length > found {
+            return Err(CartridgeError::Truncated { section: "NSFe chunk", expected: length, found });
+        }
+        let chunk = &bytes[position..position + length];
+        position += length;
+
+        match id {
+            b"INFO" => {
+                if chunk.len() < 8 {
+                    return Err(CartridgeError::Truncated { section: "NSFe INFO", expected: 8, found: chunk.len() });
+                }
+                nsf.load_address = word(chunk, 0);
+                nsf.init_address = word(chunk, 2);
+                nsf.play_address = word(chunk, 4);
+                timing = region_timing(chunk[6]);
+                nsf.expansion_chips = ExpansionChips::from_bits_truncate(chunk[7]);
+                if let Some(&count) = chunk.get(8) {
+                    nsf.song_count = count;
+                }
+                // Unlike NSF, the starting song is counted from 0
+                if let Some(&song) = chunk.get(9) {
+                    nsf.starting_song = song.saturating_add(1);
+                }
+                has_info = true;
+            }
+            b"DATA" => data = chunk,
+            b"BANK" => {
+                let length = chunk.len().min(nsf.banks.len());
+                nsf.banks[..length].copy_from_slice(&chunk[..length]);
+            }
+            b"RATE" => {
+                if chunk.len() >= 2 {
+                    nsf.ntsc_play_speed = play_speed(word(chunk, 0), DEFAULT_NTSC_PLAY_SPEED);
+                }
+                if chunk.len() >= 4 {
+                    nsf.pal_play_speed = play_speed(word(chunk, 2), DEFAULT_PAL_PLAY_SPEED);
+                }
+            }
+            b"auth" => {
+                let mut fields = chunk.split(|&byte| byte == 0).map(text);
+                nsf.title = fields.next().unwrap_or_default();
+                nsf.artist = fields.next().unwrap_or_default();
+                nsf.copyright = fields.next().unwrap_or_default();
+            }
+            b"tlbl" => {
+                nsf.track_titles = chunk.split(|&byte| byte == 0).map(text).take(nsf.song_count as usize).collect();
+            }
+            b"NEND" => break,
+            _ if id[0].is_ascii_uppercase() => return Err(CartridgeError::InvalidHeader),
+            _ => {} // Optional chunks (plst, time, fade, text) are not used
+        }
+    }
+
+    if !has_info {
+        return Err(CartridgeError::InvalidHeader);
+    }
+
+    Ok((nsf, data, timing))
+}
+
+/// Lays the program data out in 4 KB banks. Bank switched tunes are padded so
+/// that the load address falls at the same offset within its bank; other
+/// tunes are placed at their load address in an image starting at $8000
+/// ($6000 for FDS tunes).
+fn layout_program(nsf: &NsfHeader, data: &[u8]) -> Vec<u8> {
+    let padding = if nsf.is_bank_switched() {
+        nsf.load_address as usize % BANK_SIZE
+    } else {
+        (nsf.load_address - nsf.image_base()) as usize
+    };
+
+    let mut prg_rom = vec![0; padding];
+    prg_rom.extend_from_slice(data);
+    prg_rom.resize(prg_rom.len().div_ceil(BANK_SIZE) * BANK_SIZE, 0);
+    prg_rom
+}
+
+fn region_timing(region: u8) -> TimingRegion {
+    if region & REGION_DUAL != 0 {
+        TimingRegion::MultiRegion
+    } else if region & REGION_PAL != 0 {
+        TimingRegion::Pal
+    } else {
+        TimingRegion::Ntsc
+    }
+}
+
+fn play_speed(speed: u16, default: u16) -> u16 {
+    if speed == 0 { default } else { speed }
+}
+
+fn word(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+/// Decodes a NUL padded text field.
+fn text(bytes: &[u8]) -> String {
+    let text = bytes.split(|&byte| byte == 0).next().unwrap_or_default();
+    String::from_utf8_lossy(text).trim().to_string()
+}
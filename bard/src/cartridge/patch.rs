@@ -0,0 +1,474 @@
+//! # patch.rs
+//!
+//!  Author: Paul Hazen
+//! Created: 2026-10-19
+//! License: MIT (see LICENSE file)
+//!
+//! ## Description
+//! Applies and creates IPS, UPS and BPS soft patches. Patches are applied to
+//! the whole ROM file (header included) before it is parsed, so a patch that
+//! changes the header is honoured.
+use std::path::{Path, PathBuf};
+
+use super::CartridgeError;
+
+const IPS_MAGIC: &[u8] = b"PATCH";
+const IPS_EOF: &[u8] = b"EOF";
+const IPS_MAX_SIZE: usize = 0x0100_0000;
+const IPS_MAX_RECORD: usize = 0xFFFF;
+/// A record starting here would be read back as the "EOF" marker.
+const IPS_EOF_OFFSET: usize = 0x454F46;
+
+const UPS_MAGIC: &[u8] = b"UPS1";
+const BPS_MAGIC: &[u8] = b"BPS1";
+/// Source, target and patch CRC32s close every UPS and BPS file.
+const FOOTER_SIZE: usize = 12;
+/// The largest target a UPS or BPS patch may declare, well beyond any real
+/// ROM, so that a corrupt size cannot exhaust memory.
+const MAX_TARGET_SIZE: usize = 0x0800_0000;
+
+/* BPS actions
+ * --- | Bits | Action
+ *     | 0-1  | Command (SourceRead, TargetRead, SourceCopy, TargetCopy)
+ *     | 2-   | Length - 1
+ */
+const BPS_SOURCE_READ: usize = 0;
+const BPS_TARGET_READ: usize = 1;
+const BPS_SOURCE_COPY: usize = 2;
+const BPS_TARGET_COPY: usize = 3;
+const BPS_COMMAND_MASK: usize = 0x03;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PatchFormat {
+    Ips,
+    Ups,
+    Bps,
+}
+
+impl PatchFormat {
+    /// Every format, in the order patches beside a ROM are looked for.
+    pub const ALL: [PatchFormat; 3] = [PatchFormat::Ips, PatchFormat::Ups, PatchFormat::Bps];
+
+    /// Identifies a patch by its magic bytes.
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        Self::ALL.into_iter().find(|format| bytes.starts_with(format.magic()))
+    }
+
+    /// Identifies a patch format by a file's extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        Self::ALL.into_iter().find(|format| format.extension() == extension)
+    }
+
+    /// The file extension used by patches of this format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            PatchFormat::Ips => "ips",
+            PatchFormat::Ups => "ups",
+            PatchFormat::Bps => "bps",
+        }
+    }
+
+    fn magic(&self) -> &'static [u8] {
+        match self {
+            PatchFormat::Ips => IPS_MAGIC,
+            PatchFormat::Ups => UPS_MAGIC,
+            PatchFormat::Bps => BPS_MAGIC,
+        }
+    }
+}
+
+/// Looks for a patch with the same name as the ROM, e.g. `game.ips` beside
+/// `game.nes`.
+///
+/// # Arguments
+///
+/// * `rom_path` - The path of the ROM file.
+///
+/// # Returns
+///
+/// * `Some(PathBuf)` with the first IPS, UPS or BPS file found.
+/// * `None` if the ROM has no patch beside it.
+pub fn find_patch(rom_path: &Path) -> Option<PathBuf> {
+    PatchFormat::ALL
+        .into_iter()
+        .map(|format| rom_path.with_extension(format.extension()))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Applies a patch to a ROM, detecting the format from the patch's magic bytes.
+///
+/// # Arguments
+///
+/// * `rom` - The unpatched ROM file.
+/// * `patch` - The contents of an IPS, UPS or BPS file.
+///
+/// # Returns
+///
+/// * `Ok(Vec<u8>)` containing the patched ROM file.
+/// * `Err(CartridgeError::InvalidPatch)` if the patch cannot be read.
+/// * `Err(CartridgeError::PatchChecksumMismatch)` if a UPS or BPS patch was
+///   made for a different ROM, produces the wrong result or is corrupt.
+pub fn apply(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, CartridgeError> {
+    match PatchFormat::detect(patch) {
+        Some(PatchFormat::Ips) => apply_ips(rom, patch),
+        Some(PatchFormat::Ups) => apply_ups(rom, patch),
+        Some(PatchFormat::Bps) => apply_bps(rom, patch),
+        None => Err(invalid("not an IPS, UPS or BPS file")),
+    }
+}
+
+/// Creates a patch that turns `original` into `modified`.
+///
+/// # Arguments
+///
+/// * `format` - The patch format to write.
+/// * `original` - The unmodified ROM file.
+/// * `modified` - The modified ROM file.
+///
+/// # Returns
+///
+/// * `Ok(Vec<u8>)` containing the patch file.
+/// * `Err(CartridgeError::InvalidPatch)` if the format cannot describe the
+///   change (IPS is limited to 16 MB files).
+pub fn create(format: PatchFormat, original: &[u8], modified: &[u8]) -> Result<Vec<u8>, CartridgeError> {
+    match format {
+        PatchFormat::Ips => create_ips(original, modified),
+        PatchFormat::Ups => Err(invalid("creating UPS patches is not supported, use IPS or BPS")),
+        PatchFormat::Bps => Ok(create_bps(original, modified)),
+    }
+}
+
+/// Creates an IPS patch. Any changed run of bytes becomes a record, and a
+/// truncation record is added when the modified file is shorter.
+pub fn create_ips(original: &[u8], modified: &[u8]) -> Result<Vec<u8>, CartridgeError> {
+    if modified.len() > IPS_MAX_SIZE || original.len() > IPS_MAX_SIZE {
+        return Err(invalid("IPS patches cannot describe files larger than 16 MB"));
+    }
+
+    let differs = |index: usize| index >= original.len() || original[index] != modified[index];
+
+    let mut patch = IPS_MAGIC.to_vec();
+    let mut index = 0;
+    while index < modified.len() {
+        if !differs(index) {
+            index += 1;
+            continue;
+        }
+
+        // Start one byte early rather than write an offset that reads as "EOF"
+        let start = if index == IPS_EOF_OFFSET { index - 1 } else { index };
+        let mut end = index;
+        while end < modified.len() && end - start < IPS_MAX_RECORD && differs(end) {
+            end += 1;
+        }
+
+        patch.extend_from_slice(&(start as u32).to_be_bytes()[1..]);
+        patch.extend_from_slice(&((end - start) as u16).to_be_bytes());
+        patch.extend_from_slice(&modified[start..end]);
+        index = end;
+    }
+
+    patch.extend_from_slice(IPS_EOF);
+    if modified.len() < original.len() {
+        patch.extend_from_slice(&(modified.len() as u32).to_be_bytes()[1..]);
+    }
+
+    Ok(patch)
+}
+
+/// Creates a BPS patch made of source and target reads. Unchanged runs are
+/// read from the original, changed runs are stored in the patch.
+pub fn create_bps(original: &[u8], modified: &[u8]) -> Vec<u8> {
+    let mut patch = BPS_MAGIC.to_vec();
+    write_number(&mut patch, original.len());
+    write_number(&mut patch, modified.len());
+    write_number(&mut patch, 0); // No metadata
+
+    let unchanged = |index: usize| index < original.len() && original[index] == modified[index];
+
+    let mut index = 0;
+    while index < modified.len() {
+        let start = index;
+        let copy = unchanged(start);
+        while index < modified.len() && unchanged(index) == copy {
+            index += 1;
+        }
+
+        let length = index - start;
+        if copy {
+            write_number(&mut patch, ((length - 1) << 2) | BPS_SOURCE_READ);
+        } else {
+            write_number(&mut patch, ((length - 1) << 2) | BPS_TARGET_READ);
+            patch.extend_from_slice(&modified[start..index]);
+        }
+    }
+
+    patch.extend_from_slice(&crc32fast::hash(original).to_le_bytes());
+    patch.extend_from_slice(&crc32fast::hash(modified).to_le_bytes());
+    let patch_crc = crc32fast::hash(&patch);
+    patch.extend_from_slice(&patch_crc.to_le_bytes());
+
+    patch
+}
+
+fn apply_ips(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, CartridgeError> {
+    let mut output = rom.to_vec();
+    let mut position = IPS_MAGIC.len();
+
+    loop {
+        let tag = read_slice(patch, &mut position, 3, patch.len())?;
+        if tag == IPS_EOF {
+            break;
+        }
+
+        let offset = read_u24(tag);
+        let size = read_u16(read_slice(patch, &mut position, 2, patch.len())?);
+
+        if size == 0 {
+            // Run length encoded record
+            let count = read_u16(read_slice(patch, &mut position, 2, patch.len())?);
+            let value = read_slice(patch, &mut position, 1, patch.len())?[0];
+            write_at(&mut output, offset, &vec![value; count]);
+        } else {
+            let data = read_slice(patch, &mut position, size, patch.len())?;
+            write_at(&mut output, offset, data);
+        }
+    }
+
+    // Optional truncation extension
+    if patch.len() - position >= 3 {
+        let length = read_u24(&patch[position..position + 3]);
+        output.truncate(length);
+    }
+
+    Ok(output)
+}
+
+fn apply_ups(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, CartridgeError> {
+    let (source_crc, target_crc) = verify_footer(patch, UPS_MAGIC)?;
+    let end = patch.len() - FOOTER_SIZE;
+
+    let mut position = UPS_MAGIC.len();
+    let source_size = read_number(patch, &mut position, end)?;
+    let target_size = read_number(patch, &mut position, end)?;
+    check_source(rom, source_size, source_crc)?;
+    check_target_size(target_size)?;
+
+    // Hunks XOR the source, bytes past the end of the source count as zero
+    let mut output = rom.to_vec();
+    output.resize(target_size, 0);
+
+    let mut offset = 0usize;
+    while position < end {
+        offset = offset.saturating_add(read_number(patch, &mut position, end)?);
+
+        loop {
+            let value = read_slice(patch, &mut position, 1, end)?[0];
+            if value == 0 {
+                offset = offset.saturating_add(1);
+                break;
+            }
+            if let Some(byte) = output.get_mut(offset) {
+                *byte ^= value;
+            }
+            offset = offset.saturating_add(1);
+        }
+    }
+
+    check_target(&output, target_crc)?;
+    Ok(output)
+}
+
+fn apply_bps(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, CartridgeError> {
+    let (source_crc, target_crc) = verify_footer(patch, BPS_MAGIC)?;
+    let end = patch.len() - FOOTER_SIZE;
+
+    let mut position = BPS_MAGIC.len();
+    let source_size = read_number(patch, &mut position, end)?;
+    let target_size = read_number(patch, &mut position, end)?;
+    let metadata_size = read_number(patch, &mut position, end)?;
+    read_slice(patch, &mut position, metadata_size, end)?;
+    check_source(rom, source_size, source_crc)?;
+    check_target_size(target_size)?;
+
+    let mut output: Vec<u8> = Vec::new();
+    let mut source_offset = 0usize;
+    let mut target_offset = 0usize;
+
+    while position < end {
+        let action = read_number(patch, &mut position, end)?;
+        let length = (action >> 2) + 1;
+
+        output
+            .len()
+            .checked_add(length)
+            .filter(|stop| *stop <= target_size)
+            .ok_or_else(|| invalid("BPS action writes past the end of the target"))?;
+
+        match action & BPS_COMMAND_MASK {
+            BPS_SOURCE_READ => {
+                let start = output.len();
+                let data = rom
+                    .get(start..start + length)
+                    .ok_or_else(|| invalid("BPS source read past the end of the source"))?;
+                output.extend_from_slice(data);
+            }
+            BPS_TARGET_READ => {
+                output.extend_from_slice(read_slice(patch, &mut position, length, end)?);
+            }
+            BPS_SOURCE_COPY => {
+                source_offset = read_relative_offset(patch, &mut position, end, source_offset)?;
+                let data = source_offset
+                    .checked_add(length)
+                    .and_then(|stop| rom.get(source_offset..stop))
+                    .ok_or_else(|| invalid("BPS source copy past the end of the source"))?;
+                output.extend_from_slice(data);
+                source_offset += length;
+            }
+            BPS_TARGET_COPY => {
+                target_offset = read_relative_offset(patch, &mut position, end, target_offset)?;
+                // Copies may overlap the bytes they produce, so go one at a time
+                for _ in 0..length {
+                    let value = *output
+                        .get(target_offset)
+                        .ok_or_else(|| invalid("BPS target copy reads unwritten data"))?;
+                    output.push(value);
+                    target_offset += 1;
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    if output.len() != target_size {
+        return Err(invalid("BPS patch ends before the target is complete"));
+    }
+
+    check_target(&output, target_crc)?;
+    Ok(output)
+}
+
+/// Checks the patch's own CRC32 and returns the source and target CRC32s.
+fn verify_footer(patch: &[u8], magic: &[u8]) -> Result<(u32, u32), CartridgeError> {
+    if patch.len() < magic.len() + FOOTER_SIZE {
+        return Err(invalid("patch is too short"));
+    }
+
+    let footer = &patch[patch.len() - FOOTER_SIZE..];
+    let source_crc = read_u32_le(&footer[0..4]);
+    let target_crc = read_u32_le(&footer[4..8]);
+    let patch_crc = read_u32_le(&footer[8..12]);
+
+    let found = crc32fast::hash(&patch[..patch.len() - 4]);
+    if found != patch_crc {
+        return Err(CartridgeError::PatchChecksumMismatch { checksum: "patch", expected: patch_crc, found });
+    }
+
+    Ok((source_crc, target_crc))
+}
+
+fn check_target_size(target_size: usize) -> Result<(), CartridgeError> {
+    if target_size > MAX_TARGET_SIZE {
+        return Err(invalid("target is too large"));
+    }
+
+    Ok(())
+}
+
+fn check_source(rom: &[u8], source_size: usize, source_crc: u32) -> Result<(), CartridgeError> {
+    let found = crc32fast::hash(rom);
+    if rom.len() != source_size || found != source_crc {
+        return Err(CartridgeError::PatchChecksumMismatch { checksum: "source", expected: source_crc, found });
+    }
+
+    Ok(())
+}
+
+fn check_target(output: &[u8], target_crc: u32) -> Result<(), CartridgeError> {
+    let found = crc32fast::hash(output);
+    if found != target_crc {
+        return Err(CartridgeError::PatchChecksumMismatch { checksum: "target", expected: target_crc, found });
+    }
+
+    Ok(())
+}
+
+/// Reads a variable length number as used by UPS and BPS. Each byte holds
+/// seven bits, the high bit marks the last byte.
+fn read_number(patch: &[u8], position: &mut usize, end: usize) -> Result<usize, CartridgeError> {
+    let mut data = 0usize;
+    let mut shift = 1usize;
+
+    loop {
+        let value = read_slice(patch, position, 1, end)?[0];
+        data = (value as usize & 0x7F)
+            .checked_mul(shift)
+            .and_then(|bits| data.checked_add(bits))
+            .ok_or_else(|| invalid("number is too large"))?;
+
+        if value & 0x80 != 0 {
+            return Ok(data);
+        }
+
+        shift = shift.checked_shl(7).filter(|shift| *shift != 0).ok_or_else(|| invalid("number is too large"))?;
+        data = data.checked_add(shift).ok_or_else(|| invalid("number is too large"))?;
+    }
+}
+
+fn write_number(patch: &mut Vec<u8>, mut data: usize) {
+    loop {
+        let value = (data & 0x7F) as u8;
+        data >>= 7;
+
+        if data == 0 {
+            patch.push(0x80 | value);
+            break;
+        }
+
+        patch.push(value);
+        data -= 1;
+    }
+}
+
+/// Reads a BPS copy offset, stored as a magnitude with the sign in bit 0.
+fn read_relative_offset(patch: &[u8], position: &mut usize, end: usize, offset: usize) -> Result<usize, CartridgeError> {
+    let data = read_number(patch, position, end)?;
+    let distance = data >> 1;
+
+    let offset = if data & 1 != 0 { offset.checked_sub(distance) } else { offset.checked_add(distance) };
+    offset.ok_or_else(|| invalid("BPS copy offset is out of range"))
+}
+
+fn read_slice<'a>(patch: &'a [u8], position: &mut usize, length: usize, end: usize) -> Result<&'a [u8], CartridgeError> {
+    let start = *position;
+    let stop = start.checked_add(length).filter(|stop| *stop <= end).ok_or_else(|| invalid("unexpected end of patch"))?;
+
+    *position = stop;
+    Ok(&patch[start..stop])
+}
+
+fn write_at(output: &mut Vec<u8>, offset: usize, data: &[u8]) {
+    let end = offset + data.len();
+    if output.len() < end {
+        output.resize(end, 0);
+    }
+
+    output[offset..end].copy_from_slice(data);
+}
+
+fn read_u16(bytes: &[u8]) -> usize {
+    ((bytes[0] as usize) << 8) | bytes[1] as usize
+}
+
+fn read_u24(bytes: &[u8]) -> usize {
+    ((bytes[0] as usize) << 16) | ((bytes[1] as usize) << 8) | bytes[2] as usize
+}
+
+fn read_u32_le(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn invalid(reason: &str) -> CartridgeError {
+    CartridgeError::InvalidPatch(reason.to_string())
+}
@@ -0,0 +1,345 @@
+//! # rom_database.rs
+//!
+//!  Author: Paul Hazen
+//! Created: 2026-10-19
+//! License: MIT (see LICENSE file)
+//!
+//! ## Description
+//! Identifies ROMs by the hashes of their PRG and CHR data and looks them up in
+//! an XML database. Two formats are understood:
+//!
+//! * The NES 2.0 database (`<nes20db>`), whose entries carry the full header
+//!   and can be used to correct bad iNES headers.
+//! * No-Intro DAT files (`<datafile>`), which only identify the game.
+//!
+//! No database ships with the emulator. Header correction and the region
+//! lookup only find games once `Settings::rom_database` (or the database
+//! argument of `bard info`) points at one.
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use super::{Cartridge, CartridgeError, CartridgeHeader, ConsoleType, Mirroring, TimingRegion, VsSystemType};
+
+/// The CRC32 and SHA-1 of a cartridge's PRG-ROM followed by its CHR-ROM.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RomHashes {
+    pub crc32: u32,
+    pub sha1: [u8; 20],
+}
+
+impl RomHashes {
+    /// Hashes the ROM data of a cartridge. The header and trainer are left out
+    /// so that dumps with different headers hash the same.
+    pub fn of(cartridge: &Cartridge) -> Self {
+        let mut crc32 = crc32fast::Hasher::new();
+        let mut sha1 = sha1_smol::Sha1::new();
+
+        for data in [&cartridge.prg_rom, &cartridge.chr_rom] {
+            crc32.update(data);
+            sha1.update(data);
+        }
+
+        Self {
+            crc32: crc32.finalize(),
+            sha1: sha1.digest().bytes(),
+        }
+    }
+
+    /// The SHA-1 as an upper case hex string.
+    pub fn sha1_hex(&self) -> String {
+        self.sha1.iter().map(|byte| format!("{:02X}", byte)).collect()
+    }
+}
+
+/// Header values recorded in the database. `None` means the database does not
+/// say, and the value from the file's header is kept.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HeaderOverrides {
+    pub mapper_id: Option<u16>,
+    pub submapper_id: Option<u8>,
+    pub mirroring: Option<Mirroring>,
+    pub has_battery: Option<bool>,
+    pub prg_ram_size: Option<usize>,
+    pub prg_nvram_size: Option<usize>,
+    pub chr_ram_size: Option<usize>,
+    pub chr_nvram_size: Option<usize>,
+    pub console_type: Option<ConsoleType>,
+    pub vs_system: Option<VsSystemType>,
+    pub timing: Option<TimingRegion>,
+    pub expansion_device: Option<u8>,
+}
+
+/// A header field that was changed to match the database.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HeaderCorrection {
+    pub field: &'static str,
+    pub old: String,
+    pub new: String,
+}
+
+impl fmt::Display for HeaderCorrection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} -> {}", self.field, self.old, self.new)
+    }
+}
+
+impl HeaderOverrides {
+    /// Replaces header fields that disagree with the database.
+    ///
+    /// # Arguments
+    ///
+    /// * `header` - The header parsed from the ROM file.
+    ///
+    /// # Returns
+    ///
+    /// * The fields that were changed, empty if the header was already correct.
+    pub fn apply(&self, header: &mut CartridgeHeader) -> Vec<HeaderCorrection> {
+        let mut corrections = vec![];
+
+        correct(&mut corrections, "mapper", &mut header.mapper_id, self.mapper_id);
+        correct(&mut corrections, "submapper", &mut header.submapper_id, self.submapper_id);
+        correct(&mut corrections, "mirroring", &mut header.mirroring, self.mirroring);
+        correct(&mut corrections, "battery", &mut header.has_battery, self.has_battery);
+        correct(&mut corrections, "PRG-RAM size", &mut header.prg_ram_size, self.prg_ram_size);
+        correct(&mut corrections, "PRG-NVRAM size", &mut header.prg_nvram_size, self.prg_nvram_size);
+        correct(&mut corrections, "CHR-RAM size", &mut header.chr_ram_size, self.chr_ram_size);
+        correct(&mut corrections, "CHR-NVRAM size", &mut header.chr_nvram_size, self.chr_nvram_size);
+        correct(&mut corrections, "console type", &mut header.console_type, self.console_type);
+        correct(&mut corrections, "Vs. System", &mut header.vs_system, self.vs_system.map(Some));
+        correct(&mut corrections, "timing", &mut header.timing, self.timing);
+        correct(&mut corrections, "expansion device", &mut header.expansion_device, self.expansion_device);
+
+        corrections
+    }
+}
+
+fn correct<T: Copy + PartialEq + fmt::Debug>(
+    corrections: &mut Vec<HeaderCorrection>,
+    field: &'static str,
+    current: &mut T,
+    value: Option<T>,
+) {
+    if let Some(value) = value.filter(|value| value != current) {
+        corrections.push(HeaderCorrection {
+            field,
+            old: format!("{:?}", current),
+            new: format!("{:?}", value),
+        });
+        *current = value;
+    }
+}
+
+/// What the database knows about one ROM.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RomEntry {
+    /// The canonical name of the dump, e.g. "Super Mario Bros. (World)".
+    pub title: String,
+    /// The region the game was released in.
+    pub region: Option<String>,
+    /// The circuit board the game was released on, if the database names it.
+    pub board: Option<String>,
+    pub crc32: Option<u32>,
+    pub sha1: Option<[u8; 20]>,
+    /// The header the ROM should have. Empty for No-Intro entries.
+    pub overrides: HeaderOverrides,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RomDatabase {
+    entries: Vec<RomEntry>,
+}
+
+impl RomDatabase {
+    /// The user supplied database, or an empty one if none is given.
+    pub fn open(user_database: Option<&Path>) -> Result<Self, CartridgeError> {
+        match user_database {
+            Some(path) => Self::load(path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Reads a NES 2.0 or No-Intro XML database from disk.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the XML file.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(RomDatabase)` if the file is read and parsed.
+    /// * `Err(CartridgeError)` if the file cannot be read or is not a database.
+    pub fn load(path: &Path) -> Result<Self, CartridgeError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parses a NES 2.0 or No-Intro XML database. Games without hashes are
+    /// skipped.
+    pub fn parse(xml: &str) -> Result<Self, CartridgeError> {
+        let document = roxmltree::Document::parse(xml)
+            .map_err(|error| CartridgeError::InvalidDatabase(error.to_string()))?;
+        let root = document.root_element();
+
+        let parse_game = match root.tag_name().name() {
+            "nes20db" => parse_nes20db_game,
+            "datafile" => parse_no_intro_game,
+            other => return Err(CartridgeError::InvalidDatabase(format!("unknown database type <{}>", other))),
+        };
+
+        let entries = root
+            .children()
+            .filter(|node| node.has_tag_name("game"))
+            .filter_map(parse_game)
+            .collect();
+
+        Ok(Self { entries })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Finds a ROM by SHA-1, falling back to CRC32 for entries without one.
+    pub fn lookup(&self, hashes: &RomHashes) -> Option<&RomEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.sha1 == Some(hashes.sha1))
+            .or_else(|| self.entries.iter().find(|entry| entry.sha1.is_none() && entry.crc32 == Some(hashes.crc32)))
+    }
+}
+
+/// Parses a `<game>` of the NES 2.0 database:
+///
+/// ```xml
+/// <game>
+///   <!-- Super Mario Bros. (World).nes -->
+///   <rom size="40960" crc32="3337EC46" sha1="..."/>
+///   <pcb mapper="0" submapper="0" mirroring="V" battery="0"/>
+///   <console type="0" region="0"/>
+/// </game>
+/// ```
+///
+/// Elements for RAM the board does not have are left out, so their absence
+/// means a size of zero.
+fn parse_nes20db_game(game: roxmltree::Node) -> Option<RomEntry> {
+    let child = |name: &str| game.children().find(|node| node.has_tag_name(name));
+    let attribute = |name: &str, key: &str| child(name).and_then(|node| node.attribute(key));
+    let number = |name: &str, key: &str| attribute(name, key).and_then(|value| value.parse::<usize>().ok());
+    let ram_size = |name: &str| Some(number(name, "size").unwrap_or(0));
+
+    let rom = child("rom")?;
+    let crc32 = rom.attribute("crc32").and_then(parse_crc32);
+    let sha1 = rom.attribute("sha1").and_then(parse_sha1);
+    if crc32.is_none() && sha1.is_none() {
+        return None;
+    }
+
+    // The title is the file name in the comment at the top of the entry
+    let title = game
+        .children()
+        .find(|node| node.is_comment())
+        .and_then(|node| node.text())
+        .map(|text| {
+            let name = text.trim().rsplit(['\\', '/']).next().unwrap_or_default();
+            name.strip_suffix(".nes").unwrap_or(name).to_string()
+        })
+        .unwrap_or_default();
+
+    let timing = number("console", "region").map(|region| match region {
+        0 => TimingRegion::Ntsc,
+        1 => TimingRegion::Pal,
+        2 => TimingRegion::MultiRegion,
+        _ => TimingRegion::Dendy,
+    });
+
+    let overrides = HeaderOverrides {
+        mapper_id: number("pcb", "mapper").map(|mapper| mapper as u16),
+        submapper_id: number("pcb", "submapper").map(|submapper| submapper as u8),
+        mirroring: attribute("pcb", "mirroring").and_then(|mirroring| match mirroring {
+            "H" => Some(Mirroring::Horizontal),
+            "V" => Some(Mirroring::Vertical),
+            "4" => Some(Mirroring::FourScreen),
+            _ => None, // Mapper controlled
+        }),
+        has_battery: number("pcb", "battery").map(|battery| battery != 0),
+        prg_ram_size: ram_size("prgram"),
+        prg_nvram_size: ram_size("prgnvram"),
+        chr_ram_size: ram_size("chrram"),
+        chr_nvram_size: ram_size("chrnvram"),
+        console_type: number("console", "type").map(|console| match console {
+            0 => ConsoleType::Nes,
+            1 => ConsoleType::VsSystem,
+            2 => ConsoleType::PlayChoice10,
+            other => ConsoleType::Extended(other as u8),
+        }),
+        vs_system: number("vs", "ppu").map(|ppu| {
+            let hardware = number("vs", "hardware").unwrap_or(0);
+            VsSystemType::from_byte(((hardware as u8) << 4) | (ppu as u8 & 0x0F))
+        }),
+        timing,
+        expansion_device: number("expansion", "type").map(|device| device as u8),
+    };
+
+    Some(RomEntry {
+        title,
+        region: timing.map(|timing| format!("{:?}", timing)),
+        board: attribute("pcb", "name").map(str::to_string),
+        crc32,
+        sha1,
+        overrides,
+    })
+}
+
+/// Parses a `<game>` of a headerless No-Intro DAT:
+///
+/// ```xml
+/// <game name="Super Mario Bros. (World)">
+///   <rom name="Super Mario Bros. (World).nes" size="40960" crc="3337EC46" sha1="..."/>
+/// </game>
+/// ```
+fn parse_no_intro_game(game: roxmltree::Node) -> Option<RomEntry> {
+    let rom = game.children().find(|node| node.has_tag_name("rom"))?;
+    let crc32 = rom.attribute("crc").and_then(parse_crc32);
+    let sha1 = rom.attribute("sha1").and_then(parse_sha1);
+    if crc32.is_none() && sha1.is_none() {
+        return None;
+    }
+
+    let title = game.attribute("name").unwrap_or_default().to_string();
+
+    // The region is the first bracketed tag of the name, e.g. "(USA)"
+    let region = title
+        .split_once('(')
+        .and_then(|(_, rest)| rest.split_once(')'))
+        .map(|(region, _)| region.to_string());
+
+    Some(RomEntry {
+        title,
+        region,
+        board: None,
+        crc32,
+        sha1,
+        overrides: HeaderOverrides::default(),
+    })
+}
+
+fn parse_crc32(text: &str) -> Option<u32> {
+    u32::from_str_radix(text, 16).ok()
+}
+
+fn parse_sha1(text: &str) -> Option<[u8; 20]> {
+    if text.len() != 40 || !text.is_ascii() {
+        return None;
+    }
+
+    let mut sha1 = [0; 20];
+    for (index, byte) in sha1.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&text[index * 2..index * 2 + 2], 16).ok()?;
+    }
+
+    Some(sha1)
+}
@@ -0,0 +1,94 @@
+//! # save_file.rs
+//!
+//!  Author: Paul Hazen
+//! Created: 2026-10-19
+//! License: MIT (see LICENSE file)
+//!
+//! ## Description
+//! Persists battery backed cartridge RAM to a `.sav` file, either next to the
+//! ROM or in a separate saves directory.
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+pub struct SaveFile {
+    path: PathBuf,
+    /// The contents of the file as last read or written, so unchanged RAM is
+    /// not rewritten on every flush.
+    last_saved: Vec<u8>,
+}
+
+impl SaveFile {
+    /// Creates the save file for a ROM.
+    ///
+    /// # Arguments
+    ///
+    /// * `rom_path` - The path of the ROM file the save belongs to.
+    /// * `saves_directory` - Where to keep the save, `None` for next to the ROM.
+    pub fn for_rom(rom_path: &Path, saves_directory: Option<&Path>) -> Self {
+        let directory = match saves_directory {
+            Some(directory) => directory.to_path_buf(),
+            None => rom_path.parent().map(Path::to_path_buf).unwrap_or_default(),
+        };
+
+        let stem = rom_path.file_stem().unwrap_or(rom_path.as_os_str());
+
+        Self {
+            path: directory.join(format!("{}.sav", stem.to_string_lossy())),
+            last_saved: vec![],
+        }
+    }
+
+    /// The path of the `.sav` file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Reads the save file.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(Vec<u8>))` with the saved RAM.
+    /// * `Ok(None)` if the game has not been saved yet.
+    /// * `Err(io::Error)` if the file exists but cannot be read.
+    pub fn load(&mut self) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(&self.path) {
+            Ok(data) => {
+                self.last_saved = data.clone();
+                Ok(Some(data))
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Writes the RAM to disk if it has changed since the last load or write.
+    ///
+    /// The data is written to a temporary file which then replaces the save,
+    /// so a crash part way through never leaves a half written `.sav` behind.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(true)` if the file was written.
+    /// * `Ok(false)` if the RAM was unchanged.
+    pub fn flush(&mut self, data: &[u8]) -> io::Result<bool> {
+        if data == self.last_saved.as_slice() {
+            return Ok(false);
+        }
+
+        if let Some(directory) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(directory)?;
+        }
+
+        let temp_path = self.path.with_extension("sav.tmp");
+        {
+            let mut file = File::create(&temp_path)?;
+            file.write_all(data)?;
+            file.sync_all()?;
+        }
+        fs::rename(&temp_path, &self.path)?;
+
+        self.last_saved = data.to_vec();
+        Ok(true)
+    }
+}
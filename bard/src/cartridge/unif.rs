@@ -0,0 +1,234 @@
+//! # unif.rs
+//!
+//!  Author: Paul Hazen
+//! Created: 2026-10-19
+//! License: MIT (see LICENSE file)
+//!
+//! ## Description
+//! Converts UNIF (`.unf`) images into a `Cartridge`. UNIF names the board
+//! instead of giving a mapper number, and stores everything else in tagged
+//! chunks.
+
+/*
+    UNIF Layout
+    ----------------------------------------------------------------
+    | Offset | Function                                            |
+    ----------------------------------------------------------------
+    |   0-3  | "UNIF"                                              |
+    |   4-7  | Revision (little endian)                            |
+    |  8-31  | Reserved                                            |
+    |   32-  | Chunks: 4 byte ID, 4 byte little endian length, data|
+    ----------------------------------------------------------------
+
+    Chunks
+    ----------------------------------------------------------------
+    | ID        | Function                                         |
+    ----------------------------------------------------------------
+    | MAPR      | Board name, NUL terminated                       |
+    | PRG0-PRGF | PRG-ROM, concatenated in order                   |
+    | CHR0-CHRF | CHR-ROM, concatenated in order                   |
+    | MIRR      | Mirroring (see below)                            |
+    | BATR      | Present if the board has battery backed RAM      |
+    | TVCI      | 0 = NTSC, 1 = PAL, 2 = either                    |
+    ----------------------------------------------------------------
+ */
+use super::cartridge_header::{CHR_ROM_UNIT, PRG_ROM_UNIT};
+use super::{Cartridge, CartridgeError, CartridgeHeader, ConsoleType, HeaderFormat, Mirroring, TimingRegion};
+
+const UNIF_MAGIC: &[u8] = b"UNIF";
+const UNIF_HEADER_SIZE: usize = 32;
+const CHUNK_HEADER_SIZE: usize = 8;
+
+/*
+    MIRR
+    ----------------------------------------------------------------
+    | Value | Function                                             |
+    ----------------------------------------------------------------
+    |   0   | Horizontal                                           |
+    |   1   | Vertical                                             |
+    |   2   | Single screen, first page                            |
+    |   3   | Single screen, second page                           |
+    |   4   | Four screen                                          |
+    |   5   | Controlled by the mapper                             |
+    ----------------------------------------------------------------
+ */
+const MIRR_HORIZONTAL: u8 = 0;
+const MIRR_VERTICAL: u8 = 1;
+const MIRR_SINGLE_SCREEN_A: u8 = 2;
+const MIRR_SINGLE_SCREEN_B: u8 = 3;
+const MIRR_FOUR_SCREEN: u8 = 4;
+
+/// Manufacturer prefixes that come before the board name proper.
+const BOARD_PREFIXES: [&str; 5] = ["NES-", "HVC-", "UNL-", "BTL-", "BMC-"];
+
+/// Board names and the iNES mapper / submapper that implements them.
+const BOARDS: [(&str, u16, u8); 14] = [
+    ("NROM", 0, 0),
+    ("NROM-128", 0, 0),
+    ("NROM-256", 0, 0),
+    ("HROM", 0, 0),
+    ("RROM", 0, 0),
+    ("RROM-128", 0, 0),
+    ("SROM", 0, 0),
+    ("RTROM", 0, 0),
+    ("STROM", 0, 0),
+    ("EKROM", 5, 0),
+    ("ELROM", 5, 0),
+    ("ETROM", 5, 0),
+    ("EWROM", 5, 0),
+    ("EXROM", 5, 0),
+];
+
+/// Whether the data is a UNIF image.
+pub fn is_unif(bytes: &[u8]) -> bool {
+    bytes.starts_with(UNIF_MAGIC)
+}
+
+/// Looks up the mapper that implements a UNIF board.
+///
+/// # Arguments
+///
+/// * `board` - The board name from the MAPR chunk, e.g. "NES-NROM-256".
+///
+/// # Returns
+///
+/// * `Some((mapper, submapper))` if the board is supported.
+/// * `None` if no mapper implements the board.
+pub fn board_mapper(board: &str) -> Option<(u16, u8)> {
+    let name = BOARD_PREFIXES
+        .iter()
+        .find_map(|prefix| board.strip_prefix(prefix))
+        .unwrap_or(board);
+
+    BOARDS
+        .iter()
+        .find(|(known, _, _)| known.eq_ignore_ascii_case(name))
+        .map(|&(_, mapper, submapper)| (mapper, submapper))
+}
+
+/// Parses a UNIF image into a cartridge with an equivalent header.
+///
+/// # Arguments
+///
+/// * `bytes` - The contents of a `.unf` file.
+///
+/// # Returns
+///
+/// * `Ok(Cartridge)` if the image is successfully parsed.
+/// * `Err(CartridgeError::InvalidHeader)` if the image has no board name or
+///   no PRG-ROM.
+/// * `Err(CartridgeError::UnsupportedBoard)` if no mapper implements the board.
+/// * `Err(CartridgeError::Truncated)` if a chunk runs past the end of the file.
+pub fn parse(bytes: &[u8]) -> Result<Cartridge, CartridgeError> {
+    if bytes.len() < UNIF_HEADER_SIZE {
+        return Err(CartridgeError::Truncated { section: "header", expected: UNIF_HEADER_SIZE, found: bytes.len() });
+    }
+
+    let mut board = None;
+    let mut prg_chunks: [Vec<u8>; 16] = Default::default();
+    let mut chr_chunks: [Vec<u8>; 16] = Default::default();
+    let mut mirroring = Mirroring::Horizontal;
+    let mut has_battery = false;
+    let mut timing = TimingRegion::Ntsc;
+
+    let mut position = UNIF_HEADER_SIZE;
+    while bytes.len() - position >= CHUNK_HEADER_SIZE {
+        let id = &bytes[position..position + 4];
+        let length = u32::from_le_bytes([bytes[position + 4], bytes[position + 5], bytes[position + 6], bytes[position + 7]]) as usize;
+        position += CHUNK_HEADER_SIZE;
+
+        let found = bytes.len() - position;
+        if length > found {
+            return Err(CartridgeError::Truncated { section: "UNIF chunk", expected: length, found });
+        }
+        let data = &bytes[position..position + length];
+        position += length;
+
+        match id {
+            b"MAPR" => {
+                let name = data.split(|&byte| byte == 0).next().unwrap_or_default();
+                board = Some(String::from_utf8_lossy(name).trim().to_string());
+            }
+            b"MIRR" => {
+                mirroring = match data.first() {
+                    Some(&MIRR_HORIZONTAL) => Mirroring::Horizontal,
+                    Some(&MIRR_VERTICAL) => Mirroring::Vertical,
+                    Some(&MIRR_SINGLE_SCREEN_A) => Mirroring::SingleScreenA,
+                    Some(&MIRR_SINGLE_SCREEN_B) => Mirroring::SingleScreenB,
+                    Some(&MIRR_FOUR_SCREEN) => Mirroring::FourScreen,
+                    _ => Mirroring::Horizontal, // Set by the mapper at run time
+                }
+            }
+            b"BATR" => has_battery = data.first() != Some(&0),
+            b"TVCI" => {
+                timing = match data.first() {
+                    Some(1) => TimingRegion::Pal,
+                    Some(2) => TimingRegion::MultiRegion,
+                    _ => TimingRegion::Ntsc,
+                }
+            }
+            _ => {
+                if let Some(index) = rom_chunk_index(id, b"PRG") {
+                    prg_chunks[index] = data.to_vec();
+                } else if let Some(index) = rom_chunk_index(id, b"CHR") {
+                    chr_chunks[index] = data.to_vec();
+                }
+                // Other chunks (NAME, READ, DINF, CTRL, checksums) are informational
+            }
+        }
+    }
+
+    let board = board.ok_or(CartridgeError::InvalidHeader)?;
+    let (mapper_id, submapper_id) =
+        board_mapper(&board).ok_or_else(|| CartridgeError::UnsupportedBoard(board.clone()))?;
+
+    let prg_rom = prg_chunks.concat();
+    let chr_rom = chr_chunks.concat();
+    if prg_rom.is_empty() {
+        return Err(CartridgeError::InvalidHeader);
+    }
+
+    let mut header = CartridgeHeader {
+        format: HeaderFormat::Unif,
+        prg_rom_size: prg_rom.len().div_ceil(PRG_ROM_UNIT) as u16,
+        chr_rom_size: chr_rom.len().div_ceil(CHR_ROM_UNIT) as u16,
+        prg_rom_bytes: prg_rom.len(),
+        chr_rom_bytes: chr_rom.len(),
+        mapper_id,
+        submapper_id,
+        mirroring,
+        has_battery,
+        has_trainer: false,
+        prg_ram_size: 0,
+        prg_nvram_size: 0,
+        chr_ram_size: 0,
+        chr_nvram_size: 0,
+        console_type: ConsoleType::Nes,
+        vs_system: None,
+        timing,
+        expansion_device: 0,
+        misc_rom_count: 0,
+        board: Some(board),
+        buffer: bytes[..UNIF_HEADER_SIZE].into(),
+    };
+    header.apply_ines_ram_defaults(0);
+
+    Ok(Cartridge {
+        header,
+        prg_rom,
+        chr_rom,
+        trainer: vec![],
+        misc_rom: vec![],
+        disk: None,
+        nsf: None,
+    })
+}
+
+/// Returns the index of a PRGn / CHRn chunk, where n is a hex digit.
+fn rom_chunk_index(id: &[u8], kind: &[u8; 3]) -> Option<usize> {
+    if !id.starts_with(kind) {
+        return None;
+    }
+
+    (id[3] as char).to_digit(16).map(|digit| digit as usize)
+}
@@ -129,6 +129,14 @@ impl CPU {
         self.s = value;
     }
 
+    pub fn get_p(&self) -> u8 {
+        self.p
+    }
+
+    pub fn set_p(&mut self, value: u8) {
+        self.p = value;
+    }
+
     pub fn get_pc(&self) -> u16 {
         self.pc
     }
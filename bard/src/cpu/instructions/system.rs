@@ -28,4 +28,45 @@ impl CPU {
         let high = memory.default_read_word(0xFFFF);
         self.set_pc((high << 8) | low);
     }
+
+    /// Services a pending IRQ from the cartridge (or APU) if interrupts are not
+    /// disabled.
+    ///
+    /// # Returns
+    ///
+    /// * `u8` The number of cycles taken, 0 if no interrupt was serviced.
+    pub fn handle_irq(&mut self, memory: &mut CPUBus) -> u8 {
+        if !memory.irq_pending() || self.is_flag_set(Status::INTERRUPT_DISABLE) {
+            return 0;
+        }
+
+        self.interrupt(memory, 0xFFFE)
+    }
+
+    /// Services an NMI raised by the PPU at the start of VBlank. NMIs ignore
+    /// the interrupt disable flag.
+    ///
+    /// # Returns
+    ///
+    /// * `u8` The number of cycles taken, 0 if no interrupt was serviced.
+    pub fn handle_nmi(&mut self, memory: &mut CPUBus) -> u8 {
+        if !memory.take_nmi() {
+            return 0;
+        }
+
+        self.interrupt(memory, 0xFFFA)
+    }
+
+    fn interrupt(&mut self, memory: &mut CPUBus, vector: u16) -> u8 {
+        self.push_stack_word(memory, self.get_pc());
+
+        // Hardware interrupts push the status with Break clear and Unused set
+        let status = (self.get_p() & !Status::BREAK.bits()) | Status::UNUSED.bits();
+        self.push_stack(memory, status);
+
+        self.set_flag(Status::INTERRUPT_DISABLE, true);
+        self.set_pc(memory.read_word(vector));
+
+        7
+    }
 }
\ No newline at end of file
@@ -1,11 +1,18 @@
-use minifb::{Key, Window, WindowOptions};
+use minifb::{Key, KeyRepeat, Window, WindowOptions};
+
+use crate::ppu::PPU;
 
 const WIDTH: usize = 256;
 const HEIGHT: usize = 240;
 
+/// How much an emphasis bit dims the two colour channels it does not
+/// emphasise, as a fraction of 256 (about 0.75).
+const EMPHASIS_ATTENUATION: u32 = 191;
+
 pub struct FramebufferViewer {
     window: Window,
     buffer: [u32; WIDTH * HEIGHT], // Pre-allocated framebuffer
+    palette: [u32; 64],
 }
 
 impl FramebufferViewer {
@@ -24,7 +31,27 @@ impl FramebufferViewer {
 
         Self { 
             window, 
-            buffer: [0; WIDTH * HEIGHT], }
+            buffer: [0; WIDTH * HEIGHT],
+            palette: Self::NES_PALETTE, }
+    }
+
+    /// Whether the key went down since the last update, ignoring key repeat.
+    pub fn is_key_pressed(&self, key: Key) -> bool {
+        self.window.is_key_pressed(key, KeyRepeat::No)
+    }
+
+    /// Whether the key is currently held down.
+    pub fn is_key_down(&self, key: Key) -> bool {
+        self.window.is_key_down(key)
+    }
+
+    /// Replaces the NES palette, for consoles with a different PPU.
+    pub fn set_palette(&mut self, palette: [u32; 64]) {
+        self.palette = palette;
+    }
+
+    pub fn set_title(&mut self, title: &str) {
+        self.window.set_title(title);
     }
 
     pub fn is_open(&self) -> bool {
@@ -42,16 +69,38 @@ impl FramebufferViewer {
         0xD4E675, 0xB7F070, 0x9DF09F, 0x99F1CC, 0xA0ECF0, 0xA4A4A4, 0x000000, 0x000000,
     ];
 
-    fn convert_to_rgb(color: u8) -> u32 {
-        let index = (color as usize) % 64; // Ensure it's within range
-        0xFF000000 | Self::NES_PALETTE[index] // Add alpha channel
+    fn convert_to_rgb(&self, color: u16) -> u32 {
+        let index = (color & PPU::COLOR_MASK) as usize;
+        let emphasis = color >> PPU::EMPHASIS_SHIFT;
+        0xFF000000 | Self::emphasise(self.palette[index], emphasis) // Add alpha channel
+    }
+
+    /// Tints an RGB colour by PPUMASK's emphasis bits (red, green, blue from
+    /// bit 0). Each set bit darkens the other two channels.
+    fn emphasise(rgb: u32, emphasis: u16) -> u32 {
+        if emphasis == 0 {
+            return rgb;
+        }
+
+        let mut channels = [(rgb >> 16) & 0xFF, (rgb >> 8) & 0xFF, rgb & 0xFF];
+        for bit in 0..3 {
+            if emphasis & (1 << bit) == 0 {
+                continue;
+            }
+            for (channel, value) in channels.iter_mut().enumerate() {
+                if channel != bit {
+                    *value = *value * EMPHASIS_ATTENUATION / 256;
+                }
+            }
+        }
+        (channels[0] << 16) | (channels[1] << 8) | channels[2]
     }
 
 
-    pub fn update(&mut self, framebuffer: &[u8; WIDTH * HEIGHT]) {
-        // Convert NES framebuffer (grayscale 0-3) to RGB u32 in-place
+    pub fn update(&mut self, framebuffer: &[u16; WIDTH * HEIGHT]) {
+        // Convert the NES framebuffer (palette indexes and emphasis) to RGB u32 in-place
         for (i, &color) in framebuffer.iter().enumerate() {
-            self.buffer[i] = Self::convert_to_rgb(color);
+            self.buffer[i] = self.convert_to_rgb(color);
         }
 
         // Draw the framebuffer if the window is open
@@ -1,8 +1,14 @@
+pub mod apu;
 pub mod cpu;
 pub mod memory;
 pub mod cartridge;
+pub mod mapper;
 pub mod nes;
+pub mod nsf_player;
 pub mod ppu;
+pub mod region;
+pub mod settings;
+pub mod vs_system;
 
 pub use cartridge::Cartridge;
 mod framebuffer_viewer;
@@ -1,11 +1,263 @@
-pub mod cpu;
-pub mod memory;
-pub mod util;
-pub mod cartridge;
-pub use cpu::CPU;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use bard::cartridge::patch::{self, PatchFormat};
+use bard::cartridge::{Cartridge, CartridgeError, CartridgeHeader, RomDatabase, RomHashes};
 use bard::nes::NES;
+use bard::nsf_player::{self, NsfPlayer};
+use bard::settings::Settings;
+
+const DEFAULT_ROM: &str = "../roms/dk.nes";
+
+/// How much of a song `nsf-wav` renders when no length is given.
+const DEFAULT_WAV_SECONDS: u64 = 60;
+
+const USAGE: &str = "Usage:\n  bard [rom] [patch]\n  bard info <rom> [database.xml]\n  bard create-patch <original> <modified> <output.ips|output.bps>\n  bard nsf-wav <file.nsf> <output.wav> [song] [seconds]";
 
 fn main() {
-    let mut nes = NES::open_rom("../roms/dk.nes");
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    if args.first().map(String::as_str) == Some("create-patch") {
+        create_patch(&args[1..]);
+        return;
+    }
+
+    if args.first().map(String::as_str) == Some("nsf-wav") {
+        render_nsf(&args[1..]);
+        return;
+    }
+
+    if args.first().map(String::as_str) == Some("info") {
+        print_info(&args[1..]);
+        return;
+    }
+
+    let rom_filepath = args.first().cloned().unwrap_or_else(|| DEFAULT_ROM.to_string());
+
+    // An explicit patch wins over one found beside the ROM
+    let patch_path = args
+        .get(1)
+        .map(PathBuf::from)
+        .or_else(|| patch::find_patch(Path::new(&rom_filepath)));
+    if let Some(patch_path) = &patch_path {
+        println!("INFO: Applying patch {}", patch_path.display());
+    }
+
+    let settings = Settings::default();
+    let cartridge = load_cartridge(&rom_filepath, patch_path.as_deref())
+        .and_then(|mut cartridge| cartridge.load_bios(&rom_filepath, settings.fds_bios.as_deref()).map(|_| cartridge));
+    let cartridge = match cartridge {
+        Ok(cartridge) => cartridge,
+        Err(error) => {
+            eprintln!("ERROR: Could not load {}: {}", rom_filepath, error);
+            std::process::exit(1);
+        }
+    };
+
+    // Sound files are played rather than run
+    if cartridge.nsf.is_some() {
+        match NsfPlayer::new(cartridge, nsf_player::DEFAULT_SAMPLE_RATE) {
+            Ok(mut player) => player.run(),
+            Err(error) => eprintln!("ERROR: Could not play {}: {}", rom_filepath, error),
+        }
+        return;
+    }
+
+    let mut nes = NES::with_cartridge(cartridge, &rom_filepath, &settings);
     nes.run();
 }
+
+/// Loads the ROM, asking which one to run when an archive holds several.
+fn load_cartridge(rom_filepath: &str, patch_path: Option<&Path>) -> Result<Cartridge, CartridgeError> {
+    match Cartridge::load(rom_filepath, None, patch_path) {
+        Err(CartridgeError::MultipleRomsInArchive(entries)) => {
+            let entry = prompt_for_entry(&entries)?;
+            Cartridge::load(rom_filepath, Some(&entry), patch_path)
+        }
+        result => result,
+    }
+}
+
+/// Prints the ROM's header and hashes, and what the ROM database knows about it.
+fn print_info(args: &[String]) {
+    let (rom_filepath, database_path) = match args {
+        [rom] => (rom, None),
+        [rom, database] => (rom, Some(Path::new(database))),
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let patch_path = patch::find_patch(Path::new(rom_filepath));
+    let result = load_cartridge(rom_filepath, patch_path.as_deref())
+        .and_then(|cartridge| Ok((cartridge, RomDatabase::open(database_path)?)));
+    let (mut cartridge, database) = match result {
+        Ok(loaded) => loaded,
+        Err(error) => {
+            eprintln!("ERROR: Could not read {}: {}", rom_filepath, error);
+            std::process::exit(1);
+        }
+    };
+
+    println!("File:             {}", rom_filepath);
+    if let Some(patch_path) = &patch_path {
+        println!("Patch:            {}", patch_path.display());
+    }
+    print_header(&cartridge.header);
+
+    if let Some(nsf) = &cartridge.nsf {
+        println!("Title:            {}", nsf.title);
+        println!("Artist:           {}", nsf.artist);
+        println!("Copyright:        {}", nsf.copyright);
+        println!("Songs:            {} (starting with {})", nsf.song_count, nsf.starting_song);
+        println!("Expansion chips:  {:?}", nsf.expansion_chips);
+        return;
+    }
+
+    let hashes = RomHashes::of(&cartridge);
+    println!("CRC32:            {:08X}", hashes.crc32);
+    println!("SHA-1:            {}", hashes.sha1_hex());
+
+    if database_path.is_none() {
+        println!("Database:         none given");
+        return;
+    }
+    let Some(entry) = database.lookup(&hashes) else {
+        println!("Database:         not found ({} entries searched)", database.len());
+        return;
+    };
+
+    println!("Title:            {}", entry.title);
+    println!("Region:           {}", entry.region.as_deref().unwrap_or("unknown"));
+    println!("Board:            {}", entry.board.as_deref().unwrap_or("unknown"));
+
+    let corrections = entry.overrides.apply(&mut cartridge.header);
+    if corrections.is_empty() {
+        println!("Corrections:      none, the header matches the database");
+    } else {
+        println!("Corrections:");
+        for correction in corrections {
+            println!("  {}", correction);
+        }
+    }
+}
+
+fn print_header(header: &CartridgeHeader) {
+    println!("Header format:    {:?}", header.format);
+    println!("PRG-ROM:          {} bytes", header.prg_rom_bytes);
+    println!("CHR-ROM:          {} bytes", header.chr_rom_bytes);
+    println!("Mapper:           {}.{}", header.mapper_id, header.submapper_id);
+    if let Some(board) = &header.board {
+        println!("Board:            {}", board);
+    }
+    println!("Mirroring:        {:?}", header.mirroring);
+    println!("Battery:          {}", header.has_battery);
+    println!("Trainer:          {}", header.has_trainer);
+    println!("PRG-RAM / NVRAM:  {} / {} bytes", header.prg_ram_size, header.prg_nvram_size);
+    println!("CHR-RAM / NVRAM:  {} / {} bytes", header.chr_ram_size, header.chr_nvram_size);
+    println!("Console type:     {:?}", header.console_type);
+    if let Some(vs_system) = &header.vs_system {
+        println!("Vs. System:       {:?} PPU, {:?} board", vs_system.ppu, vs_system.hardware);
+    }
+    println!("Timing:           {:?}", header.timing);
+    println!("Expansion device: {}", header.expansion_device);
+}
+
+/// Writes an IPS or BPS patch that turns one ROM into another. The format is
+/// chosen by the output file's extension.
+fn create_patch(args: &[String]) {
+    let [original_path, modified_path, output_path] = args else {
+        eprintln!("{}", USAGE);
+        std::process::exit(2);
+    };
+
+    let Some(format) = PatchFormat::from_path(Path::new(output_path)) else {
+        eprintln!("ERROR: {} must end in .ips or .bps", output_path);
+        std::process::exit(2);
+    };
+
+    let result = write_patch(format, original_path, modified_path, output_path);
+
+    match result {
+        Ok(size) => println!("INFO: Wrote {} byte patch to {}", size, output_path),
+        Err(error) => {
+            eprintln!("ERROR: Could not create {}: {}", output_path, error);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Renders a song from an NSF or NSFe file to a WAV file.
+fn render_nsf(args: &[String]) {
+    let [nsf_path, output_path, options @ ..] = args else {
+        eprintln!("{}", USAGE);
+        std::process::exit(2);
+    };
+
+    let song = options.first().map(|song| song.parse::<u8>()).transpose();
+    let seconds = options.get(1).map(|seconds| seconds.parse::<u64>()).transpose();
+    let (Ok(song), Ok(seconds), true) = (song, seconds, options.len() <= 2) else {
+        eprintln!("{}", USAGE);
+        std::process::exit(2);
+    };
+    let seconds = seconds.unwrap_or(DEFAULT_WAV_SECONDS);
+
+    let player = Cartridge::load_from_file(nsf_path)
+        .and_then(|cartridge| NsfPlayer::new(cartridge, nsf_player::DEFAULT_SAMPLE_RATE));
+    let mut player = match player {
+        Ok(player) => player,
+        Err(error) => {
+            eprintln!("ERROR: Could not play {}: {}", nsf_path, error);
+            std::process::exit(1);
+        }
+    };
+
+    if let Some(song) = song {
+        player.start_song(song);
+    }
+
+    let samples = player.render(Duration::from_secs(seconds));
+    match nsf_player::write_wav(Path::new(output_path), &samples, player.sample_rate()) {
+        Ok(()) => println!("INFO: Wrote {} seconds of {} to {}", seconds, player.song_title(), output_path),
+        Err(error) => {
+            eprintln!("ERROR: Could not write {}: {}", output_path, error);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn prompt_for_entry(entries: &[String]) -> io::Result<String> {
+    println!("The archive contains several ROMs:");
+    for (index, entry) in entries.iter().enumerate() {
+        println!("  {}) {}", index + 1, entry);
+    }
+
+    let stdin = io::stdin();
+    loop {
+        print!("Select a ROM [1-{}]: ", entries.len());
+        io::stdout().flush()?;
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "No ROM was selected."));
+        }
+
+        match line.trim().parse::<usize>() {
+            Ok(choice) if (1..=entries.len()).contains(&choice) => return Ok(entries[choice - 1].clone()),
+            _ => println!("Please enter a number between 1 and {}.", entries.len()),
+        }
+    }
+}
+
+fn write_patch(format: PatchFormat, original_path: &str, modified_path: &str, output_path: &str) -> Result<usize, CartridgeError> {
+    let original = fs::read(original_path)?;
+    let modified = fs::read(modified_path)?;
+
+    let patch = patch::create(format, &original, &modified)?;
+    fs::write(output_path, &patch)?;
+
+    Ok(patch.len())
+}
@@ -0,0 +1,65 @@
+//! # chr_memory.rs
+//!
+//!  Author: Paul Hazen
+//! Created: 2026-10-19
+//! License: MIT (see LICENSE file)
+//!
+//! ## Description
+//! The memory behind the PPU's pattern tables. Boards either carry CHR-ROM,
+//! or - when the header reports no CHR-ROM - CHR-RAM that the game fills with
+//! tiles through PPUDATA ($2007).
+use crate::cartridge::Cartridge;
+
+/// CHR-RAM size assumed when the header does not give one.
+const DEFAULT_CHR_RAM_SIZE: usize = 0x2000;
+
+pub struct ChrMemory {
+    data: Vec<u8>,
+    is_ram: bool,
+}
+
+impl ChrMemory {
+    /// Creates the pattern table memory for the cartridge. CHR-ROM is used
+    /// when present, otherwise CHR-RAM is allocated using the size from the
+    /// header (volatile and battery backed combined), defaulting to 8 KB.
+    pub fn new(cartridge: &Cartridge) -> Self {
+        if !cartridge.chr_rom.is_empty() {
+            return Self {
+                data: cartridge.chr_rom.clone(),
+                is_ram: false,
+            };
+        }
+
+        let header = &cartridge.header;
+        let size = match header.chr_ram_size + header.chr_nvram_size {
+            0 => DEFAULT_CHR_RAM_SIZE,
+            size => size,
+        };
+
+        Self {
+            data: vec![0; size],
+            is_ram: true,
+        }
+    }
+
+    /// Whether the pattern tables can be written to.
+    pub fn is_ram(&self) -> bool {
+        self.is_ram
+    }
+
+    /// Reads a byte from the given bank.
+    pub fn read(&self, bank: usize, bank_size: usize, address: u16) -> u8 {
+        super::read_banked(&self.data, bank, bank_size, address)
+    }
+
+    /// Writes a byte to the given bank. Writes to CHR-ROM are ignored.
+    pub fn write(&mut self, bank: usize, bank_size: usize, address: u16, value: u8) {
+        if !self.is_ram || self.data.is_empty() {
+            return;
+        }
+
+        let offset = super::bank_offset(bank, bank_size, self.data.len()) + (address as usize % bank_size);
+        let length = self.data.len();
+        self.data[offset % length] = value;
+    }
+}
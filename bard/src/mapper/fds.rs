@@ -0,0 +1,471 @@
+//! # fds.rs
+//!
+//!  Author: Paul Hazen
+//! Created: 2026-10-19
+//! License: MIT (see LICENSE file)
+//!
+//! ## Description
+//! Mapper 20 - the Famicom Disk System RAM adapter. It carries 32 KB of
+//! PRG-RAM at $6000-$DFFF, the 8 KB BIOS at $E000-$FFFF, 8 KB of CHR-RAM, a
+//! cycle counting IRQ timer and the interface to the disk drive.
+//!
+//! The drive is emulated at the byte level. Each side is laid out the way the
+//! head sees it - a lead-in gap, then every block preceded by a start mark and
+//! followed by its CRC and a gap - and one byte passes under the head roughly
+//! every 149 CPU cycles. The BIOS does the rest.
+//!
+//! CRCs are not checked: the status register always reports a good CRC, and
+//! zeros are written in place of the CRC of a written block.
+use crate::cartridge::{Cartridge, Mirroring};
+use super::{ChrMemory, Mapper};
+
+const PRG_RAM_SIZE: usize = 0x8000;
+const PRG_RAM_START: u16 = 0x6000;
+const BIOS_START: u16 = 0xE000;
+
+/// Bytes of zeros before the first block (28300 bits).
+const LEAD_IN_GAP: usize = 28_300 / 8;
+/// Bytes of zeros after each block (976 bits).
+const BLOCK_GAP: usize = 976 / 8;
+const BLOCK_START_MARK: u8 = 0x80;
+/// Stands in for the CRC of blocks loaded from an image.
+const PLACEHOLDER_CRC: [u8; 2] = [0x4D, 0x62];
+/// The smallest a side is made, leaving room for files the game writes.
+const MIN_RAW_SIDE_SIZE: usize = 0x14000;
+
+/// CPU cycles between bytes passing under the head (96.4 kbit/s).
+const BYTE_DELAY: u32 = 149;
+/// CPU cycles for the head to return to the start of the disk.
+const HEAD_RETURN_DELAY: u32 = 50_000;
+/// CPU cycles a swapped disk is left out of the drive, long enough for the
+/// BIOS to notice that the disk changed.
+const DISK_SWAP_DELAY: u32 = 1_789_773;
+
+/* $4022 - IRQ control
+ * --- | Bit | Function
+ *     |  0  | Repeat: reload the counter instead of stopping
+ *     |  1  | Enable the timer
+ */
+const IRQ_CONTROL_REPEAT: u8 = 0b0000_0001;
+const IRQ_CONTROL_ENABLE: u8 = 0b0000_0010;
+
+/* $4023 - Master I/O enable
+ * --- | Bit | Function
+ *     |  0  | Enable the disk registers (and timer)
+ *     |  1  | Enable the sound registers
+ */
+const IO_ENABLE_DISK: u8 = 0b0000_0001;
+
+/* $4025 - FDS control
+ * --- | Bit | Function
+ *     |  0  | Motor on
+ *     |  1  | Transfer reset
+ *     |  2  | Mode (0 = write, 1 = read)
+ *     |  3  | Mirroring (0 = vertical, 1 = horizontal)
+ *     |  4  | CRC control - set while the CRC is transferred
+ *     |  5  | Always 1
+ *     |  6  | Drive ready - clear while the head crosses a gap
+ *     |  7  | Disk IRQ enable
+ */
+const CONTROL_MOTOR_ON: u8 = 0b0000_0001;
+const CONTROL_TRANSFER_RESET: u8 = 0b0000_0010;
+const CONTROL_READ_MODE: u8 = 0b0000_0100;
+const CONTROL_HORIZONTAL: u8 = 0b0000_1000;
+const CONTROL_CRC: u8 = 0b0001_0000;
+const CONTROL_DRIVE_READY: u8 = 0b0100_0000;
+const CONTROL_DISK_IRQ: u8 = 0b1000_0000;
+
+/* $4030 - Disk status
+ * --- | Bit | Function
+ *     |  0  | Timer IRQ occurred
+ *     |  1  | Byte transferred
+ *     |  4  | CRC error
+ *     |  6  | End of disk
+ */
+const STATUS_TIMER_IRQ: u8 = 0b0000_0001;
+const STATUS_BYTE_TRANSFERRED: u8 = 0b0000_0010;
+const STATUS_END_OF_HEAD: u8 = 0b0100_0000;
+
+/* $4032 - Drive status
+ * --- | Bit | Function
+ *     |  0  | No disk inserted
+ *     |  1  | Not ready
+ *     |  2  | Write protected (always set without a disk)
+ */
+const DRIVE_NO_DISK: u8 = 0b0000_0001;
+const DRIVE_NOT_READY: u8 = 0b0000_0010;
+const DRIVE_WRITE_PROTECTED: u8 = 0b0000_0100;
+
+/// $4033 bit 7 - the batteries powering the drive are good.
+const EXTERNAL_BATTERY_GOOD: u8 = 0b1000_0000;
+
+pub struct Fds {
+    bios: Vec<u8>,
+    prg_ram: Vec<u8>,
+    chr: ChrMemory,
+    mirroring: Mirroring,
+
+    /// Every side as the head sees it, `side_size` bytes each.
+    disk: Vec<u8>,
+    side_size: usize,
+    side_count: usize,
+    inserted_side: Option<usize>,
+    /// The side that goes in once `insert_delay` runs out.
+    pending_side: Option<usize>,
+    insert_delay: u32,
+
+    disk_registers_enabled: bool,
+
+    // IRQ timer
+    irq_reload: u16,
+    irq_counter: u16,
+    irq_repeat: bool,
+    timer_enabled: bool,
+    timer_irq: bool,
+
+    // Drive
+    motor_on: bool,
+    transfer_reset: bool,
+    read_mode: bool,
+    crc_control: bool,
+    drive_ready: bool,
+    disk_irq_enabled: bool,
+    disk_irq: bool,
+    head_position: usize,
+    delay: u32,
+    end_of_head: bool,
+    scanning: bool,
+    gap_ended: bool,
+    byte_transferred: bool,
+    read_data: u8,
+    write_data: u8,
+}
+
+impl Fds {
+    pub fn new(cartridge: &Cartridge) -> Self {
+        let raw_sides: Vec<Vec<u8>> = cartridge
+            .disk
+            .iter()
+            .flat_map(|image| image.sides.iter())
+            .map(|side| Self::add_gaps(side))
+            .collect();
+
+        let side_size = raw_sides.iter().map(Vec::len).max().unwrap_or(0).max(MIN_RAW_SIDE_SIZE);
+        let mut disk = Vec::with_capacity(side_size * raw_sides.len());
+        for mut side in raw_sides.iter().cloned() {
+            side.resize(side_size, 0);
+            disk.append(&mut side);
+        }
+
+        Self {
+            bios: cartridge.prg_rom.clone(),
+            prg_ram: vec![0; PRG_RAM_SIZE],
+            chr: ChrMemory::new(cartridge),
+            mirroring: Mirroring::Horizontal,
+            disk,
+            side_size,
+            side_count: raw_sides.len(),
+            inserted_side: (!raw_sides.is_empty()).then_some(0),
+            pending_side: None,
+            insert_delay: 0,
+            disk_registers_enabled: false,
+            irq_reload: 0,
+            irq_counter: 0,
+            irq_repeat: false,
+            timer_enabled: false,
+            timer_irq: false,
+            motor_on: false,
+            transfer_reset: false,
+            read_mode: true,
+            crc_control: false,
+            drive_ready: false,
+            disk_irq_enabled: false,
+            disk_irq: false,
+            head_position: 0,
+            delay: 0,
+            end_of_head: true,
+            scanning: false,
+            gap_ended: false,
+            byte_transferred: false,
+            read_data: 0,
+            write_data: 0,
+        }
+    }
+
+    /// Lays a side out the way the head reads it, with the gaps, start marks
+    /// and CRCs that `.fds` images leave out.
+    fn add_gaps(side: &[u8]) -> Vec<u8> {
+        let mut raw = vec![0; LEAD_IN_GAP];
+        let mut position = 0;
+        let mut file_size = 0;
+
+        while position < side.len() {
+            let length = match side[position] {
+                1 => 56,
+                2 => 2,
+                3 => 16,
+                4 => 1 + file_size,
+                _ => break, // The rest of the side is unused
+            };
+            if position + length > side.len() {
+                break;
+            }
+
+            if side[position] == 3 {
+                file_size = u16::from_le_bytes([side[position + 13], side[position + 14]]) as usize;
+            }
+
+            raw.push(BLOCK_START_MARK);
+            raw.extend_from_slice(&side[position..position + length]);
+            raw.extend_from_slice(&PLACEHOLDER_CRC);
+            raw.extend(std::iter::repeat_n(0, BLOCK_GAP));
+            position += length;
+        }
+
+        raw
+    }
+
+    /// Moves the disk under the head by one CPU cycle.
+    fn clock_drive(&mut self) {
+        if self.insert_delay > 0 {
+            self.insert_delay -= 1;
+            if self.insert_delay == 0 {
+                self.inserted_side = self.pending_side.take();
+            }
+            return;
+        }
+
+        let Some(side) = self.inserted_side else {
+            self.end_of_head = true;
+            self.scanning = false;
+            return;
+        };
+
+        if !self.motor_on {
+            self.end_of_head = true;
+            self.scanning = false;
+            return;
+        }
+
+        if self.transfer_reset && !self.scanning {
+            return;
+        }
+
+        if self.end_of_head {
+            // Wind back to the start of the disk
+            self.delay = HEAD_RETURN_DELAY;
+            self.end_of_head = false;
+            self.head_position = 0;
+            self.gap_ended = false;
+            return;
+        }
+
+        if self.delay > 0 {
+            self.delay -= 1;
+            return;
+        }
+
+        self.scanning = true;
+        let offset = side * self.side_size + self.head_position;
+
+        if self.read_mode {
+            let data = self.disk[offset];
+            let mut irq = self.disk_irq_enabled;
+
+            if !self.drive_ready {
+                self.gap_ended = false;
+            } else if data != 0 && !self.gap_ended {
+                // The start mark ends the gap, the block follows it
+                self.gap_ended = true;
+                irq = false;
+            }
+
+            if self.gap_ended {
+                self.byte_transferred = true;
+                self.read_data = data;
+                self.disk_irq |= irq;
+            }
+        } else {
+            let mut data = 0;
+            if !self.crc_control {
+                self.byte_transferred = true;
+                self.disk_irq |= self.disk_irq_enabled;
+                data = self.write_data;
+            }
+
+            // Gaps are written while the drive is not ready
+            if !self.drive_ready {
+                data = 0;
+            }
+
+            self.disk[offset] = data;
+            self.gap_ended = false;
+        }
+
+        self.head_position += 1;
+        if self.head_position >= self.side_size {
+            self.motor_on = false;
+        } else {
+            self.delay = BYTE_DELAY;
+        }
+    }
+
+    fn clock_timer(&mut self) {
+        if !self.timer_enabled {
+            return;
+        }
+
+        if self.irq_counter == 0 {
+            self.timer_irq = true;
+            self.irq_counter = self.irq_reload;
+            self.timer_enabled = self.irq_repeat;
+        } else {
+            self.irq_counter -= 1;
+        }
+    }
+
+    fn read_register(&mut self, address: u16) -> Option<u8> {
+        match address {
+            0x4030 => {
+                let mut status = 0;
+                if self.timer_irq {
+                    status |= STATUS_TIMER_IRQ;
+                }
+                if self.byte_transferred {
+                    status |= STATUS_BYTE_TRANSFERRED;
+                }
+                if self.end_of_head {
+                    status |= STATUS_END_OF_HEAD;
+                }
+
+                // Reading the status acknowledges both IRQs
+                self.timer_irq = false;
+                self.disk_irq = false;
+                self.byte_transferred = false;
+                Some(status)
+            }
+            0x4031 => {
+                self.byte_transferred = false;
+                self.disk_irq = false;
+                Some(self.read_data)
+            }
+            0x4032 => {
+                let mut status = 0;
+                if self.inserted_side.is_none() {
+                    status |= DRIVE_NO_DISK | DRIVE_NOT_READY | DRIVE_WRITE_PROTECTED;
+                } else if !self.scanning {
+                    status |= DRIVE_NOT_READY;
+                }
+                Some(status)
+            }
+            0x4033 => Some(EXTERNAL_BATTERY_GOOD),
+            _ => None,
+        }
+    }
+
+    fn write_register(&mut self, address: u16, value: u8) {
+        match address {
+            0x4020 => self.irq_reload = (self.irq_reload & 0xFF00) | value as u16,
+            0x4021 => self.irq_reload = (self.irq_reload & 0x00FF) | ((value as u16) << 8),
+            0x4022 => {
+                self.irq_repeat = value & IRQ_CONTROL_REPEAT != 0;
+                self.timer_enabled = value & IRQ_CONTROL_ENABLE != 0;
+                if self.timer_enabled {
+                    self.irq_counter = self.irq_reload;
+                } else {
+                    self.timer_irq = false;
+                }
+            }
+            0x4024 => {
+                self.write_data = value;
+                self.byte_transferred = false;
+                self.disk_irq = false;
+            }
+            0x4025 => {
+                self.motor_on = value & CONTROL_MOTOR_ON != 0;
+                self.transfer_reset = value & CONTROL_TRANSFER_RESET != 0;
+                self.read_mode = value & CONTROL_READ_MODE != 0;
+                self.mirroring = if value & CONTROL_HORIZONTAL != 0 { Mirroring::Horizontal } else { Mirroring::Vertical };
+                self.crc_control = value & CONTROL_CRC != 0;
+                self.drive_ready = value & CONTROL_DRIVE_READY != 0;
+                self.disk_irq_enabled = value & CONTROL_DISK_IRQ != 0;
+                self.disk_irq = false;
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Mapper for Fds {
+    fn cpu_read(&mut self, address: u16) -> Option<u8> {
+        match address {
+            0x4030..=0x4033 if self.disk_registers_enabled => self.read_register(address),
+            PRG_RAM_START..BIOS_START => Some(self.prg_ram[(address - PRG_RAM_START) as usize]),
+            BIOS_START..=0xFFFF if !self.bios.is_empty() => {
+                Some(self.bios[(address - BIOS_START) as usize % self.bios.len()])
+            }
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        match address {
+            0x4023 => {
+                self.disk_registers_enabled = value & IO_ENABLE_DISK != 0;
+                if !self.disk_registers_enabled {
+                    self.timer_enabled = false;
+                    self.timer_irq = false;
+                    self.disk_irq = false;
+                }
+            }
+            0x4020..=0x4026 if self.disk_registers_enabled => self.write_register(address, value),
+            PRG_RAM_START..BIOS_START => self.prg_ram[(address - PRG_RAM_START) as usize] = value,
+            _ => {} // The BIOS is ROM
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        self.chr.read(0, 0x2000, address)
+    }
+
+    fn ppu_write(&mut self, address: u16, value: u8) {
+        self.chr.write(0, 0x2000, address, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn cpu_clock(&mut self) {
+        self.clock_timer();
+        self.clock_drive();
+    }
+
+    fn irq_pending(&self) -> bool {
+        self.timer_irq || self.disk_irq
+    }
+
+    /// The disk sides, so that files the game writes are kept like a
+    /// battery save.
+    fn save_ram(&self) -> Option<&[u8]> {
+        (!self.disk.is_empty()).then_some(self.disk.as_slice())
+    }
+
+    fn save_ram_mut(&mut self) -> Option<&mut [u8]> {
+        (!self.disk.is_empty()).then_some(self.disk.as_mut_slice())
+    }
+
+    fn disk_side_count(&self) -> usize {
+        self.side_count
+    }
+
+    fn inserted_disk_side(&self) -> Option<usize> {
+        self.inserted_side
+    }
+
+    fn insert_disk_side(&mut self, side: Option<usize>) {
+        self.inserted_side = None;
+        self.pending_side = side.filter(|&side| side < self.side_count);
+        self.insert_delay = if self.pending_side.is_some() { DISK_SWAP_DELAY } else { 0 };
+    }
+}
@@ -0,0 +1,61 @@
+//! # four_screen_vram.rs
+//!
+//!  Author: Paul Hazen
+//! Created: 2026-10-19
+//! License: MIT (see LICENSE file)
+//!
+//! ## Description
+//! The extra 2 KB of nametable RAM carried by four-screen boards. The PPU's
+//! own VRAM holds nametables 0 and 1, the cartridge supplies 2 and 3.
+use crate::cartridge::{Cartridge, Mirroring};
+
+const FOUR_SCREEN_VRAM_SIZE: usize = 0x0800;
+
+pub struct FourScreenVram {
+    data: Vec<u8>,
+}
+
+impl FourScreenVram {
+    /// Allocates the VRAM if the header asks for a four-screen layout.
+    pub fn new(cartridge: &Cartridge) -> Self {
+        Self::with_mirroring(cartridge.header.mirroring)
+    }
+
+    /// Allocates the VRAM if the board's layout is four-screen, for boards
+    /// that decide this themselves rather than from the header.
+    pub fn with_mirroring(mirroring: Mirroring) -> Self {
+        let size = match mirroring {
+            Mirroring::FourScreen => FOUR_SCREEN_VRAM_SIZE,
+            _ => 0,
+        };
+
+        Self { data: vec![0; size] }
+    }
+
+    /// Reads a nametable byte, `None` if the address is not backed by the
+    /// cartridge.
+    pub fn read(&self, address: u16) -> Option<u8> {
+        self.offset(address).map(|offset| self.data[offset])
+    }
+
+    /// Writes a nametable byte, returning whether the cartridge consumed it.
+    pub fn write(&mut self, address: u16, value: u8) -> bool {
+        match self.offset(address) {
+            Some(offset) => {
+                self.data[offset] = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn offset(&self, address: u16) -> Option<usize> {
+        let table = ((address >> 10) & 0x03) as usize;
+
+        if self.data.is_empty() || table < 2 {
+            return None;
+        }
+
+        Some((table - 2) * 0x0400 + (address as usize & 0x03FF))
+    }
+}
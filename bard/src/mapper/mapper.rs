@@ -0,0 +1,169 @@
+//! # mapper.rs
+//!
+//!  Author: Paul Hazen
+//! Created: 2026-10-19
+//! License: MIT (see LICENSE file)
+//!
+//! ## Description
+//! Contains the Mapper trait - the interface between the CPU/PPU buses and the
+//! circuitry on a cartridge board that decides which ROM/RAM bank answers a
+//! given address.
+use crate::cartridge::{Cartridge, Mirroring};
+
+/// Identifies which part of the PPU is driving the PPU address bus. Boards
+/// such as the MMC5 bank sprite and background patterns independently.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PpuFetchSource {
+    /// Nametable, attribute and pattern fetches for the background.
+    Background,
+    /// Pattern fetches (and the garbage nametable fetches) for sprites.
+    Sprite,
+    /// Accesses made by the CPU through PPUDATA ($2007).
+    Cpu,
+}
+
+pub trait Mapper {
+
+    /// Reads a byte from the cartridge side of the CPU address space
+    /// ($4020-$FFFF).
+    ///
+    /// # Returns
+    ///
+    /// * `Some(u8)` if the cartridge drives the data bus for this address.
+    /// * `None` if nothing on the cartridge responds (open bus).
+    fn cpu_read(&mut self, address: u16) -> Option<u8>;
+
+    /// Writes a byte to the cartridge side of the CPU address space. Writes to
+    /// ROM are how most mappers receive their bank switching commands.
+    fn cpu_write(&mut self, address: u16, value: u8);
+
+    /// Reads a byte from the pattern tables ($0000-$1FFF) of the PPU address
+    /// space.
+    fn ppu_read(&mut self, address: u16) -> u8;
+
+    /// Writes a byte to the pattern tables ($0000-$1FFF) of the PPU address
+    /// space. Ignored by boards that only carry CHR-ROM.
+    fn ppu_write(&mut self, _address: u16, _value: u8) {}
+
+    /// Reads a byte from the nametables ($2000-$2FFF). Every nametable read
+    /// made by the PPU is offered to the cartridge first.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(u8)` if the cartridge supplies the byte itself.
+    /// * `None` if the PPU's internal VRAM should answer.
+    fn nametable_read(&mut self, _address: u16) -> Option<u8> {
+        None
+    }
+
+    /// Writes a byte to the nametables ($2000-$2FFF).
+    ///
+    /// # Returns
+    ///
+    /// * `true` if the cartridge consumed the write.
+    /// * `false` if the PPU's internal VRAM should be written.
+    fn nametable_write(&mut self, _address: u16, _value: u8) -> bool {
+        false
+    }
+
+    /// The nametable layout the board is currently selecting.
+    fn mirroring(&self) -> Mirroring;
+
+    /// Which 1 KB page of the PPU's internal VRAM (CIRAM) a nametable address
+    /// ($2000-$2FFF) selects. Boards drive CIRAM A10 themselves - by default
+    /// this follows `mirroring()`.
+    fn ciram_page(&self, address: u16) -> u16 {
+        mirroring_page(self.mirroring(), address)
+    }
+
+    /// Called by the PPU before it starts a group of fetches, so the board
+    /// knows which unit the following reads belong to.
+    fn set_ppu_fetch_source(&mut self, _source: PpuFetchSource) {}
+
+    /// Called when the game changes the sprite size in PPUCTRL ($2000).
+    fn set_ppu_tall_sprites(&mut self, _tall_sprites: bool) {}
+
+    /// Called with writes to $4016, which set the CPU's OUT0-OUT2 pins. Vs.
+    /// System boards take their bank select from OUT2.
+    fn out_write(&mut self, _value: u8) {}
+
+    /// Advances any cycle driven circuitry (IRQ counters) by one CPU cycle.
+    fn cpu_clock(&mut self) {}
+
+    /// Whether the board is currently asserting the CPU's IRQ line.
+    fn irq_pending(&self) -> bool {
+        false
+    }
+
+    /// The RAM kept alive by the battery on boards that have one, `None` for
+    /// boards without PRG-RAM.
+    fn save_ram(&self) -> Option<&[u8]> {
+        None
+    }
+
+    /// Mutable access to the battery backed RAM, used to restore a save.
+    fn save_ram_mut(&mut self) -> Option<&mut [u8]> {
+        None
+    }
+
+    /// The number of disk sides that can be put in the drive, 0 for boards
+    /// without one.
+    fn disk_side_count(&self) -> usize {
+        0
+    }
+
+    /// The disk side in the drive, `None` when it is empty.
+    fn inserted_disk_side(&self) -> Option<usize> {
+        None
+    }
+
+    /// Ejects the disk and, when `side` is given, inserts that side once the
+    /// drive has had time to notice the change.
+    fn insert_disk_side(&mut self, _side: Option<usize>) {}
+}
+
+/// Resolves a nametable address to one of the two 1 KB pages of CIRAM for a
+/// fixed mirroring layout. Four-screen boards answer nametables 2 and 3 from
+/// their own VRAM, so only the first two reach CIRAM.
+pub fn mirroring_page(mirroring: Mirroring, address: u16) -> u16 {
+    let table = (address >> 10) & 0x03;
+
+    match mirroring {
+        Mirroring::Horizontal => table >> 1,
+        Mirroring::Vertical => table & 0x01,
+        Mirroring::SingleScreenA => 0,
+        Mirroring::SingleScreenB => 1,
+        Mirroring::FourScreen => table & 0x01,
+    }
+}
+
+/// Offset of $7000 within the $6000-$7FFF PRG-RAM window.
+const TRAINER_OFFSET: usize = 0x1000;
+
+/// Copies the cartridge's trainer (if any) into PRG-RAM at $7000-$71FF, where
+/// copier devices placed it before starting the game.
+pub fn load_trainer(prg_ram: &mut [u8], cartridge: &Cartridge) {
+    let end = TRAINER_OFFSET + cartridge.trainer.len();
+
+    if !cartridge.trainer.is_empty() && prg_ram.len() >= end {
+        prg_ram[TRAINER_OFFSET..end].copy_from_slice(&cartridge.trainer);
+    }
+}
+
+/// Resolves a bank number to a byte offset within a ROM/RAM of `total_size`
+/// bytes. Bank numbers wrap, the same way unconnected high address lines do on
+/// a real board.
+pub fn bank_offset(bank: usize, bank_size: usize, total_size: usize) -> usize {
+    let bank_count = (total_size / bank_size).max(1);
+    (bank % bank_count) * bank_size
+}
+
+/// Reads a byte from `data`, returning 0 for boards without that memory.
+pub fn read_banked(data: &[u8], bank: usize, bank_size: usize, address: u16) -> u8 {
+    if data.is_empty() {
+        return 0;
+    }
+
+    let offset = bank_offset(bank, bank_size, data.len()) + (address as usize % bank_size);
+    data[offset % data.len()]
+}
@@ -0,0 +1,64 @@
+mod mapper;
+mod chr_memory;
+mod four_screen_vram;
+mod nrom;
+mod uxrom;
+mod fds;
+mod nsf;
+mod mmc5;
+mod vrc_irq;
+mod vrc4;
+mod vrc6;
+mod vrc7;
+mod vs_unisystem;
+
+use std::{cell::RefCell, rc::Rc};
+use crate::cartridge::{Cartridge, CartridgeError};
+
+pub use mapper::{mirroring_page, Mapper, PpuFetchSource};
+pub use chr_memory::ChrMemory;
+pub use four_screen_vram::FourScreenVram;
+pub use nrom::Nrom;
+pub use uxrom::Uxrom;
+pub use fds::Fds;
+pub use nsf::Nsf;
+pub use mmc5::Mmc5;
+pub use vrc4::Vrc4;
+pub use vrc6::Vrc6;
+pub use vrc7::Vrc7;
+pub use vs_unisystem::VsUnisystem;
+use mapper::{bank_offset, load_trainer, read_banked};
+use vrc_irq::VrcIrq;
+
+/// A mapper shared between the CPU and PPU buses.
+pub type SharedMapper = Rc<RefCell<dyn Mapper>>;
+
+/// Creates the mapper for the board described by the cartridge header.
+///
+/// # Returns
+///
+/// * `Result<SharedMapper, CartridgeError>` - The mapper, or `UnsupportedMapper` if no mapper implements the board.
+pub fn try_create(cartridge: &Cartridge) -> Result<SharedMapper, CartridgeError> {
+    let mapper_number = cartridge.header.mapper_id;
+
+    let mapper: SharedMapper = match mapper_number {
+        0 => Rc::new(RefCell::new(Nrom::new(cartridge))),
+        2 => Rc::new(RefCell::new(Uxrom::new(cartridge))),
+        5 => Rc::new(RefCell::new(Mmc5::new(cartridge))),
+        20 => Rc::new(RefCell::new(Fds::new(cartridge))),
+        21 | 22 | 23 | 25 => Rc::new(RefCell::new(Vrc4::new(cartridge))),
+        24 | 26 => Rc::new(RefCell::new(Vrc6::new(cartridge))),
+        31 => Rc::new(RefCell::new(Nsf::new(cartridge))),
+        85 => Rc::new(RefCell::new(Vrc7::new(cartridge))),
+        99 => Rc::new(RefCell::new(VsUnisystem::new(cartridge))),
+        _ => return Err(CartridgeError::UnsupportedMapper(mapper_number)),
+    };
+    Ok(mapper)
+}
+
+/// Creates the mapper for the board described by the cartridge header.
+/// Unsupported mappers fall back to NROM so that the ROM can at least be
+/// inspected; use `try_create` to find out when that happens.
+pub fn create(cartridge: &Cartridge) -> SharedMapper {
+    try_create(cartridge).unwrap_or_else(|_| Rc::new(RefCell::new(Nrom::new(cartridge))))
+}
@@ -0,0 +1,93 @@
+//! # nrom.rs
+//!
+//!  Author: Paul Hazen
+//! Created: 2026-10-19
+//! License: MIT (see LICENSE file)
+//!
+//! ## Description
+//! Mapper 0 (NROM) - no bank switching. 16 KB of PRG-ROM is mirrored into
+//! $C000-$FFFF, 32 KB is mapped directly. Boards with a battery (Family
+//! BASIC) and ROMs with a trainer get 8 KB of PRG-RAM at $6000-$7FFF.
+//! Mirroring is hard-wired by the board, including four-screen VRAM.
+use crate::cartridge::{Cartridge, Mirroring};
+use super::{ChrMemory, FourScreenVram, Mapper};
+
+const PRG_RAM_SIZE: usize = 0x2000;
+
+pub struct Nrom {
+    prg_rom: Vec<u8>,
+    chr: ChrMemory,
+    prg_ram: Vec<u8>,
+    mirroring: Mirroring,
+    four_screen_vram: FourScreenVram,
+}
+
+impl Nrom {
+    pub fn new(cartridge: &Cartridge) -> Self {
+        let mut prg_ram = if cartridge.header.has_battery || !cartridge.trainer.is_empty() {
+            vec![0; PRG_RAM_SIZE]
+        } else {
+            vec![]
+        };
+        super::load_trainer(&mut prg_ram, cartridge);
+
+        Self {
+            prg_rom: cartridge.prg_rom.clone(),
+            chr: ChrMemory::new(cartridge),
+            prg_ram,
+            mirroring: cartridge.header.mirroring,
+            four_screen_vram: FourScreenVram::new(cartridge),
+        }
+    }
+}
+
+impl Mapper for Nrom {
+    fn cpu_read(&mut self, address: u16) -> Option<u8> {
+        if (0x6000..0x8000).contains(&address) && !self.prg_ram.is_empty() {
+            return Some(self.prg_ram[address as usize % PRG_RAM_SIZE]);
+        }
+
+        if address < 0x8000 || self.prg_rom.is_empty() {
+            return None;
+        }
+
+        let offset = (address - 0x8000) as usize % self.prg_rom.len();
+        Some(self.prg_rom[offset])
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        if (0x6000..0x8000).contains(&address) && !self.prg_ram.is_empty() {
+            self.prg_ram[address as usize % PRG_RAM_SIZE] = value;
+        }
+
+        // NROM has no registers, writes to ROM are ignored.
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        self.chr.read(0, 0x2000, address)
+    }
+
+    fn ppu_write(&mut self, address: u16, value: u8) {
+        self.chr.write(0, 0x2000, address, value);
+    }
+
+    fn nametable_read(&mut self, address: u16) -> Option<u8> {
+        self.four_screen_vram.read(address)
+    }
+
+    fn nametable_write(&mut self, address: u16, value: u8) -> bool {
+        self.four_screen_vram.write(address, value)
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn save_ram(&self) -> Option<&[u8]> {
+        (!self.prg_ram.is_empty()).then_some(self.prg_ram.as_slice())
+    }
+
+    fn save_ram_mut(&mut self) -> Option<&mut [u8]> {
+        (!self.prg_ram.is_empty()).then_some(self.prg_ram.as_mut_slice())
+    }
+}
@@ -0,0 +1,179 @@
+//! # nsf.rs
+//!
+//!  Author: Paul Hazen
+//! Created: 2026-10-19
+//! License: MIT (see LICENSE file)
+//!
+//! ## Description
+//! Mapper 31 - NSF style bank switching. $8000-$FFFF is split into eight 4 KB
+//! windows, each selecting a PRG-ROM bank. The synthetic cartridges built
+//! for NSF files use it with the registers at $5FF8-$5FFF; iNES ROMs on
+//! mapper 31 boards mirror the registers across $5000-$5FFF.
+//!
+//! Tunes for the FDS run from RAM at $6000-$DFFF instead. Selecting a bank for
+//! one of those windows ($5FF6-$5FFD) copies it into the RAM. Tunes for the
+//! MMC5 get its 1 KB of ExRAM at $5C00-$5FF5. Writes to the other expansion
+//! sound chips are ignored.
+use crate::cartridge::nsf::{ExpansionChips, BANK_SIZE};
+use crate::cartridge::{Cartridge, Mirroring};
+use super::{ChrMemory, Mapper};
+
+const PRG_RAM_SIZE: usize = 0x2000;
+const FDS_RAM_SIZE: usize = 0x8000;
+const EXRAM_SIZE: usize = 0x400;
+
+const PRG_RAM_START: u16 = 0x6000;
+const PRG_ROM_START: u16 = 0x8000;
+/// The FDS BIOS area, which stays ROM in FDS tunes.
+const FDS_ROM_START: u16 = 0xE000;
+const EXRAM_START: u16 = 0x5C00;
+
+/*
+    Bank Registers
+    ----------------------------------------------------------------
+    | Address     | Function                                       |
+    ----------------------------------------------------------------
+    | $5FF6-$5FF7 | Banks copied to $6000 and $7000 (FDS tunes)    |
+    | $5FF8-$5FFF | Banks for $8000, $9000, ... $F000              |
+    | $5000-$5FFF | Mirrors of $5FF8-$5FFF on mapper 31 boards     |
+    ----------------------------------------------------------------
+ */
+const NSF_REGISTERS_START: u16 = 0x5FF8;
+const FDS_REGISTERS_START: u16 = 0x5FF6;
+const BOARD_REGISTERS_START: u16 = 0x5000;
+
+/// The windows from $6000 to $FFFF, 4 KB each.
+const WINDOW_COUNT: usize = 10;
+
+pub struct Nsf {
+    prg_rom: Vec<u8>,
+    chr: ChrMemory,
+    /// 8 KB at $6000-$7FFF, or 32 KB at $6000-$DFFF for FDS tunes.
+    prg_ram: Vec<u8>,
+    exram: Vec<u8>,
+    /// The selected bank for each window, starting with $6000.
+    banks: [u8; WINDOW_COUNT],
+    /// Whether the registers are the NSF ones ($5FF8-$5FFF) or mirrored
+    /// across $5000-$5FFF as on the boards.
+    nsf_registers: bool,
+    fds: bool,
+    mirroring: Mirroring,
+}
+
+impl Nsf {
+    pub fn new(cartridge: &Cartridge) -> Self {
+        let chips = cartridge.nsf.as_ref().map_or(ExpansionChips::empty(), |nsf| nsf.expansion_chips);
+        let fds = chips.contains(ExpansionChips::FDS);
+
+        let mut mapper = Self {
+            prg_rom: cartridge.prg_rom.clone(),
+            chr: ChrMemory::new(cartridge),
+            prg_ram: vec![0; if fds { FDS_RAM_SIZE } else { PRG_RAM_SIZE }],
+            exram: if chips.contains(ExpansionChips::MMC5) { vec![0; EXRAM_SIZE] } else { vec![] },
+            banks: [0; WINDOW_COUNT],
+            nsf_registers: cartridge.nsf.is_some(),
+            fds,
+            mirroring: cartridge.header.mirroring,
+        };
+        mapper.reset_banks(cartridge);
+        mapper
+    }
+
+    /// Selects the banks a tune starts with. The boards start with the last
+    /// bank at $F000.
+    fn reset_banks(&mut self, cartridge: &Cartridge) {
+        let Some(nsf) = &cartridge.nsf else {
+            self.banks[WINDOW_COUNT - 1] = 0xFF;
+            return;
+        };
+
+        if nsf.is_bank_switched() {
+            for (window, &bank) in nsf.banks.iter().enumerate() {
+                self.select_bank(window + 2, bank);
+            }
+            // FDS tunes also start with banks 6 and 7 at $6000-$7FFF
+            if self.fds {
+                self.select_bank(0, nsf.banks[6]);
+                self.select_bank(1, nsf.banks[7]);
+            }
+        } else {
+            // The image starts at the window the program is laid out from
+            let first_window = ((nsf.image_base() - PRG_RAM_START) as usize) / BANK_SIZE;
+            for window in first_window..WINDOW_COUNT {
+                self.select_bank(window, (window - first_window) as u8);
+            }
+        }
+    }
+
+    /// Selects the bank for a window, copying it into RAM for the windows
+    /// FDS tunes run from.
+    fn select_bank(&mut self, window: usize, bank: u8) {
+        self.banks[window] = bank;
+
+        let address = PRG_RAM_START + (window * BANK_SIZE) as u16;
+        if self.is_fds_ram(address) && !self.prg_rom.is_empty() {
+            let source = super::bank_offset(bank as usize, BANK_SIZE, self.prg_rom.len());
+            let target = window * BANK_SIZE;
+            self.prg_ram[target..target + BANK_SIZE].copy_from_slice(&self.prg_rom[source..source + BANK_SIZE]);
+        }
+    }
+
+    fn is_fds_ram(&self, address: u16) -> bool {
+        self.fds && (PRG_RAM_START..FDS_ROM_START).contains(&address)
+    }
+
+    fn window(address: u16) -> usize {
+        (address - PRG_RAM_START) as usize / BANK_SIZE
+    }
+}
+
+impl Mapper for Nsf {
+    fn cpu_read(&mut self, address: u16) -> Option<u8> {
+        match address {
+            EXRAM_START..FDS_REGISTERS_START if !self.exram.is_empty() => {
+                Some(self.exram[(address - EXRAM_START) as usize % EXRAM_SIZE])
+            }
+            PRG_RAM_START.. if self.is_fds_ram(address) => {
+                Some(self.prg_ram[(address - PRG_RAM_START) as usize])
+            }
+            PRG_RAM_START..PRG_ROM_START => Some(self.prg_ram[(address - PRG_RAM_START) as usize]),
+            PRG_ROM_START.. if !self.prg_rom.is_empty() => {
+                let bank = self.banks[Self::window(address)] as usize;
+                Some(super::read_banked(&self.prg_rom, bank, BANK_SIZE, address))
+            }
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        match address {
+            NSF_REGISTERS_START..PRG_RAM_START => self.select_bank((address - NSF_REGISTERS_START) as usize + 2, value),
+            FDS_REGISTERS_START..NSF_REGISTERS_START if self.fds => {
+                self.select_bank((address - FDS_REGISTERS_START) as usize, value)
+            }
+            EXRAM_START..FDS_REGISTERS_START if !self.exram.is_empty() => {
+                self.exram[(address - EXRAM_START) as usize % EXRAM_SIZE] = value;
+            }
+            BOARD_REGISTERS_START..PRG_RAM_START if !self.nsf_registers => {
+                self.select_bank((address & 0x07) as usize + 2, value)
+            }
+            PRG_RAM_START.. if self.is_fds_ram(address) => {
+                self.prg_ram[(address - PRG_RAM_START) as usize] = value;
+            }
+            PRG_RAM_START..PRG_ROM_START => self.prg_ram[(address - PRG_RAM_START) as usize] = value,
+            _ => {} // ROM, and the expansion sound registers
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        self.chr.read(0, 0x2000, address)
+    }
+
+    fn ppu_write(&mut self, address: u16, value: u8) {
+        self.chr.write(0, 0x2000, address, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
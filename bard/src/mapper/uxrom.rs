@@ -0,0 +1,87 @@
+//! # uxrom.rs
+//!
+//!  Author: Paul Hazen
+//! Created: 2026-10-19
+//! License: MIT (see LICENSE file)
+//!
+//! ## Description
+//! Mapper 2 (UxROM) - any write to $8000-$FFFF selects the 16 KB PRG bank at
+//! $8000-$BFFF, and the last bank is fixed at $C000-$FFFF. CHR is 8 KB,
+//! usually RAM. On the Vs. System (Vs. Castlevania) the mainboard adds 2 KB
+//! of work RAM mirrored across $6000-$7FFF, and its 4 KB of VRAM gives
+//! four-screen nametables.
+use crate::cartridge::{Cartridge, ConsoleType, Mirroring};
+use super::{ChrMemory, FourScreenVram, Mapper};
+
+const PRG_BANK_SIZE: usize = 0x4000;
+const CHR_BANK_SIZE: usize = 0x2000;
+const VS_WORK_RAM_SIZE: usize = 0x800;
+
+pub struct Uxrom {
+    prg_rom: Vec<u8>,
+    chr: ChrMemory,
+    /// The Vs. System's work RAM, empty on other consoles.
+    work_ram: Vec<u8>,
+    mirroring: Mirroring,
+    four_screen_vram: FourScreenVram,
+    bank: usize,
+}
+
+impl Uxrom {
+    pub fn new(cartridge: &Cartridge) -> Self {
+        let vs_system = cartridge.header.console_type == ConsoleType::VsSystem;
+        let mirroring = if vs_system { Mirroring::FourScreen } else { cartridge.header.mirroring };
+
+        Self {
+            prg_rom: cartridge.prg_rom.clone(),
+            chr: ChrMemory::new(cartridge),
+            work_ram: if vs_system { vec![0; VS_WORK_RAM_SIZE] } else { vec![] },
+            mirroring,
+            four_screen_vram: FourScreenVram::with_mirroring(mirroring),
+            bank: 0,
+        }
+    }
+
+    fn last_bank(&self) -> usize {
+        (self.prg_rom.len() / PRG_BANK_SIZE).saturating_sub(1)
+    }
+}
+
+impl Mapper for Uxrom {
+    fn cpu_read(&mut self, address: u16) -> Option<u8> {
+        match address {
+            0x6000..=0x7FFF if !self.work_ram.is_empty() => Some(self.work_ram[address as usize % VS_WORK_RAM_SIZE]),
+            0x8000..=0xBFFF => Some(super::read_banked(&self.prg_rom, self.bank, PRG_BANK_SIZE, address)),
+            0xC000..=0xFFFF => Some(super::read_banked(&self.prg_rom, self.last_bank(), PRG_BANK_SIZE, address)),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        match address {
+            0x6000..=0x7FFF if !self.work_ram.is_empty() => self.work_ram[address as usize % VS_WORK_RAM_SIZE] = value,
+            0x8000..=0xFFFF => self.bank = value as usize,
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        self.chr.read(0, CHR_BANK_SIZE, address)
+    }
+
+    fn ppu_write(&mut self, address: u16, value: u8) {
+        self.chr.write(0, CHR_BANK_SIZE, address, value);
+    }
+
+    fn nametable_read(&mut self, address: u16) -> Option<u8> {
+        self.four_screen_vram.read(address)
+    }
+
+    fn nametable_write(&mut self, address: u16, value: u8) -> bool {
+        self.four_screen_vram.write(address, value)
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
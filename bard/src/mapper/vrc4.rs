@@ -0,0 +1,230 @@
+//! # vrc4.rs
+//!
+//!  Author: Paul Hazen
+//! Created: 2026-10-19
+//! License: MIT (see LICENSE file)
+//!
+//! ## Description
+//! Mappers 21, 22, 23 and 25 - the Konami VRC2 and VRC4. Both chips decode
+//! their registers from A12-A15 plus two "low" address lines, and each board
+//! revision wires different CPU address lines into those two inputs. The
+//! register address is normalised to $x000-$x003 before being decoded.
+use crate::cartridge::{Cartridge, Mirroring};
+use super::{ChrMemory, Mapper, VrcIrq};
+
+const PRG_BANK_SIZE: usize = 0x2000;
+const CHR_BANK_SIZE: usize = 0x0400;
+const PRG_RAM_SIZE: usize = 0x2000;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Chip {
+    Vrc2,
+    Vrc4,
+}
+
+pub struct Vrc4 {
+    chip: Chip,
+    prg_rom: Vec<u8>,
+    chr: ChrMemory,
+    prg_ram: Vec<u8>,
+
+    /// CPU address lines (OR'd together) that drive the chip's A0 input.
+    a0_lines: u16,
+    /// CPU address lines (OR'd together) that drive the chip's A1 input.
+    a1_lines: u16,
+    /// VRC2a ignores the lowest bit of each CHR bank number.
+    chr_shift: u8,
+
+    prg_banks: [u8; 2],
+    prg_swap_mode: bool,
+    chr_banks: [u16; 8],
+    mirroring: Mirroring,
+
+    /// VRC2 boards without WRAM have a single bit latch at $6000-$6FFF.
+    latch: u8,
+    irq: VrcIrq,
+}
+
+impl Vrc4 {
+    pub fn new(cartridge: &Cartridge) -> Self {
+        // NES 2.0 submappers name the exact board revision. Without one there
+        // is no way to tell the revisions of a mapper number apart, so both
+        // candidate pairs of address lines are OR'd together. No game writes
+        // to an address that would confuse the two.
+        let header = &cartridge.header;
+        let (chip, a0_lines, a1_lines, chr_shift) = match (header.mapper_id, header.submapper_id) {
+            (21, 1) => (Chip::Vrc4, 0x0002, 0x0004, 0),                  // VRC4a
+            (21, 2) => (Chip::Vrc4, 0x0040, 0x0080, 0),                  // VRC4c
+            (21, _) => (Chip::Vrc4, 0x0002 | 0x0040, 0x0004 | 0x0080, 0),
+            (22, _) => (Chip::Vrc2, 0x0002, 0x0001, 1),                  // VRC2a
+            (23, 1) => (Chip::Vrc4, 0x0001, 0x0002, 0),                  // VRC4f
+            (23, 2) => (Chip::Vrc4, 0x0004, 0x0008, 0),                  // VRC4e
+            (23, 3) => (Chip::Vrc2, 0x0001, 0x0002, 0),                  // VRC2b
+            (23, _) => (Chip::Vrc4, 0x0001 | 0x0004, 0x0002 | 0x0008, 0),
+            (25, 1) => (Chip::Vrc4, 0x0002, 0x0001, 0),                  // VRC4b
+            (25, 2) => (Chip::Vrc4, 0x0008, 0x0004, 0),                  // VRC4d
+            (25, 3) => (Chip::Vrc2, 0x0002, 0x0001, 0),                  // VRC2c
+            _ => (Chip::Vrc4, 0x0002 | 0x0008, 0x0001 | 0x0004, 0),
+        };
+
+        let mut prg_ram = match chip {
+            Chip::Vrc4 => vec![0; PRG_RAM_SIZE],
+            Chip::Vrc2 => vec![],
+        };
+        super::load_trainer(&mut prg_ram, cartridge);
+
+        Self {
+            chip,
+            prg_rom: cartridge.prg_rom.clone(),
+            chr: ChrMemory::new(cartridge),
+            prg_ram,
+            a0_lines,
+            a1_lines,
+            chr_shift,
+            prg_banks: [0, 1],
+            prg_swap_mode: false,
+            chr_banks: [0; 8],
+            mirroring: Mirroring::Vertical,
+            latch: 0,
+            irq: VrcIrq::new(),
+        }
+    }
+
+    /// Maps the board's address lines onto the chip's register select inputs,
+    /// producing an address in the form $x000-$x003.
+    fn register(&self, address: u16) -> u16 {
+        let a0 = (address & self.a0_lines != 0) as u16;
+        let a1 = (address & self.a1_lines != 0) as u16;
+
+        (address & 0xF000) | (a1 << 1) | a0
+    }
+
+    fn prg_bank_count(&self) -> usize {
+        (self.prg_rom.len() / PRG_BANK_SIZE).max(1)
+    }
+
+    fn prg_bank(&self, address: u16) -> usize {
+        let second_last = self.prg_bank_count().saturating_sub(2);
+        let last = self.prg_bank_count() - 1;
+
+        match ((address - 0x8000) / PRG_BANK_SIZE as u16, self.prg_swap_mode) {
+            (0, false) => self.prg_banks[0] as usize,
+            (0, true) => second_last,
+            (1, _) => self.prg_banks[1] as usize,
+            (2, false) => second_last,
+            (2, true) => self.prg_banks[0] as usize,
+            _ => last,
+        }
+    }
+
+    fn chr_bank(&self, address: u16) -> usize {
+        (self.chr_banks[(address as usize / CHR_BANK_SIZE) & 0x07] >> self.chr_shift) as usize
+    }
+
+    fn write_mirroring(&mut self, value: u8) {
+        let mode = match self.chip {
+            Chip::Vrc2 => value & 0x01,
+            Chip::Vrc4 => value & 0x03,
+        };
+
+        self.mirroring = match mode {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::SingleScreenA,
+            _ => Mirroring::SingleScreenB,
+        };
+    }
+
+    fn write_chr_bank(&mut self, register: u16, value: u8) {
+        // $B000/$B001 select bank 0, $B002/$B003 bank 1, $C000 bank 2 ...
+        let index = ((((register - 0xB000) >> 12) << 1) | ((register & 0x0002) >> 1)) as usize;
+        let bank = self.chr_banks[index];
+
+        self.chr_banks[index] = if register & 0x0001 == 0 {
+            (bank & 0x01F0) | (value & 0x0F) as u16
+        } else {
+            let high_mask = match self.chip {
+                Chip::Vrc2 => 0x0F,
+                Chip::Vrc4 => 0x1F,
+            };
+            (bank & 0x000F) | (((value & high_mask) as u16) << 4)
+        };
+    }
+}
+
+impl Mapper for Vrc4 {
+    fn cpu_read(&mut self, address: u16) -> Option<u8> {
+        match address {
+            0x6000..=0x7FFF if !self.prg_ram.is_empty() => {
+                Some(self.prg_ram[address as usize % self.prg_ram.len()])
+            }
+            0x6000..=0x6FFF if self.chip == Chip::Vrc2 => Some(self.latch),
+            0x8000..=0xFFFF => {
+                Some(super::read_banked(&self.prg_rom, self.prg_bank(address), PRG_BANK_SIZE, address))
+            }
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        match address {
+            0x6000..=0x7FFF if !self.prg_ram.is_empty() => {
+                let offset = address as usize % self.prg_ram.len();
+                self.prg_ram[offset] = value;
+                return;
+            }
+            0x6000..=0x6FFF if self.chip == Chip::Vrc2 => {
+                self.latch = value & 0x01;
+                return;
+            }
+            0x8000..=0xFFFF => {}
+            _ => return,
+        }
+
+        let register = self.register(address);
+
+        match (register, self.chip) {
+            (0x8000..=0x8003, _) => self.prg_banks[0] = value & 0x1F,
+            (0x9000..=0x9003, Chip::Vrc2) |
+            (0x9000..=0x9001, Chip::Vrc4) => self.write_mirroring(value),
+            (0x9002..=0x9003, Chip::Vrc4) => self.prg_swap_mode = value & 0x02 != 0,
+            (0xA000..=0xA003, _) => self.prg_banks[1] = value & 0x1F,
+            (0xB000..=0xE003, _) => self.write_chr_bank(register, value),
+            (0xF000, Chip::Vrc4) => self.irq.write_latch_low(value),
+            (0xF001, Chip::Vrc4) => self.irq.write_latch_high(value),
+            (0xF002, Chip::Vrc4) => self.irq.write_control(value),
+            (0xF003, Chip::Vrc4) => self.irq.acknowledge(),
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        self.chr.read(self.chr_bank(address), CHR_BANK_SIZE, address)
+    }
+
+    fn ppu_write(&mut self, address: u16, value: u8) {
+        self.chr.write(self.chr_bank(address), CHR_BANK_SIZE, address, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn cpu_clock(&mut self) {
+        if self.chip == Chip::Vrc4 {
+            self.irq.clock();
+        }
+    }
+
+    fn irq_pending(&self) -> bool {
+        self.irq.pending()
+    }
+
+    fn save_ram(&self) -> Option<&[u8]> {
+        (!self.prg_ram.is_empty()).then_some(self.prg_ram.as_slice())
+    }
+
+    fn save_ram_mut(&mut self) -> Option<&mut [u8]> {
+        (!self.prg_ram.is_empty()).then_some(self.prg_ram.as_mut_slice())
+    }
+}
@@ -0,0 +1,184 @@
+//! # vrc6.rs
+//!
+//!  Author: Paul Hazen
+//! Created: 2026-10-19
+//! License: MIT (see LICENSE file)
+//!
+//! ## Description
+//! Mappers 24 (VRC6a) and 26 (VRC6b) - the Konami VRC6. The two boards differ
+//! only in that VRC6b swaps the A0 and A1 register select lines. Expansion
+//! audio registers ($9000-$B002) are accepted and ignored.
+use crate::cartridge::{Cartridge, Mirroring};
+use super::{ChrMemory, Mapper, VrcIrq};
+
+const PRG_16K_BANK_SIZE: usize = 0x4000;
+const PRG_8K_BANK_SIZE: usize = 0x2000;
+const CHR_BANK_SIZE: usize = 0x0400;
+const PRG_RAM_SIZE: usize = 0x2000;
+
+/*
+    $B003 - PPU banking style
+    --------------------------------------------------------------------
+    | Bit | Function                                                   |
+    --------------------------------------------------------------------
+    | 1-0 | CHR banking mode                                           |
+    | 3-2 | Mirroring (V, H, one-screen A, one-screen B)               |
+    |  4  | Nametables from CHR-ROM (not used by any released game)    |
+    |  5  | CHR A10 taken from the bank register in 2 KB modes         |
+    |  7  | PRG-RAM enable                                             |
+    --------------------------------------------------------------------
+ */
+const BANKING_MODE_MASK: u8 = 0b0000_0011;
+const BANKING_CHR_A10_FROM_REGISTER: u8 = 0b0010_0000;
+const BANKING_PRG_RAM_ENABLE: u8 = 0b1000_0000;
+
+pub struct Vrc6 {
+    prg_rom: Vec<u8>,
+    chr: ChrMemory,
+    prg_ram: Vec<u8>,
+
+    /// VRC6b (mapper 26) swaps the A0 and A1 register select lines.
+    swap_a0_a1: bool,
+
+    prg_16k_bank: u8,
+    prg_8k_bank: u8,
+    chr_banks: [u8; 8],
+    banking_control: u8,
+    irq: VrcIrq,
+}
+
+impl Vrc6 {
+    pub fn new(cartridge: &Cartridge) -> Self {
+        let mut prg_ram = vec![0; PRG_RAM_SIZE];
+        super::load_trainer(&mut prg_ram, cartridge);
+
+        Self {
+            prg_rom: cartridge.prg_rom.clone(),
+            chr: ChrMemory::new(cartridge),
+            prg_ram,
+            swap_a0_a1: cartridge.header.mapper_id == 26,
+            prg_16k_bank: 0,
+            prg_8k_bank: 0,
+            chr_banks: [0; 8],
+            banking_control: 0,
+            irq: VrcIrq::new(),
+        }
+    }
+
+    fn register(&self, address: u16) -> u16 {
+        let low = if self.swap_a0_a1 {
+            ((address & 0x0001) << 1) | ((address & 0x0002) >> 1)
+        } else {
+            address & 0x0003
+        };
+
+        (address & 0xF000) | low
+    }
+
+    fn prg_ram_enabled(&self) -> bool {
+        self.banking_control & BANKING_PRG_RAM_ENABLE != 0
+    }
+
+    /// Resolves a pattern table address to a 1 KB CHR bank for the current
+    /// banking mode.
+    fn chr_bank(&self, address: u16) -> usize {
+        let slot = (address as usize / CHR_BANK_SIZE) & 0x07;
+        let a10 = slot & 0x01;
+
+        // In the 2 KB modes the register supplies A11 and up, and A10 either
+        // passes through from the PPU or is taken from the register itself.
+        let two_kb_bank = |register: u8| -> usize {
+            if self.banking_control & BANKING_CHR_A10_FROM_REGISTER != 0 {
+                register as usize
+            } else {
+                (register as usize & !0x01) | a10
+            }
+        };
+
+        match (self.banking_control & BANKING_MODE_MASK, slot) {
+            (0, _) => self.chr_banks[slot] as usize,
+            (1, _) => two_kb_bank(self.chr_banks[slot >> 1]),
+            (_, 0..=3) => self.chr_banks[slot] as usize,
+            (_, _) => two_kb_bank(self.chr_banks[4 + ((slot - 4) >> 1)]),
+        }
+    }
+}
+
+impl Mapper for Vrc6 {
+    fn cpu_read(&mut self, address: u16) -> Option<u8> {
+        match address {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => {
+                Some(self.prg_ram[address as usize % PRG_RAM_SIZE])
+            }
+            0x8000..=0xBFFF => {
+                Some(super::read_banked(&self.prg_rom, self.prg_16k_bank as usize, PRG_16K_BANK_SIZE, address))
+            }
+            0xC000..=0xDFFF => {
+                Some(super::read_banked(&self.prg_rom, self.prg_8k_bank as usize, PRG_8K_BANK_SIZE, address))
+            }
+            0xE000..=0xFFFF => {
+                let last = (self.prg_rom.len() / PRG_8K_BANK_SIZE).max(1) - 1;
+                Some(super::read_banked(&self.prg_rom, last, PRG_8K_BANK_SIZE, address))
+            }
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        match address {
+            0x6000..=0x7FFF => {
+                if self.prg_ram_enabled() {
+                    self.prg_ram[address as usize % PRG_RAM_SIZE] = value;
+                }
+                return;
+            }
+            0x8000..=0xFFFF => {}
+            _ => return,
+        }
+
+        match self.register(address) {
+            0x8000..=0x8003 => self.prg_16k_bank = value & 0x0F,
+            0xB003 => self.banking_control = value,
+            0xC000..=0xC003 => self.prg_8k_bank = value & 0x1F,
+            register @ 0xD000..=0xD003 => self.chr_banks[(register & 0x03) as usize] = value,
+            register @ 0xE000..=0xE003 => self.chr_banks[4 + (register & 0x03) as usize] = value,
+            0xF000 => self.irq.write_latch(value),
+            0xF001 => self.irq.write_control(value),
+            0xF002 => self.irq.acknowledge(),
+            _ => {} // Expansion audio
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        self.chr.read(self.chr_bank(address), CHR_BANK_SIZE, address)
+    }
+
+    fn ppu_write(&mut self, address: u16, value: u8) {
+        self.chr.write(self.chr_bank(address), CHR_BANK_SIZE, address, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match (self.banking_control >> 2) & 0x03 {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::SingleScreenA,
+            _ => Mirroring::SingleScreenB,
+        }
+    }
+
+    fn cpu_clock(&mut self) {
+        self.irq.clock();
+    }
+
+    fn irq_pending(&self) -> bool {
+        self.irq.pending()
+    }
+
+    fn save_ram(&self) -> Option<&[u8]> {
+        (!self.prg_ram.is_empty()).then_some(self.prg_ram.as_slice())
+    }
+
+    fn save_ram_mut(&mut self) -> Option<&mut [u8]> {
+        (!self.prg_ram.is_empty()).then_some(self.prg_ram.as_mut_slice())
+    }
+}
@@ -0,0 +1,152 @@
+//! # vrc7.rs
+//!
+//!  Author: Paul Hazen
+//! Created: 2026-10-19
+//! License: MIT (see LICENSE file)
+//!
+//! ## Description
+//! Mapper 85 - the Konami VRC7. The second register of each pair sits at
+//! $x010 on VRC7a boards and $x008 on VRC7b boards; both are accepted. The
+//! FM synthesis registers ($9010/$9030) are accepted and ignored.
+use crate::cartridge::{Cartridge, Mirroring};
+use super::{ChrMemory, Mapper, VrcIrq};
+
+const PRG_BANK_SIZE: usize = 0x2000;
+const CHR_BANK_SIZE: usize = 0x0400;
+const PRG_RAM_SIZE: usize = 0x2000;
+
+/*
+    $E000 - Control
+    ----------------------------------------------------------------
+    | Bit | Function                                               |
+    ----------------------------------------------------------------
+    | 1-0 | Mirroring (V, H, one-screen A, one-screen B)           |
+    |  6  | Silence expansion sound                                |
+    |  7  | PRG-RAM enable                                         |
+    ----------------------------------------------------------------
+ */
+const CONTROL_MIRRORING_MASK: u8 = 0b0000_0011;
+const CONTROL_PRG_RAM_ENABLE: u8 = 0b1000_0000;
+
+/// VRC7a uses A4 and VRC7b uses A3 to select the second register of a pair.
+const SECOND_REGISTER_LINES: u16 = 0x0010 | 0x0008;
+
+pub struct Vrc7 {
+    prg_rom: Vec<u8>,
+    chr: ChrMemory,
+    prg_ram: Vec<u8>,
+
+    prg_banks: [u8; 3],
+    chr_banks: [u8; 8],
+    control: u8,
+    irq: VrcIrq,
+}
+
+impl Vrc7 {
+    pub fn new(cartridge: &Cartridge) -> Self {
+        let mut prg_ram = vec![0; PRG_RAM_SIZE];
+        super::load_trainer(&mut prg_ram, cartridge);
+
+        Self {
+            prg_rom: cartridge.prg_rom.clone(),
+            chr: ChrMemory::new(cartridge),
+            prg_ram,
+            prg_banks: [0; 3],
+            chr_banks: [0; 8],
+            control: 0,
+            irq: VrcIrq::new(),
+        }
+    }
+
+    /// Normalises a register address to $x000 or $x001.
+    fn register(address: u16) -> u16 {
+        let second = (address & SECOND_REGISTER_LINES != 0) as u16;
+        (address & 0xF000) | second
+    }
+
+    fn prg_ram_enabled(&self) -> bool {
+        self.control & CONTROL_PRG_RAM_ENABLE != 0
+    }
+}
+
+impl Mapper for Vrc7 {
+    fn cpu_read(&mut self, address: u16) -> Option<u8> {
+        match address {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => {
+                Some(self.prg_ram[address as usize % PRG_RAM_SIZE])
+            }
+            0x8000..=0xFFFF => {
+                let slot = ((address - 0x8000) as usize) / PRG_BANK_SIZE;
+                let bank = match slot {
+                    0..=2 => self.prg_banks[slot] as usize,
+                    _ => (self.prg_rom.len() / PRG_BANK_SIZE).max(1) - 1,
+                };
+                Some(super::read_banked(&self.prg_rom, bank, PRG_BANK_SIZE, address))
+            }
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        match address {
+            0x6000..=0x7FFF => {
+                if self.prg_ram_enabled() {
+                    self.prg_ram[address as usize % PRG_RAM_SIZE] = value;
+                }
+                return;
+            }
+            0x8000..=0xFFFF => {}
+            _ => return,
+        }
+
+        match Self::register(address) {
+            0x8000 => self.prg_banks[0] = value & 0x3F,
+            0x8001 => self.prg_banks[1] = value & 0x3F,
+            0x9000 => self.prg_banks[2] = value & 0x3F,
+            register @ 0xA000..=0xD001 => {
+                let index = ((((register - 0xA000) >> 12) << 1) | (register & 0x0001)) as usize;
+                self.chr_banks[index] = value;
+            }
+            0xE000 => self.control = value,
+            0xE001 => self.irq.write_latch(value),
+            0xF000 => self.irq.write_control(value),
+            0xF001 => self.irq.acknowledge(),
+            _ => {} // Expansion audio
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        let bank = self.chr_banks[(address as usize / CHR_BANK_SIZE) & 0x07];
+        self.chr.read(bank as usize, CHR_BANK_SIZE, address)
+    }
+
+    fn ppu_write(&mut self, address: u16, value: u8) {
+        let bank = self.chr_banks[(address as usize / CHR_BANK_SIZE) & 0x07];
+        self.chr.write(bank as usize, CHR_BANK_SIZE, address, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.control & CONTROL_MIRRORING_MASK {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::SingleScreenA,
+            _ => Mirroring::SingleScreenB,
+        }
+    }
+
+    fn cpu_clock(&mut self) {
+        self.irq.clock();
+    }
+
+    fn irq_pending(&self) -> bool {
+        self.irq.pending()
+    }
+
+    fn save_ram(&self) -> Option<&[u8]> {
+        (!self.prg_ram.is_empty()).then_some(self.prg_ram.as_slice())
+    }
+
+    fn save_ram_mut(&mut self) -> Option<&mut [u8]> {
+        (!self.prg_ram.is_empty()).then_some(self.prg_ram.as_mut_slice())
+    }
+}
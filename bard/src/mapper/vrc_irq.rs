@@ -0,0 +1,110 @@
+//! # vrc_irq.rs
+//!
+//!  Author: Paul Hazen
+//! Created: 2026-10-19
+//! License: MIT (see LICENSE file)
+//!
+//! ## Description
+//! The IRQ counter shared by the Konami VRC4, VRC6 and VRC7. It is clocked by
+//! M2 (the CPU clock) and either counts CPU cycles directly, or runs them
+//! through a prescaler that divides by 113.667 to approximate one scanline.
+
+/*
+    -------------------------------------------------------------------------
+    | Register | Bits      | Function                                       |
+    -------------------------------------------------------------------------
+    | Latch    | LLLL LLLL | Value reloaded into the counter on overflow    |
+    | Control  | .... .MEA | A = enable after ack, E = enable, M = mode     |
+    |          |           | (0 = scanline, 1 = CPU cycle)                  |
+    | Ack      | any       | Clears the IRQ and copies A into E             |
+    -------------------------------------------------------------------------
+ */
+const CONTROL_ENABLE_AFTER_ACK: u8 = 0b0000_0001;
+const CONTROL_ENABLE: u8 = 0b0000_0010;
+const CONTROL_CYCLE_MODE: u8 = 0b0000_0100;
+
+/// Three prescaler steps per CPU cycle, 341 per scanline.
+const PRESCALER_RELOAD: i16 = 341;
+const PRESCALER_STEP: i16 = 3;
+
+#[derive(Default)]
+pub struct VrcIrq {
+    latch: u8,
+    counter: u8,
+    prescaler: i16,
+    enabled: bool,
+    enable_after_ack: bool,
+    cycle_mode: bool,
+    pending: bool,
+}
+
+impl VrcIrq {
+    pub fn new() -> Self {
+        Self {
+            prescaler: PRESCALER_RELOAD,
+            ..Self::default()
+        }
+    }
+
+    pub fn write_latch(&mut self, value: u8) {
+        self.latch = value;
+    }
+
+    /// VRC4 splits the latch across two registers, one nibble each.
+    pub fn write_latch_low(&mut self, value: u8) {
+        self.latch = (self.latch & 0xF0) | (value & 0x0F);
+    }
+
+    pub fn write_latch_high(&mut self, value: u8) {
+        self.latch = (self.latch & 0x0F) | ((value & 0x0F) << 4);
+    }
+
+    pub fn write_control(&mut self, value: u8) {
+        self.enable_after_ack = value & CONTROL_ENABLE_AFTER_ACK != 0;
+        self.enabled = value & CONTROL_ENABLE != 0;
+        self.cycle_mode = value & CONTROL_CYCLE_MODE != 0;
+        self.pending = false;
+
+        if self.enabled {
+            self.counter = self.latch;
+            self.prescaler = PRESCALER_RELOAD;
+        }
+    }
+
+    pub fn acknowledge(&mut self) {
+        self.pending = false;
+        self.enabled = self.enable_after_ack;
+    }
+
+    pub fn pending(&self) -> bool {
+        self.pending
+    }
+
+    /// Advances the counter by one CPU cycle.
+    pub fn clock(&mut self) {
+        if !self.enabled {
+            return;
+        }
+
+        if self.cycle_mode {
+            self.clock_counter();
+            return;
+        }
+
+        self.prescaler -= PRESCALER_STEP;
+
+        if self.prescaler <= 0 {
+            self.prescaler += PRESCALER_RELOAD;
+            self.clock_counter();
+        }
+    }
+
+    fn clock_counter(&mut self) {
+        if self.counter == 0xFF {
+            self.counter = self.latch;
+            self.pending = true;
+        } else {
+            self.counter += 1;
+        }
+    }
+}
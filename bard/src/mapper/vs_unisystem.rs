@@ -0,0 +1,92 @@
+//! # vs_unisystem.rs
+//!
+//!  Author: Paul Hazen
+//! Created: 2026-10-19
+//! License: MIT (see LICENSE file)
+//!
+//! ## Description
+//! Mapper 99 - the Vs. System's own bank switching. OUT2 ($4016 bit 2)
+//! selects between two 8 KB CHR banks, and on 40 KB games also swaps the
+//! extra 8 KB into $8000-$9FFF. The mainboard's 2 KB of work RAM is mirrored
+//! across $6000-$7FFF, and the 4 KB of VRAM gives four-screen nametables.
+use crate::cartridge::{Cartridge, Mirroring};
+use super::{ChrMemory, FourScreenVram, Mapper};
+
+const WORK_RAM_SIZE: usize = 0x800;
+const PRG_BANK_SIZE: usize = 0x2000;
+const CHR_BANK_SIZE: usize = 0x2000;
+
+/// $4016 bit 2 - OUT2, the bank select.
+const OUT_BANK_SELECT: u8 = 0b0000_0100;
+
+/// The size of the games that have a swappable first PRG bank.
+const SWAPPABLE_PRG_SIZE: usize = 0xA000;
+
+pub struct VsUnisystem {
+    prg_rom: Vec<u8>,
+    chr: ChrMemory,
+    work_ram: Vec<u8>,
+    mirroring: Mirroring,
+    four_screen_vram: FourScreenVram,
+    bank: usize,
+}
+
+impl VsUnisystem {
+    pub fn new(cartridge: &Cartridge) -> Self {
+        Self {
+            prg_rom: cartridge.prg_rom.clone(),
+            chr: ChrMemory::new(cartridge),
+            work_ram: vec![0; WORK_RAM_SIZE],
+            mirroring: cartridge.header.mirroring,
+            four_screen_vram: FourScreenVram::new(cartridge),
+            bank: 0,
+        }
+    }
+}
+
+impl Mapper for VsUnisystem {
+    fn cpu_read(&mut self, address: u16) -> Option<u8> {
+        match address {
+            0x6000..=0x7FFF => Some(self.work_ram[address as usize % WORK_RAM_SIZE]),
+            0x8000..=0x9FFF if self.prg_rom.len() >= SWAPPABLE_PRG_SIZE => {
+                // Bank 4 is the extra 8 KB after the usual 32 KB
+                let bank = if self.bank == 0 { 0 } else { 4 };
+                Some(super::read_banked(&self.prg_rom, bank, PRG_BANK_SIZE, address))
+            }
+            0x8000..=0xFFFF if !self.prg_rom.is_empty() => {
+                Some(self.prg_rom[(address - 0x8000) as usize % self.prg_rom.len()])
+            }
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        if (0x6000..=0x7FFF).contains(&address) {
+            self.work_ram[address as usize % WORK_RAM_SIZE] = value;
+        }
+    }
+
+    fn out_write(&mut self, value: u8) {
+        self.bank = usize::from(value & OUT_BANK_SELECT != 0);
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        self.chr.read(self.bank, CHR_BANK_SIZE, address)
+    }
+
+    fn ppu_write(&mut self, address: u16, value: u8) {
+        self.chr.write(self.bank, CHR_BANK_SIZE, address, value);
+    }
+
+    fn nametable_read(&mut self, address: u16) -> Option<u8> {
+        self.four_screen_vram.read(address)
+    }
+
+    fn nametable_write(&mut self, address: u16, value: u8) -> bool {
+        self.four_screen_vram.write(address, value)
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
@@ -1,11 +1,14 @@
-use crate::{cartridge::Cartridge, memory::bus::Bus};
+use crate::{apu::Apu, cartridge::Cartridge, mapper::{self, SharedMapper}, memory::bus::Bus, vs_system::{self, VsSystem}};
 use std::{cell::{Cell, RefCell}, rc::Rc};
 
 use super::PPUBus;
 
 pub struct CPUBus {
     memory: Box<[u8]>,
+    mapper: SharedMapper,
     ppu_bus: Option<Rc<RefCell<PPUBus>>>,
+    apu: Option<Rc<RefCell<Apu>>>,
+    vs_system: Option<Rc<RefCell<VsSystem>>>,
     last_read_value: Cell<u8>,
     cycle_counter: Cell<u8>,
 }
@@ -17,48 +20,81 @@ impl CPUBus {
     pub const RESET_VECTOR_DEFAULT: u16 = 0x8000;
     pub const RAM_START: u16 = 0x0000;
     pub const RAM_END: u16 = 0x1FFF;
+    pub const CARTRIDGE_START: u16 = 0x4020;
+    pub const OAM_DMA: u16 = 0x4014;
+
+    /// Creates a CPU bus with the cartridge space ($4020-$FFFF) served by the
+    /// given mapper. Use this when the mapper must be shared with the PPU bus.
+    pub fn with_mapper(mapper: SharedMapper) -> Self {
+        let memory = vec![Self::UNMAPPED; 0x10000].into_boxed_slice();
+
+        let bus = Self {
+            memory,
+            mapper,
+            ppu_bus: None,
+            apu: None,
+            vs_system: None,
+            last_read_value: Cell::new(Self::UNMAPPED),
+            cycle_counter: Cell::new(0x00),
+        };
+
+        // Ensure the reset vector is read from the cartridge PRG-ROM
+        let reset_address = bus.read_word(Self::RESET_VECTOR_ADDR_LOW);
+
+        println!("Loaded reset vector: {:04X}", reset_address); // Debugging
+
+        bus
+    }
 
     pub fn set_ppu_bus(&mut self, ppu_bus: Rc<RefCell<PPUBus>>) {
         self.ppu_bus = Some(ppu_bus);
     }
 
-    pub fn trigger_nmi(&mut self) {
-        println!("CPU: NMI Triggered!");
+    /// Connects the APU, which answers $4000-$4013, $4015 and $4017.
+    pub fn set_apu(&mut self, apu: Rc<RefCell<Apu>>) {
+        self.apu = Some(apu);
+    }
+
+    /// Connects a Vs. System mainboard, which answers the input reads at
+    /// $4016 / $4017, the coin counter at $4020 and the game's protection chip.
+    pub fn set_vs_system(&mut self, vs_system: Rc<RefCell<VsSystem>>) {
+        self.vs_system = Some(vs_system);
+    }
+
+    /// Takes the NMI the PPU raises at the start of VBlank, if there is one.
+    pub fn take_nmi(&mut self) -> bool {
+        self.ppu_bus.as_ref().is_some_and(|ppu_bus| ppu_bus.borrow_mut().take_nmi())
+    }
+
+    /// Advances the cartridge by the given number of CPU cycles, so that
+    /// mappers with cycle based IRQ counters stay in step with the CPU.
+    pub fn tick(&mut self, cpu_cycles: u8) {
+        let mut mapper = self.mapper.borrow_mut();
+
+        for _ in 0..cpu_cycles {
+            mapper.cpu_clock();
+        }
+    }
+
+    /// Whether the cartridge or the APU's frame counter is asserting the
+    /// CPU's IRQ line.
+    pub fn irq_pending(&self) -> bool {
+        let apu_irq = self.apu.as_ref().is_some_and(|apu| apu.borrow().irq_pending());
+        self.mapper.borrow().irq_pending() || apu_irq
+    }
+
+    fn is_apu_register(address: u16) -> bool {
+        (Apu::CHANNELS_START..=Apu::CHANNELS_END).contains(&address)
+            || address == Apu::STATUS
+            || address == Apu::FRAME_COUNTER
     }
 }
 
 impl Bus for CPUBus {
 
     fn load_cartridge(cartridge: Cartridge) -> Self {
-        let mut memory = vec![Self::UNMAPPED; 0x10000].into_boxed_slice();
-    
-        let prg_rom_size = cartridge.prg_rom.len();
-        let prg_rom_start = Self::RESET_VECTOR_DEFAULT as usize; // PRG-ROM is mapped to $8000-$BFFF
-    
-        // Copy PRG-ROM into $8000-$BFFF
-        memory[prg_rom_start..(prg_rom_start + prg_rom_size)]
-            .copy_from_slice(&cartridge.prg_rom);
-    
-        // If PRG-ROM is only 16KB, mirror it into $C000-$FFFF
-        if prg_rom_size == 0x4000 {
-            memory[0xC000..=0xFFFF].copy_from_slice(&cartridge.prg_rom);
-        }
-    
-        // Ensure the reset vector is read from the cartridge PRG-ROM
-        let reset_vector_lsb = memory[0xFFFC]; // Low byte
-        let reset_vector_msb = memory[0xFFFD]; // High byte
-        let reset_address = ((reset_vector_msb as u16) << 8) | (reset_vector_lsb as u16);
-    
-        println!("Loaded reset vector: {:04X}", reset_address); // Debugging
-    
-        Self {
-            memory,
-            ppu_bus: None,
-            last_read_value: Cell::new(Self::UNMAPPED),
-            cycle_counter: Cell::new(0x00),
-        }
+        Self::with_mapper(mapper::create(&cartridge))
     }
-    
 
     fn mask_address(address: u16) -> u16 {
         match address {
@@ -91,6 +127,45 @@ impl Bus for CPUBus {
                 return true;
             }
         }
+
+        if address == Self::OAM_DMA {
+            if let Some(bus) = &self.ppu_bus {
+                // Copies a page of CPU memory into OAM through OAMDATA
+                let page = (value as u16) << 8;
+                let data: Vec<u8> = (0..=0xFF).map(|offset| self.read_byte(page | offset)).collect();
+
+                let mut bus = bus.borrow_mut();
+                for value in data {
+                    bus.write_register(0x2004, value);
+                }
+                return true;
+            }
+        }
+
+        if Self::is_apu_register(address) {
+            if let Some(apu) = &self.apu {
+                apu.borrow_mut().write_register(address, value);
+                return true;
+            }
+        }
+
+        // The OUT pins of $4016 reach the cartridge connector
+        if address == vs_system::CONTROLLER_1 {
+            self.mapper.borrow_mut().out_write(value);
+        }
+
+        if address == vs_system::COIN_COUNTER_ADDRESS {
+            if let Some(vs_system) = &self.vs_system {
+                vs_system.borrow_mut().write_coin_counter(value);
+                return true;
+            }
+        }
+
+        if address >= Self::CARTRIDGE_START {
+            self.mapper.borrow_mut().cpu_write(address, value);
+            return true;
+        }
+
         Bus::default_write_byte(self, address, value)
     }
 
@@ -100,6 +175,30 @@ impl Bus for CPUBus {
                 return bus.borrow_mut().read_register(address)
             }
         }
+
+        if address == Apu::STATUS {
+            if let Some(apu) = &self.apu {
+                return apu.borrow_mut().read_status();
+            }
+        }
+
+        if let Some(vs_system) = &self.vs_system {
+            if address == vs_system::CONTROLLER_1 || address == vs_system::CONTROLLER_2 {
+                return vs_system.borrow().read_input(address);
+            }
+            if let Some(value) = vs_system.borrow_mut().read_protection(address) {
+                return value;
+            }
+        }
+
+        if address >= Self::CARTRIDGE_START {
+            if let Some(value) = self.mapper.borrow_mut().cpu_read(address) {
+                self.increment_cycle_counter();
+                self.set_last_read_value(value);
+                return value;
+            }
+        }
+
         Bus::default_read_byte(self, address)
     }
 
@@ -1,27 +1,302 @@
 use std::cell::Cell;
-use crate::{cartridge::Cartridge, memory::bus::Bus};
+use crate::{cartridge::{Cartridge, VsPpuType}, mapper::{self, PpuFetchSource, SharedMapper}, memory::bus::Bus, ppu::ScrollRegisters, region::Region};
 
 pub struct PPUBus {
     memory: Box<[u8]>,
+    mapper: SharedMapper,
     oam: [u8; 256],        // Object Attribute Memory (OAM) for sprites
     pub ppu_ctrl: u8,          // $2000 - PPUCTRL
     ppu_mask: u8,          // $2001 - PPUMASK
     ppu_status: u8,        // $2002 - PPUSTATUS
     oam_addr: u8,          // $2003 - OAMADDR
-    ppu_scroll: (u8, u8),  // $2005 - PPUSCROLL (x, y)
-    ppu_addr: u16,         // $2006 - VRAM Address
+    pub scroll: ScrollRegisters, // $2005 / $2006 - Loopy's v, t, x and w
     vram_buffer: u8,       // Buffered read for $2007
     last_read_value: Cell<u8>,
     cycle_counter: Cell<u8>,
-    nmi_callback: Option<Box<dyn FnMut()>>,
+    /// An NMI raised at the start of VBlank that the CPU has not yet taken.
+    nmi_pending: bool,
+    /// Set by a $2002 read on the dot before VBlank starts, which stops it
+    /// starting this frame.
+    vblank_suppressed: bool,
+    /// The scanline and dot the PPU last ran, for the $2002 read race.
+    scanline: u16,
+    dot: u16,
+    /// Decides when VBlank starts and the order of the emphasis bits.
+    region: Region,
+    /// The PPU's I/O data latch, the last value on the data bus between the
+    /// CPU and the PPU. Reads of write-only registers return it.
+    io_latch: u8,
+    /// Frames since each bit of the latch was last driven.
+    io_latch_age: [u16; 8],
+    /// Whether $2000 and $2001 trade places, as on the RC2C05s.
+    swap_control_registers: bool,
+    /// The value an RC2C05 puts in the low bits of $2002.
+    status_id: u8,
 }
 
 impl PPUBus {
+    pub const PATTERN_TABLE_END: u16 = 0x1FFF;
+    pub const NAMETABLE_START: u16 = 0x2000;
+    pub const NAMETABLE_MIRROR_END: u16 = 0x3EFF;
+    pub const PALETTE_START: u16 = 0x3F00;
+    /// The PPU's address bus is 14 bits wide.
+    const ADDRESS_MASK: u16 = 0x3FFF;
+    /// Palette RAM is 32 bytes, mirrored through $3F00-$3FFF.
+    const PALETTE_SIZE: u16 = 0x20;
+    /// Palette entries are 6 bits wide.
+    const PALETTE_VALUE_MASK: u8 = 0x3F;
+
+    /// PPUCTRL bit 2 - $2007 accesses step down a row (32 bytes) instead of
+    /// across one.
+    const CONTROL_INCREMENT_DOWN: u8 = 0b0000_0100;
+
+    /// PPUSTATUS bits 0-5, which hold an RC2C05's ID.
+    const STATUS_ID_MASK: u8 = 0b0011_1111;
+
+    /* PPUMASK - Rendering
+     * --- | Bit | Function
+     *     |  0  | Greyscale
+     *     |  1  | Show the background in the leftmost 8 pixels
+     *     |  2  | Show sprites in the leftmost 8 pixels
+     *     |  3  | Show the background
+     *     |  4  | Show sprites
+     *     | 5-7 | Emphasise red, green and blue
+     */
+    const MASK_GREYSCALE: u8 = 0b0000_0001;
+    const MASK_BACKGROUND_LEFT: u8 = 0b0000_0010;
+    const MASK_SPRITES_LEFT: u8 = 0b0000_0100;
+    const MASK_BACKGROUND: u8 = 0b0000_1000;
+    const MASK_SPRITES: u8 = 0b0001_0000;
+    const MASK_RENDERING: u8 = Self::MASK_BACKGROUND | Self::MASK_SPRITES;
+    const MASK_EMPHASIS_SHIFT: u8 = 5;
+
+    /* PPUSTATUS
+     * --- | Bit | Function
+     *     | 0-4 | Unused (an RC2C05's ID)
+     *     |  5  | More than 8 sprites were found on a scanline
+     *     |  6  | An opaque pixel of sprite 0 met an opaque background pixel
+     *     |  7  | VBlank, cleared by reading $2002
+     */
+    pub const STATUS_SPRITE_OVERFLOW: u8 = 0b0010_0000;
+    pub const STATUS_SPRITE_ZERO_HIT: u8 = 0b0100_0000;
+    pub const STATUS_VBLANK: u8 = 0b1000_0000;
+
+    /// The PPUSTATUS bits the PPU drives. The rest come from the I/O latch.
+    const STATUS_DRIVEN_BITS: u8 = 0b1110_0000;
+    /// How long a bit of the I/O latch holds its value without being driven.
+    const IO_LATCH_DECAY_SECONDS: f64 = 0.6;
+
+    /// PPUCTRL bit 7 - raise an NMI when VBlank starts.
+    const CONTROL_NMI_ENABLE: u8 = 0b1000_0000;
+
+    /// VBlank starts on dot 1 of the region's VBlank scanline.
+    const VBLANK_DOT: u16 = 1;
+    /// The dots from the start of VBlank in which a $2002 read still
+    /// suppresses the NMI.
+    const NMI_RACE_DOTS: u16 = 2;
+
+    /// Creates a PPU bus with the pattern tables ($0000-$1FFF) served by the
+    /// given mapper. Use this when the mapper must be shared with the CPU bus.
+    pub fn with_mapper(mapper: SharedMapper) -> Self {
+        let memory = vec![0; 0x4000].into_boxed_slice(); // 16 KB for PPU memory
+
+        // Initialize PPUBus with registers and VRAM initialized
+        Self {
+            memory,
+            mapper,
+            oam: [0; 256],   // Sprite memory (OAM)
+            ppu_ctrl: 0x00,  // Default value of $2000
+            ppu_mask: 0x00,  // Default value of $2001
+            ppu_status: 0xA0, // VBlank flag initially set (bit 7 = 1 on startup)
+            oam_addr: 0x00,  // Default OAM address
+            scroll: ScrollRegisters::new(), // VRAM address and scroll initially 0
+            vram_buffer: 0x00, // Buffered read for $2007
+            cycle_counter: Cell::new(0),
+            last_read_value: Cell::new(0),
+            nmi_pending: false,
+            vblank_suppressed: false,
+            scanline: 0,
+            dot: 0,
+            region: Region::Ntsc,
+            io_latch: 0x00,
+            io_latch_age: [0; 8],
+            swap_control_registers: false,
+            status_id: 0x00,
+        }
+    }
+
+    /// The 64 sprites, 4 bytes each: Y, tile, attributes and X.
+    pub fn oam(&self) -> &[u8; 256] {
+        &self.oam
+    }
+
+    /// Whether the background or sprites are shown. While neither is, the PPU
+    /// stops fetching and leaves `v` alone.
+    pub fn rendering_enabled(&self) -> bool {
+        self.ppu_mask & Self::MASK_RENDERING != 0
+    }
+
+    pub fn show_background(&self) -> bool {
+        self.ppu_mask & Self::MASK_BACKGROUND != 0
+    }
+
+    pub fn show_sprites(&self) -> bool {
+        self.ppu_mask & Self::MASK_SPRITES != 0
+    }
+
+    /// Whether the background is drawn in the leftmost 8 pixels.
+    pub fn show_background_left(&self) -> bool {
+        self.ppu_mask & Self::MASK_BACKGROUND_LEFT != 0
+    }
+
+    /// Whether sprites are drawn in the leftmost 8 pixels.
+    pub fn show_sprites_left(&self) -> bool {
+        self.ppu_mask & Self::MASK_SPRITES_LEFT != 0
+    }
+
+    /// Whether colours are limited to the grey column of the palette.
+    pub fn greyscale(&self) -> bool {
+        self.ppu_mask & Self::MASK_GREYSCALE != 0
+    }
+
+    /// The colour emphasis bits (0-7): bit 0 is red, bit 1 green, bit 2 blue.
+    /// PAL and Dendy PPUs take green from bit 5 of PPUMASK and red from bit 6.
+    pub fn emphasis(&self) -> u8 {
+        let emphasis = self.ppu_mask >> Self::MASK_EMPHASIS_SHIFT;
+        if self.region.swaps_red_green_emphasis() {
+            (emphasis & 0b100) | ((emphasis & 0b001) << 1) | ((emphasis & 0b010) >> 1)
+        } else {
+            emphasis
+        }
+    }
+
+    /// Changes the console region, which `PPU::set_region` does for the PPU
+    /// and its registers together.
+    pub fn set_region(&mut self, region: Region) {
+        self.region = region;
+    }
+
+    /// Whether PPUCTRL asks for an NMI when VBlank starts.
+    pub fn nmi_enabled(&self) -> bool {
+        self.ppu_ctrl & Self::CONTROL_NMI_ENABLE != 0
+    }
+
+    /// Sets the VBlank flag and raises an NMI if PPUCTRL enables one, unless
+    /// $2002 was read on the dot before.
+    pub fn start_vblank(&mut self) {
+        if std::mem::take(&mut self.vblank_suppressed) {
+            return;
+        }
+
+        self.ppu_status |= Self::STATUS_VBLANK;
+        if self.nmi_enabled() {
+            self.nmi_pending = true;
+        }
+    }
+
+    /// Clears VBlank and the sprite flags, on the pre-render line.
+    pub fn end_vblank(&mut self) {
+        self.set_status_flag(Self::STATUS_VBLANK | Self::STATUS_SPRITE_OVERFLOW | Self::STATUS_SPRITE_ZERO_HIT, false);
+    }
+
+    /// Records the scanline and dot the PPU has just run.
+    pub fn set_position(&mut self, scanline: u16, dot: u16) {
+        self.scanline = scanline;
+        self.dot = dot;
+    }
+
+    /// Takes the NMI raised at the start of VBlank, if any. It is held back
+    /// while a $2002 read could still suppress it.
+    pub fn take_nmi(&mut self) -> bool {
+        if !self.nmi_pending || self.in_nmi_race() {
+            return false;
+        }
+
+        self.nmi_pending = false;
+        true
+    }
+
+    /// Whether VBlank has just started, so that reading $2002 suppresses its
+    /// NMI.
+    fn in_nmi_race(&self) -> bool {
+        self.scanline == self.region.vblank_scanline()
+            && (Self::VBLANK_DOT..Self::VBLANK_DOT + Self::NMI_RACE_DOTS).contains(&self.dot)
+    }
+
+    /// Ages the I/O latch by one frame. Bits not driven for about 600ms of
+    /// emulated time decay to 0.
+    pub fn decay_io_latch(&mut self) {
+        let decay_frames = (Self::IO_LATCH_DECAY_SECONDS * self.region.frame_rate()).round() as u16;
+
+        for (bit, age) in self.io_latch_age.iter_mut().enumerate() {
+            *age = age.saturating_add(1);
+            if *age >= decay_frames {
+                self.io_latch &= !(1 << bit);
+            }
+        }
+    }
+
+    /// Puts a value on the I/O latch, refreshing only the bits given.
+    fn drive_io_latch(&mut self, value: u8, bits: u8) {
+        self.io_latch = (self.io_latch & !bits) | (value & bits);
+        for (bit, age) in self.io_latch_age.iter_mut().enumerate() {
+            if bits & (1 << bit) != 0 {
+                *age = 0;
+            }
+        }
+    }
+
+    /// Sets or clears PPUSTATUS flags.
+    pub fn set_status_flag(&mut self, flag: u8, value: bool) {
+        if value {
+            self.ppu_status |= flag;
+        } else {
+            self.ppu_status &= !flag;
+        }
+    }
+
+    /// Changes the registers to match a Vs. System PPU. The RC2C05s swap
+    /// $2000 / $2001 and identify themselves through $2002.
+    pub fn set_vs_ppu(&mut self, ppu: VsPpuType) {
+        let VsPpuType::Rc2c05(variant) = ppu else {
+            return;
+        };
+
+        self.swap_control_registers = true;
+        self.status_id = match variant {
+            1 | 4 => 0x1B,
+            2 => 0x3D,
+            3 => 0x1C,
+            _ => 0x00,
+        };
+    }
+
     pub fn write_register(&mut self, address: u16, value: u8) {
+        // Every write fills the I/O latch, even one to $2002
+        self.drive_io_latch(value, 0xFF);
+
+        let address = match address {
+            0x2000 | 0x2001 if self.swap_control_registers => address ^ 0x0001,
+            _ => address,
+        };
+
         match address {
             0x2000 => {
                 // PPUCTRL: Control register
+                let nmi_was_enabled = self.nmi_enabled();
                 self.ppu_ctrl = value;
+                self.scroll.write_control(value);
+
+                // Enabling NMIs during VBlank raises one at once, disabling
+                // them drops one the CPU has not taken
+                if !self.nmi_enabled() {
+                    self.nmi_pending = false;
+                } else if !nmi_was_enabled && self.ppu_status & Self::STATUS_VBLANK != 0 {
+                    self.nmi_pending = true;
+                }
+
+                // Bit 5 selects 8x16 sprites, which some mappers bank differently
+                self.mapper.borrow_mut().set_ppu_tall_sprites(value & 0b0010_0000 != 0);
                 //println!("PPU: Control Register set to {:02X}", value);
             }
             0x2001 => {
@@ -39,42 +314,20 @@ impl PPUBus {
                 self.oam_addr = self.oam_addr.wrapping_add(1); // Auto-increment
             }
             0x2005 => {
-                // PPUSCROLL: Write X/Y scroll position
-                if self.ppu_scroll.0 == 0 {
-                    self.ppu_scroll.0 = value;
-                } else {
-                    self.ppu_scroll.1 = value;
-                }
+                // PPUSCROLL: X scroll, then Y scroll
+                self.scroll.write_scroll(value);
             }
             0x2006 => {
-                static mut HIGH_BYTE_LATCH: bool = false;
-                
-                if unsafe { HIGH_BYTE_LATCH } == false {
-                    // First write (high byte)
-                    self.ppu_addr = ((value as u16) << 8) | (self.ppu_addr & 0x00FF);
-                    unsafe { HIGH_BYTE_LATCH = true };
-                } else {
-                    // Second write (low byte) - full address is now set
-                    self.ppu_addr = (self.ppu_addr & 0xFF00) | (value as u16);
-                    unsafe { HIGH_BYTE_LATCH = false };
-            
-                    /* println!(
-                        "DEBUG: PPUADDR Fully Set -> Address: 0x{:04X}",
-                        self.ppu_addr
-                    ); */
-                }
-            }            
+                // PPUADDR: High byte, then low byte
+                self.scroll.write_address(value);
+            }
             0x2007 => {
                 // Write to VRAM using the existing write_byte function
-                self.write_byte(self.ppu_addr, value);
-            
-/*                 println!(
-                    "DEBUG: VRAM Write -> Address: 0x{:04X}, Value: {:02X}",
-                    self.ppu_addr, value
-                ); */
-            
+                self.set_fetch_source(PpuFetchSource::Cpu);
+                self.write_byte(self.scroll.address(), value);
+
                 // Increment VRAM address after the write
-                self.ppu_addr = self.ppu_addr.wrapping_add(1);
+                self.scroll.increment(self.vram_increment());
             }
             
             _ => {}
@@ -84,54 +337,92 @@ impl PPUBus {
     pub fn read_register(&mut self, address: u16) -> u8 {
         match address {
             0x2002 => {
-                // PPUSTATUS: Return status and clear VBlank flag
-                let status = self.ppu_status;
+                // PPUSTATUS: Return status and clear VBlank flag. The low
+                // bits are whatever the I/O latch holds, except on the
+                // RC2C05s, which put their ID there.
+                let (status, driven) = if self.swap_control_registers {
+                    ((self.ppu_status & !Self::STATUS_ID_MASK) | self.status_id, 0xFF)
+                } else {
+                    (self.ppu_status, Self::STATUS_DRIVEN_BITS)
+                };
+                let status = (status & driven) | (self.io_latch & !driven);
+                self.drive_io_latch(status, driven);
+
+                // Reading just before VBlank starts sees it clear and stops it
+                // starting, reading as it starts sees it set but stops the NMI
+                if self.scanline == self.region.vblank_scanline() && self.dot == Self::VBLANK_DOT - 1 {
+                    self.vblank_suppressed = true;
+                } else if self.in_nmi_race() {
+                    self.nmi_pending = false;
+                }
 
-                self.ppu_status &= !0x80; // ✅ Clear VBlank flag (bit 7)
-                self.nmi_callback = None; // ✅ Prevent unwanted NMIs
+                self.ppu_status &= !Self::STATUS_VBLANK; // ✅ Clear VBlank flag (bit 7)
                 self.oam_addr = 0x00; // ✅ Reset OAM latch
+                self.scroll.reset_latch(); // Next $2005 / $2006 write is the first
 
                 status
             }
             0x2004 => {
                 // OAMDATA: Read from OAM at OAMADDR
-                self.oam[self.oam_addr as usize]
+                let value = self.oam[self.oam_addr as usize];
+                self.drive_io_latch(value, 0xFF);
+                value
             }
             
             0x2007 => {
-                // Read from VRAM using the existing read_byte function
-                let addr = self.ppu_addr;
-                let result = self.vram_buffer;
-                self.vram_buffer = self.read_byte(self.ppu_addr); // Fetch next value into buffer
-            
-                // Increment VRAM address after the read
-                self.ppu_addr = self.ppu_addr.wrapping_add(1);
-            
-/*                 println!(
-                    "DEBUG: VRAM Read -> Address: 0x{:04X}, Buffered: {:02X}, Returning: {:02X}",
-                    addr, self.vram_buffer, result
-                ); */
-            
-                // Palette reads return actual value instead of buffered
-                if addr >= 0x3F00 {
-                    self.vram_buffer
+                // Reads return the buffer, then refill it from the address
+                let addr = self.scroll.address();
+                let mut result = self.vram_buffer;
+                self.set_fetch_source(PpuFetchSource::Cpu);
+
+                if addr >= Self::PALETTE_START {
+                    // Palette reads return at once, and the buffer is filled
+                    // from the nametable mirror "underneath" the palette. Only
+                    // 6 bits are driven, the top 2 come from the I/O latch.
+                    result = (self.io_latch & !Self::PALETTE_VALUE_MASK) | self.read_byte(addr);
+                    self.vram_buffer = self.read_byte(addr - 0x1000);
+                    self.drive_io_latch(result, Self::PALETTE_VALUE_MASK);
                 } else {
-                    result
+                    self.vram_buffer = self.read_byte(addr);
+                    self.drive_io_latch(result, 0xFF);
                 }
+
+                // Increment VRAM address after the read
+                self.scroll.increment(self.vram_increment());
+
+                result
             }
-            
-            _ => 0
+
+            // The write-only registers return the I/O latch
+            _ => self.io_latch
         }
     }    
 
-    pub fn set_nmi_callback<F: FnMut() + 'static>(&mut self, callback: F) {
-        self.nmi_callback = Some(Box::new(callback));
+    /// How far $2007 accesses move `v`.
+    fn vram_increment(&self) -> u16 {
+        if self.ppu_ctrl & Self::CONTROL_INCREMENT_DOWN != 0 { 32 } else { 1 }
     }
 
-    pub fn trigger_nmi(&mut self) {
-        if let Some(callback) = self.nmi_callback.as_mut() {
-            callback();
-        }
+    /// Resolves a palette address ($3F00-$3FFF) to one of the 32 bytes of
+    /// palette RAM. The backdrop entries of the sprite palettes ($3F10, $3F14,
+    /// $3F18 and $3F1C) are shared with the background palettes.
+    fn palette_address(address: u16) -> u16 {
+        let entry = address & (Self::PALETTE_SIZE - 1);
+        let entry = if entry & 0x03 == 0 { entry & 0x0F } else { entry };
+        Self::PALETTE_START + entry
+    }
+
+    /// Resolves a nametable address ($2000-$2FFF) to its location in the 2 KB
+    /// of VRAM (CIRAM) at $2000-$27FF, using the page the cartridge selects.
+    fn vram_address(&self, address: u16) -> u16 {
+        let page = self.mapper.borrow().ciram_page(address);
+        Self::NAMETABLE_START + page * 0x0400 + (address & 0x03FF)
+    }
+
+    /// Tells the cartridge which part of the PPU the following reads belong
+    /// to.
+    pub fn set_fetch_source(&self, source: PpuFetchSource) {
+        self.mapper.borrow_mut().set_ppu_fetch_source(source);
     }
 }
 
@@ -140,7 +431,7 @@ impl Bus for PPUBus {
     fn readable_ranges() -> &'static [std::ops::Range<u16>] {
         &[
             0x0000..0x2000, // Pattern tables (CHR-ROM / CHR-RAM)
-            0x2000..0x3000, // Nametables (Mirroring handled separately)
+            0x2000..0x3000, // Nametables (Mirroring chosen by the cartridge)
             0x3F00..0x4000, // Palette RAM + Mirrors
         ]
     }
@@ -153,39 +444,32 @@ impl Bus for PPUBus {
     }
 
     fn load_cartridge(cartridge: Cartridge) -> Self {
-        let mut memory = vec![0; 0x4000].into_boxed_slice(); // 16 KB for PPU memory
-    
-        // Load CHR-ROM (or allocate CHR-RAM if missing)
-        if !cartridge.chr_rom.is_empty() {
-            memory[0x0000..0x2000].copy_from_slice(&cartridge.chr_rom);
-        }
-    
-        // Debugging: Print first 512 bytes of CHR-ROM
-        /* for i in 0x0000..0x0200 {
-            println!("DEBUG: CHR-ROM Address 0x{:04X} = 0x{:02X}", i, memory[i]);
-        } */
-    
-        // Initialize PPUBus with registers and VRAM initialized
-        Self {
-            memory,
-            oam: [0; 256],   // Sprite memory (OAM)
-            ppu_ctrl: 0x00,  // Default value of $2000
-            ppu_mask: 0x00,  // Default value of $2001
-            ppu_status: 0xA0, // VBlank flag initially set (bit 7 = 1 on startup)
-            oam_addr: 0x00,  // Default OAM address
-            ppu_scroll: (0, 0), // Scroll X/Y
-            ppu_addr: 0x0000, // VRAM Address initially 0
-            vram_buffer: 0x00, // Buffered read for $2007
-            cycle_counter: Cell::new(0),
-            last_read_value: Cell::new(0),
-            nmi_callback: None,
-        }
+        Self::with_mapper(mapper::create(&cartridge))
     }
-    
 
     fn read_byte(&self, address:u16) -> u8 {
-        let value = self.default_read_byte(address);
-        value
+        let address = address & Self::ADDRESS_MASK;
+
+        // Pattern tables live on the cartridge, behind the mapper
+        if address <= Self::PATTERN_TABLE_END {
+            self.increment_cycle_counter();
+            return self.mapper.borrow_mut().ppu_read(address);
+        }
+
+        // Nametable reads are offered to the cartridge first (e.g. MMC5 ExRAM),
+        // then go to the page of VRAM selected by the cartridge's mirroring
+        if (Self::NAMETABLE_START..=Self::NAMETABLE_MIRROR_END).contains(&address) {
+            let nametable_address = Self::NAMETABLE_START | (address & 0x0FFF);
+
+            if let Some(value) = self.mapper.borrow_mut().nametable_read(nametable_address) {
+                self.increment_cycle_counter();
+                return value;
+            }
+
+            return self.default_read_byte(self.vram_address(nametable_address));
+        }
+
+        self.default_read_byte(address) & Self::PALETTE_VALUE_MASK
     }
 
     fn read_word(&self, address:u16) -> u16 {
@@ -193,7 +477,24 @@ impl Bus for PPUBus {
     }
     
     fn write_byte(&mut self, address:u16, value:u8) -> bool {
-        self.default_write_byte(address, value)
+        let address = address & Self::ADDRESS_MASK;
+
+        if address <= Self::PATTERN_TABLE_END {
+            self.mapper.borrow_mut().ppu_write(address, value);
+            return true;
+        }
+
+        if (Self::NAMETABLE_START..=Self::NAMETABLE_MIRROR_END).contains(&address) {
+            let nametable_address = Self::NAMETABLE_START | (address & 0x0FFF);
+
+            if self.mapper.borrow_mut().nametable_write(nametable_address, value) {
+                return true;
+            }
+
+            return self.default_write_byte(self.vram_address(nametable_address), value);
+        }
+
+        self.default_write_byte(address, value & Self::PALETTE_VALUE_MASK)
     }
 
     fn set_cycle_counter(&self, value: u8) {
@@ -221,17 +522,11 @@ impl Bus for PPUBus {
     }
     
     fn mask_address(address: u16) -> u16 {
+        // Nametables ($2000-$3EFF) are resolved by `vram_address`, as the
+        // mirroring is controlled by the cartridge
         let masked_address = match address {
-            0x3000..=0x3EFF => address - 0x1000, // Nametable mirrors
-    
-            0x3F20..=0x3FFF => 0x3F00 + (address & 0x1F), // Palette mirrors
+            Self::PALETTE_START..=Self::ADDRESS_MASK => Self::palette_address(address), // Palette mirrors
     
-            0x2000..=0x2FFF => {
-                // Nametable mirroring
-                let offset = (address - 0x2000) % 0x0800; // Mirror within 2KB of VRAM
-                0x2000 + offset
-            }
-            
             _ => address, // Everything else remains unchanged
         };
     
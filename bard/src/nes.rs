@@ -7,11 +7,16 @@
 //! ## Description
 //! Contains the implementation for the NES struct - which serves to orchestrate the various components of the emulator.
 //! 
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, io, path::Path, rc::Rc};
 use crate::cpu::CPU;
 use crate::ppu::PPU;
-use crate::cartridge::Cartridge;
+use crate::cartridge::{Cartridge, ConsoleType, HeaderFormat, RomDatabase, RomHashes, SaveFile, TimingRegion};
 use crate::framebuffer_viewer::FramebufferViewer;
+use crate::mapper::{self, SharedMapper};
+use crate::region::Region;
+use crate::settings::Settings;
+use crate::vs_system::{self, VsSystem};
+use minifb::Key;
 use crate::memory::CPUBus;
 use crate::memory::PPUBus;
 use crate::memory::Bus;
@@ -25,8 +30,20 @@ pub struct NES {
     pub ppu_bus: Rc<RefCell<PPUBus>>,
     
     pub viewer: FramebufferViewer,
+
+    mapper: SharedMapper,
+    /// Where battery backed RAM is persisted, `None` for cartridges without a
+    /// battery.
+    save_file: Option<SaveFile>,
+    cycles_since_save: u32,
+    /// The arcade mainboard, for Vs. System games.
+    vs_system: Option<Rc<RefCell<VsSystem>>>,
 }
 
+/// How often battery backed RAM is written to disk - about once a second of
+/// NTSC CPU time.
+const SAVE_FLUSH_INTERVAL: u32 = 1_789_773;
+
 impl NES {
     fn dump_nametable(ppu_bus: &PPUBus) {
         println!("=== Nametable Dump (0x2000 - 0x23BF) ===");
@@ -42,10 +59,48 @@ impl NES {
     }
     
     pub fn open_rom(rom_filepath: &str) -> Self {
-        let cartridge = Cartridge::load_from_file(rom_filepath).unwrap();
+        Self::open_rom_with_settings(rom_filepath, &Settings::default())
+    }
+
+    pub fn open_rom_with_settings(rom_filepath: &str, settings: &Settings) -> Self {
+        let mut cartridge = Cartridge::load_from_file(rom_filepath).unwrap();
+        cartridge.load_bios(rom_filepath, settings.fds_bios.as_deref()).unwrap();
+        Self::with_cartridge(cartridge, rom_filepath, settings)
+    }
+
+    /// Creates the console for an already loaded cartridge. `rom_filepath` is
+    /// used for the window title and to locate the battery save.
+    pub fn with_cartridge(mut cartridge: Cartridge, rom_filepath: &str, settings: &Settings) -> Self {
+        // Fix known bad headers before they are used to pick the mapper
+        if settings.correct_headers {
+            Self::correct_header(&mut cartridge, settings);
+        }
+
+        // The mapper is shared so bank switches made by the CPU are seen by the PPU
+        let mapper = mapper::try_create(&cartridge).unwrap_or_else(|error| {
+            println!("WARNING: {} Falling back to NROM.", error);
+            mapper::create(&cartridge)
+        });
 
-        let ppu = PPU::load_from_cartridge(&cartridge); // Create PPU first
-        let ppu_bus = Rc::new(RefCell::new(PPUBus::load_cartridge(cartridge.clone()))); // Create PPU bus
+        // Restore battery backed RAM from the last session
+        let save_file = if cartridge.header.has_battery {
+            let mut save_file = SaveFile::for_rom(Path::new(rom_filepath), settings.saves_directory.as_deref());
+            match save_file.load() {
+                Ok(Some(data)) => Self::copy_save_ram(&mapper, &data),
+                Ok(None) => println!("INFO: No save file at {}.", save_file.path().display()),
+                Err(error) => println!("WARNING: Could not read save file {}: {}", save_file.path().display(), error),
+            }
+            Some(save_file)
+        } else {
+            None
+        };
+
+        let mut ppu = PPU::load_from_cartridge(&cartridge); // Create PPU first
+        let ppu_bus = Rc::new(RefCell::new(PPUBus::with_mapper(Rc::clone(&mapper)))); // Create PPU bus
+
+        let region = Self::select_region(&cartridge, settings);
+        println!("INFO: Running as a {:?} console.", region);
+        ppu.set_region(&mut ppu_bus.borrow_mut(), region);
 
         // Set VRAM address to 0x2000
         ppu_bus.borrow_mut().write_register(0x2006, 0x20);  
@@ -63,7 +118,7 @@ impl NES {
 
         ppu.print_chr_rom_tiles(&ppu_bus.borrow());
 
-        let mut cpu_bus = CPUBus::load_cartridge(cartridge);
+        let mut cpu_bus = CPUBus::with_mapper(Rc::clone(&mapper));
 
         cpu_bus.dump_memory();
 
@@ -73,7 +128,9 @@ impl NES {
         
         cpu.dbg_view_opcode_table();
 
-        let viewer = FramebufferViewer::new(&rom_filepath);
+        let mut viewer = FramebufferViewer::new(&rom_filepath);
+
+        let vs_system = Self::configure_console(&cartridge, settings, &mut cpu_bus, &ppu_bus, &mut viewer);
 
         Self {
             cpu,
@@ -81,6 +138,141 @@ impl NES {
             ppu,
             ppu_bus,
             viewer,
+            mapper,
+            save_file,
+            cycles_since_save: 0,
+            vs_system,
+        }
+    }
+
+    /// Sets up the hardware of the console the game was released for. The
+    /// arcade boards have RGB PPUs, and the Vs. System adds DIP switches,
+    /// coin slots and protection chips.
+    fn configure_console(
+        cartridge: &Cartridge,
+        settings: &Settings,
+        cpu_bus: &mut CPUBus,
+        ppu_bus: &Rc<RefCell<PPUBus>>,
+        viewer: &mut FramebufferViewer,
+    ) -> Option<Rc<RefCell<VsSystem>>> {
+        match cartridge.header.console_type {
+            ConsoleType::VsSystem => {
+                let system = cartridge.header.vs_system.unwrap_or_default();
+                println!("INFO: Vs. System with {:?} PPU on {:?} board.", system.ppu, system.hardware);
+
+                let vs_system = Rc::new(RefCell::new(VsSystem::new(system, settings.vs_dip_switches)));
+                cpu_bus.set_vs_system(Rc::clone(&vs_system));
+                ppu_bus.borrow_mut().set_vs_ppu(system.ppu);
+                viewer.set_palette(vs_system::palette(system.ppu, settings.vs_palette.as_deref()));
+
+                Some(vs_system)
+            }
+            ConsoleType::PlayChoice10 => {
+                viewer.set_palette(vs_system::rgb_palette());
+                None
+            }
+            ConsoleType::Nes | ConsoleType::Extended(_) => None,
+        }
+    }
+
+    /// Chooses the console region: the one in the settings, else the one the
+    /// header records. iNES headers only rarely record PAL, so the ROM
+    /// database is asked for theirs (unless it has already corrected them).
+    fn select_region(cartridge: &Cartridge, settings: &Settings) -> Region {
+        if let Some(region) = settings.region {
+            return region;
+        }
+
+        let timing = match cartridge.header.format {
+            HeaderFormat::INes | HeaderFormat::Archaic if !settings.correct_headers => {
+                Self::database_timing(cartridge, settings).unwrap_or(cartridge.header.timing)
+            }
+            _ => cartridge.header.timing,
+        };
+        Region::from_timing(timing)
+    }
+
+    /// The timing the ROM database records for the cartridge, if any.
+    fn database_timing(cartridge: &Cartridge, settings: &Settings) -> Option<TimingRegion> {
+        let database = RomDatabase::load(settings.rom_database.as_deref()?).ok()?;
+        let entry = database.lookup(&RomHashes::of(cartridge))?;
+        entry.overrides.timing
+    }
+
+    /// Replaces header fields with the values recorded in the ROM database.
+    fn correct_header(cartridge: &mut Cartridge, settings: &Settings) {
+        let Some(database_path) = settings.rom_database.as_deref() else {
+            println!("WARNING: Correcting headers needs a ROM database, none is set.");
+            return;
+        };
+
+        let database = match RomDatabase::load(database_path) {
+            Ok(database) => database,
+            Err(error) => {
+                println!("WARNING: Could not read ROM database: {}", error);
+                return;
+            }
+        };
+
+        match database.lookup(&RomHashes::of(cartridge)) {
+            Some(entry) => {
+                println!("INFO: Identified ROM as {}.", entry.title);
+                for correction in entry.overrides.apply(&mut cartridge.header) {
+                    println!("INFO: Corrected header {}.", correction);
+                }
+            }
+            None => println!("INFO: ROM not found in the database, using its header as is."),
+        }
+    }
+
+    /// Returns a copy of the cartridge's battery backed RAM, or `None` if the
+    /// board has no RAM.
+    pub fn export_save(&self) -> Option<Vec<u8>> {
+        self.mapper.borrow().save_ram().map(<[u8]>::to_vec)
+    }
+
+    /// Replaces the cartridge's battery backed RAM with the given save. Saves
+    /// shorter than the RAM only overwrite its start.
+    pub fn import_save(&mut self, data: &[u8]) {
+        Self::copy_save_ram(&self.mapper, data);
+    }
+
+    /// Writes battery backed RAM to the `.sav` file if it has changed.
+    pub fn flush_save(&mut self) -> io::Result<()> {
+        self.cycles_since_save = 0;
+
+        let Some(save_file) = self.save_file.as_mut() else {
+            return Ok(());
+        };
+        let Some(data) = self.mapper.borrow().save_ram().map(<[u8]>::to_vec) else {
+            return Ok(());
+        };
+
+        if save_file.flush(&data)? {
+            println!("INFO: Saved {} bytes to {}.", data.len(), save_file.path().display());
+        }
+
+        Ok(())
+    }
+
+    /// Ejects the disk and inserts the next side (or the first side of the
+    /// next disk). Does nothing for cartridges.
+    pub fn switch_disk_side(&mut self) {
+        let mut mapper = self.mapper.borrow_mut();
+        let side_count = mapper.disk_side_count();
+        if side_count == 0 {
+            return;
+        }
+
+        let side = mapper.inserted_disk_side().map_or(0, |side| (side + 1) % side_count);
+        mapper.insert_disk_side(Some(side));
+        println!("INFO: Inserting disk {} side {}.", side / 2 + 1, ['A', 'B'][side % 2]);
+    }
+
+    fn copy_save_ram(mapper: &SharedMapper, data: &[u8]) {
+        if let Some(ram) = mapper.borrow_mut().save_ram_mut() {
+            let length = data.len().min(ram.len());
+            ram[..length].copy_from_slice(&data[..length]);
         }
     }
 
@@ -89,19 +281,59 @@ impl NES {
 
         // Reset the CPU explicitly before running (TODO: This may not be needed)
         self.cpu.reset(&self.cpu_bus);
+        let mut frame = self.ppu.frame();
 
         loop {
-            let cycles = self.cpu.step(&mut self.cpu_bus);
+            let cycles = self.cpu.step(&mut self.cpu_bus)
+                + self.cpu.handle_nmi(&mut self.cpu_bus)
+                + self.cpu.handle_irq(&mut self.cpu_bus);
 
-            for _ in 0..3 {
-                self.ppu.tick(&mut self.ppu_bus.borrow_mut(), cycles);
+            self.cycles_since_save += cycles as u32;
+            if self.cycles_since_save >= SAVE_FLUSH_INTERVAL {
+                if let Err(error) = self.flush_save() {
+                    println!("WARNING: Could not write save file: {}", error);
+                }
             }
 
+            // The cartridge and PPU are stepped together one CPU cycle at a
+            // time, as mappers such as the MMC5 watch the PPU's fetches.
+            for _ in 0..cycles {
+                self.cpu_bus.tick(1);
+                self.ppu.tick(&mut self.ppu_bus.borrow_mut(), 1);
+            }
+
+            // Each frame is shown once the PPU has finished it
+            if self.ppu.frame() == frame {
+                continue;
+            }
+            frame = self.ppu.frame();
             self.viewer.update(&self.ppu.frame_buffer);
 
+            if self.viewer.is_key_pressed(Key::F5) {
+                self.switch_disk_side();
+            }
+
+            // Coins are inserted with 5 and 6 as in arcade cabinets, the
+            // service button is 9
+            if let Some(vs_system) = &self.vs_system {
+                let mut vs_system = vs_system.borrow_mut();
+                vs_system.set_coin(0, self.viewer.is_key_down(Key::Key5));
+                vs_system.set_coin(1, self.viewer.is_key_down(Key::Key6));
+                vs_system.set_service_button(self.viewer.is_key_down(Key::Key9));
+            }
+
             if !self.viewer.is_open() {
                 break
             }
         }
     }
+}
+
+impl Drop for NES {
+    /// Makes sure progress made since the last periodic flush is not lost.
+    fn drop(&mut self) {
+        if let Err(error) = self.flush_save() {
+            println!("WARNING: Could not write save file: {}", error);
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,376 @@
+//! # nsf_player.rs
+//!
+//!  Author: Paul Hazen
+//! Created: 2026-10-19
+//! License: MIT (see LICENSE file)
+//!
+//! ## Description
+//! Plays NSF / NSFe sound files. The CPU runs the tune's init routine once per
+//! song and then calls its play routine at the rate given in the header, while
+//! the APU turns the register writes into samples.
+//!
+//! The routines are called the way an NSF player's driver would: the return
+//! address pushed on the stack is a sentinel, and the call is over when the
+//! routine's RTS pulls it off again. There is no live audio output; the
+//! viewer shows the channel levels and `render` produces samples for a WAV.
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::rc::Rc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use minifb::Key;
+
+use crate::apu::Apu;
+use crate::cartridge::nsf::NsfHeader;
+use crate::cartridge::{Cartridge, CartridgeError};
+use crate::cpu::CPU;
+use crate::framebuffer_viewer::FramebufferViewer;
+use crate::mapper;
+use crate::memory::{Bus, CPUBus};
+use crate::region::Region;
+
+/// Where the stack pointer sits before a routine is called. The routine has
+/// returned once the stack is back here.
+const STACK_TOP: u8 = 0xFD;
+/// The address the sentinel return address points at. Nothing is mapped
+/// there, it only has to be recognisable.
+const RETURN_ADDRESS: u16 = 0x4100;
+/// How long an init routine may run before it is given up on (1 second).
+const INIT_CYCLE_LIMIT: u32 = 1_789_773;
+
+/// The value $4017 is set to before init: 4 step mode, frame IRQ off.
+const FRAME_COUNTER_INIT: u8 = 0x40;
+/// Enables the pulse, triangle and noise channels in $4015.
+const CHANNELS_ENABLED: u8 = 0x0F;
+
+pub const DEFAULT_SAMPLE_RATE: u32 = 44_100;
+
+/// How much of the previous output the DC blocking filter keeps.
+const HIGH_PASS_FACTOR: f32 = 0.996;
+
+/// Level meter layout in the viewer, one bar per channel.
+const SCREEN_WIDTH: usize = 256;
+const SCREEN_HEIGHT: usize = 240;
+const BAR_WIDTH: usize = 32;
+const BAR_SPACING: usize = 16;
+const BAR_MAX_HEIGHT: usize = 192;
+const BACKGROUND_COLOR: u16 = 0x0F;
+const BAR_COLORS: [u16; 5] = [0x21, 0x2A, 0x26, 0x20, 0x24];
+
+pub struct NsfPlayer {
+    cartridge: Cartridge,
+    nsf: NsfHeader,
+    song: u8,
+
+    cpu: CPU,
+    cpu_bus: CPUBus,
+    apu: Rc<RefCell<Apu>>,
+
+    /// NTSC or PAL, which sets the play speed and the APU's timing.
+    region: Region,
+    cpu_clock: f64,
+    /// Fraction of a cycle carried over between play periods.
+    cycle_remainder: f64,
+
+    sample_rate: u32,
+    cycles_per_sample: f64,
+    sample_clock: f64,
+    sample_sum: f32,
+    sample_cycles: u32,
+    filter_input: f32,
+    filter_output: f32,
+}
+
+impl NsfPlayer {
+    /// Creates a player for a cartridge loaded from an NSF or NSFe file and
+    /// starts its first song.
+    ///
+    /// # Arguments
+    ///
+    /// * `cartridge` - A cartridge with `nsf` set.
+    /// * `sample_rate` - The rate samples are produced at by `render`.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(NsfPlayer)` ready to play the starting song.
+    /// * `Err(CartridgeError::InvalidHeader)` if the cartridge is not a tune.
+    pub fn new(cartridge: Cartridge, sample_rate: u32) -> Result<Self, CartridgeError> {
+        let nsf = cartridge.nsf.clone().ok_or(CartridgeError::InvalidHeader)?;
+
+        if !nsf.expansion_chips.is_empty() {
+            println!("WARNING: Expansion audio ({:?}) is not emulated, only the 2A03 channels will be heard.", nsf.expansion_chips);
+        }
+
+        // Tunes are played as NTSC or PAL, Dendy timing is not an NSF option
+        let region = Region::from_timing(cartridge.header.timing);
+        let cpu_clock = region.cpu_clock();
+        let apu = Rc::new(RefCell::new(Apu::new()));
+        let mut cpu_bus = CPUBus::with_mapper(mapper::create(&cartridge));
+        cpu_bus.set_apu(Rc::clone(&apu));
+        let cpu = CPU::new(&mut cpu_bus);
+
+        let mut player = Self {
+            cartridge,
+            song: nsf.starting_song,
+            nsf,
+            cpu,
+            cpu_bus,
+            apu,
+            region,
+            cpu_clock,
+            cycle_remainder: 0.0,
+            sample_rate,
+            cycles_per_sample: cpu_clock / sample_rate as f64,
+            sample_clock: 0.0,
+            sample_sum: 0.0,
+            sample_cycles: 0,
+            filter_input: 0.0,
+            filter_output: 0.0,
+        };
+        player.start_song(player.song);
+        Ok(player)
+    }
+
+    pub fn header(&self) -> &NsfHeader {
+        &self.nsf
+    }
+
+    /// The song playing, counting from 1.
+    pub fn song(&self) -> u8 {
+        self.song
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// The APU the tune is played on. It is replaced each time a song starts.
+    pub fn apu(&self) -> &Rc<RefCell<Apu>> {
+        &self.apu
+    }
+
+    /// The song's title, or its number when the file does not name it.
+    pub fn song_title(&self) -> String {
+        match self.nsf.track_titles.get(self.song as usize - 1) {
+            Some(title) if !title.is_empty() => title.clone(),
+            _ => format!("Song {}", self.song),
+        }
+    }
+
+    pub fn next_song(&mut self) {
+        let song = if self.song >= self.nsf.song_count { 1 } else { self.song + 1 };
+        self.start_song(song);
+    }
+
+    pub fn previous_song(&mut self) {
+        let song = if self.song <= 1 { self.nsf.song_count } else { self.song - 1 };
+        self.start_song(song);
+    }
+
+    /// Resets the machine and runs the init routine for a song. Songs outside
+    /// 1 to the song count are clamped.
+    pub fn start_song(&mut self, song: u8) {
+        self.song = song.clamp(1, self.nsf.song_count);
+
+        // A fresh mapper restores the initial banks and clears the RAM
+        self.cpu_bus = CPUBus::with_mapper(mapper::create(&self.cartridge));
+        self.apu.replace(Apu::new());
+        self.apu.borrow_mut().set_region(self.region);
+        self.cpu_bus.set_apu(Rc::clone(&self.apu));
+        self.cycle_remainder = 0.0;
+
+        for address in Apu::CHANNELS_START..=Apu::CHANNELS_END {
+            self.cpu_bus.write_byte(address, 0x00);
+        }
+        self.cpu_bus.write_byte(Apu::STATUS, CHANNELS_ENABLED);
+        self.cpu_bus.write_byte(Apu::FRAME_COUNTER, FRAME_COUNTER_INIT);
+
+        // Start the filter at the idle level so the song does not open with a click
+        self.filter_input = self.apu.borrow().output();
+        self.filter_output = 0.0;
+
+        // The init routine takes the song in A (from 0) and the region in X
+        self.cpu.set_a(self.song - 1);
+        self.cpu.set_x(u8::from(self.region == Region::Pal));
+        self.cpu.set_y(0);
+        self.call(self.nsf.init_address, INIT_CYCLE_LIMIT, &mut vec![]);
+    }
+
+    /// Runs one play period: the play routine, then the time left before the
+    /// next call.
+    ///
+    /// # Arguments
+    ///
+    /// * `samples` - Receives the samples produced during the period.
+    pub fn run_frame(&mut self, samples: &mut Vec<i16>) {
+        let period = self.play_speed() as f64 * self.cpu_clock / 1_000_000.0 + self.cycle_remainder;
+        let cycles = period as u32;
+        self.cycle_remainder = period - cycles as f64;
+
+        let used = self.call(self.nsf.play_address, cycles, samples);
+        self.clock(cycles.saturating_sub(used), samples);
+    }
+
+    /// Plays the current song for the given time.
+    ///
+    /// # Returns
+    ///
+    /// * The samples, mono, signed 16 bit at `sample_rate()`.
+    pub fn render(&mut self, duration: Duration) -> Vec<i16> {
+        let sample_count = (duration.as_secs_f64() * self.sample_rate as f64) as usize;
+
+        let mut samples = Vec::with_capacity(sample_count);
+        while samples.len() < sample_count {
+            self.run_frame(&mut samples);
+        }
+
+        samples.truncate(sample_count);
+        samples
+    }
+
+    /// Plays in a window showing the level of each channel. The right and
+    /// left arrow keys change song.
+    pub fn run(&mut self) {
+        let mut viewer = FramebufferViewer::new(&self.window_title());
+        let mut frame_buffer = [BACKGROUND_COLOR; SCREEN_WIDTH * SCREEN_HEIGHT];
+        let mut samples = Vec::new();
+        let mut deadline = Instant::now();
+
+        while viewer.is_open() {
+            samples.clear();
+            self.run_frame(&mut samples);
+            self.draw_levels(&mut frame_buffer);
+            viewer.update(&frame_buffer);
+
+            if viewer.is_key_pressed(Key::Right) {
+                self.next_song();
+                viewer.set_title(&self.window_title());
+            } else if viewer.is_key_pressed(Key::Left) {
+                self.previous_song();
+                viewer.set_title(&self.window_title());
+            }
+
+            // Keep to real time so the song can be followed
+            deadline += Duration::from_micros(self.play_speed() as u64);
+            if let Some(wait) = deadline.checked_duration_since(Instant::now()) {
+                thread::sleep(wait);
+            }
+        }
+    }
+
+    fn window_title(&self) -> String {
+        format!("{} - {} ({}/{})", self.nsf.title, self.song_title(), self.song, self.nsf.song_count)
+    }
+
+    fn play_speed(&self) -> u16 {
+        if self.region == Region::Pal { self.nsf.pal_play_speed } else { self.nsf.ntsc_play_speed }
+    }
+
+    /// Calls a routine and runs it until it returns or `cycle_limit` runs out.
+    ///
+    /// # Returns
+    ///
+    /// * The number of CPU cycles the routine ran for.
+    fn call(&mut self, address: u16, cycle_limit: u32, samples: &mut Vec<i16>) -> u32 {
+        self.cpu.set_s(STACK_TOP);
+        self.cpu.push_stack_word(&mut self.cpu_bus, RETURN_ADDRESS.wrapping_sub(1));
+        self.cpu.set_pc(address);
+
+        let mut elapsed = 0;
+        while elapsed < cycle_limit && self.cpu.get_s() != STACK_TOP {
+            // Unknown opcodes take no time, count them so the loop still ends
+            let cycles = (self.cpu.step(&mut self.cpu_bus) as u32).max(1);
+            self.clock(cycles, samples);
+            elapsed += cycles;
+        }
+
+        elapsed
+    }
+
+    /// Advances the APU and cartridge, collecting samples as they fall due.
+    fn clock(&mut self, cycles: u32, samples: &mut Vec<i16>) {
+        for _ in 0..cycles {
+            self.cpu_bus.tick(1);
+
+            let mut apu = self.apu.borrow_mut();
+            apu.clock();
+            self.sample_sum += apu.output();
+            self.sample_cycles += 1;
+            drop(apu);
+
+            self.sample_clock += 1.0;
+            if self.sample_clock >= self.cycles_per_sample {
+                self.sample_clock -= self.cycles_per_sample;
+                samples.push(self.next_sample());
+            }
+        }
+    }
+
+    /// Averages the output since the last sample and removes the DC offset
+    /// the mixer leaves.
+    fn next_sample(&mut self) -> i16 {
+        let input = self.sample_sum / self.sample_cycles.max(1) as f32;
+        self.sample_sum = 0.0;
+        self.sample_cycles = 0;
+
+        self.filter_output = input - self.filter_input + HIGH_PASS_FACTOR * self.filter_output;
+        self.filter_input = input;
+
+        (self.filter_output * i16::MAX as f32).clamp(i16::MIN as f32, i16::MAX as f32) as i16
+    }
+
+    fn draw_levels(&self, frame_buffer: &mut [u16; SCREEN_WIDTH * SCREEN_HEIGHT]) {
+        frame_buffer.fill(BACKGROUND_COLOR);
+
+        let levels = self.apu.borrow().channel_levels();
+        let maximums = [15, 15, 15, 15, 127];
+        let left = (SCREEN_WIDTH - BAR_COLORS.len() * BAR_WIDTH - (BAR_COLORS.len() - 1) * BAR_SPACING) / 2;
+        let bottom = (SCREEN_HEIGHT + BAR_MAX_HEIGHT) / 2;
+
+        for (channel, (&level, &maximum)) in levels.iter().zip(maximums.iter()).enumerate() {
+            let height = level as usize * BAR_MAX_HEIGHT / maximum;
+            let x = left + channel * (BAR_WIDTH + BAR_SPACING);
+            for y in bottom - height..bottom {
+                frame_buffer[y * SCREEN_WIDTH + x..y * SCREEN_WIDTH + x + BAR_WIDTH].fill(BAR_COLORS[channel]);
+            }
+        }
+    }
+}
+
+/// Writes mono 16 bit samples as a WAV file.
+pub fn write_wav(path: &Path, samples: &[i16], sample_rate: u32) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_wav_to(&mut writer, samples, sample_rate)?;
+    writer.flush()
+}
+
+/// Writes mono 16 bit samples in WAV format.
+pub fn write_wav_to<W: Write>(writer: &mut W, samples: &[i16], sample_rate: u32) -> io::Result<()> {
+    const CHANNELS: u16 = 1;
+    const BITS_PER_SAMPLE: u16 = 16;
+    const FORMAT_PCM: u16 = 1;
+    let block_align = CHANNELS * BITS_PER_SAMPLE / 8;
+    let data_size = (samples.len() * block_align as usize) as u32;
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(36 + data_size).to_le_bytes())?;
+    writer.write_all(b"WAVEfmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    writer.write_all(&FORMAT_PCM.to_le_bytes())?;
+    writer.write_all(&CHANNELS.to_le_bytes())?;
+    writer.write_all(&sample_rate.to_le_bytes())?;
+    writer.write_all(&(sample_rate * block_align as u32).to_le_bytes())?;
+    writer.write_all(&block_align.to_le_bytes())?;
+    writer.write_all(&BITS_PER_SAMPLE.to_le_bytes())?;
+    writer.write_all(b"data")?;
+    writer.write_all(&data_size.to_le_bytes())?;
+
+    for sample in samples {
+        writer.write_all(&sample.to_le_bytes())?;
+    }
+
+    Ok(())
+}
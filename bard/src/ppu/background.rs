@@ -0,0 +1,143 @@
+//! # background.rs
+//!
+//!  Author: Paul Hazen
+//! Created: 2026-10-19
+//! License: MIT (see LICENSE file)
+//!
+//! ## Description
+//! The background half of the PPU's rendering pipeline. Every 8 dots the PPU
+//! fetches a tile's nametable byte, attribute byte and the two planes of its
+//! pattern row, two dots per fetch. The fetched tile is loaded into the low
+//! byte of 16 bit shift registers, which shift once per dot, so the high byte
+//! always holds the tile being drawn and fine X picks the bit to output.
+use crate::{mapper::PpuFetchSource, memory::{Bus, PPUBus}};
+
+use super::ScrollRegisters;
+
+/// PPUCTRL bit 4 - the pattern table the background uses.
+const CONTROL_BACKGROUND_TABLE: u8 = 0b0001_0000;
+const PATTERN_TABLE_SIZE: u16 = 0x1000;
+const NAMETABLE_START: u16 = 0x2000;
+const ATTRIBUTE_TABLE_OFFSET: u16 = 0x03C0;
+/// Each tile is 16 bytes: 8 rows of the low plane, then 8 of the high plane.
+const TILE_SIZE: u16 = 16;
+const HIGH_PLANE_OFFSET: u16 = 8;
+
+/// The fetches of the 8 dot cycle, by dot within the cycle.
+const FETCH_NAMETABLE: u16 = 1;
+const FETCH_ATTRIBUTE: u16 = 3;
+const FETCH_PATTERN_LOW: u16 = 5;
+const FETCH_PATTERN_HIGH: u16 = 7;
+
+#[derive(Default)]
+pub struct Background {
+    // The tile fetched for the next 8 dots
+    next_tile: u8,
+    next_attribute: u8,
+    next_pattern_low: u8,
+    next_pattern_high: u8,
+
+    // The high byte is the tile being drawn, the low byte the next one
+    pattern_low: u16,
+    pattern_high: u16,
+    attribute_low: u16,
+    attribute_high: u16,
+}
+
+impl Background {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes the fetch the PPU does on the given dot of a fetching stretch
+    /// (dots 1-256 and 321-336), loading the shift registers as each tile
+    /// starts.
+    ///
+    /// # Arguments
+    ///
+    /// * `dot` - The dot of the scanline.
+    /// * `ppu_bus` - The bus to fetch from. Fetches are seen by the mapper.
+    pub fn fetch(&mut self, dot: u16, ppu_bus: &PPUBus) {
+        let scroll = &ppu_bus.scroll;
+
+        match dot % 8 {
+            FETCH_NAMETABLE => {
+                self.load_shift_registers();
+                self.next_tile = Self::fetch_nametable(scroll, ppu_bus);
+            }
+            FETCH_ATTRIBUTE => {
+                ppu_bus.set_fetch_source(PpuFetchSource::Background);
+                let address = NAMETABLE_START
+                    | ATTRIBUTE_TABLE_OFFSET
+                    | (scroll.v & 0x0C00)
+                    | ((scroll.v >> 4) & 0x38)
+                    | ((scroll.v >> 2) & 0x07);
+                let attribute = ppu_bus.read_byte(address);
+
+                // Each attribute byte covers 4x4 tiles, 2 bits per 2x2 quadrant
+                let shift = ((scroll.v >> 4) & 0x04) | (scroll.v & 0x02);
+                self.next_attribute = (attribute >> shift) & 0x03;
+            }
+            FETCH_PATTERN_LOW => {
+                self.next_pattern_low = Self::fetch_pattern(scroll, ppu_bus, self.next_tile, 0);
+            }
+            FETCH_PATTERN_HIGH => {
+                self.next_pattern_high = Self::fetch_pattern(scroll, ppu_bus, self.next_tile, HIGH_PLANE_OFFSET);
+            }
+            _ => {}
+        }
+    }
+
+    /// The unused nametable fetches at the end of a scanline (dots 337 and
+    /// 339), which boards such as the MMC5 use to find the end of a scanline.
+    pub fn fetch_unused_nametable(&mut self, ppu_bus: &PPUBus) {
+        self.next_tile = Self::fetch_nametable(&ppu_bus.scroll, ppu_bus);
+    }
+
+    /// Moves the shift registers on by one dot.
+    pub fn shift(&mut self) {
+        self.pattern_low <<= 1;
+        self.pattern_high <<= 1;
+        self.attribute_low <<= 1;
+        self.attribute_high <<= 1;
+    }
+
+    /// The pixel the background outputs this dot.
+    ///
+    /// # Returns
+    ///
+    /// * `(u8, u8)` - The pixel value (0-3, 0 is transparent) and the palette (0-3).
+    pub fn pixel(&self, fine_x: u8) -> (u8, u8) {
+        let bit = 0x8000 >> fine_x;
+        let plane = |register: u16| u8::from(register & bit != 0);
+
+        let pixel = (plane(self.pattern_high) << 1) | plane(self.pattern_low);
+        let palette = (plane(self.attribute_high) << 1) | plane(self.attribute_low);
+        (pixel, palette)
+    }
+
+    /// Puts the fetched tile into the low byte of the shift registers. The
+    /// attribute bits are the same for the whole tile, so are spread across
+    /// the byte.
+    fn load_shift_registers(&mut self) {
+        let spread = |bit: u8| if self.next_attribute & bit != 0 { 0xFF } else { 0x00 };
+
+        self.pattern_low = (self.pattern_low & 0xFF00) | self.next_pattern_low as u16;
+        self.pattern_high = (self.pattern_high & 0xFF00) | self.next_pattern_high as u16;
+        self.attribute_low = (self.attribute_low & 0xFF00) | spread(0x01);
+        self.attribute_high = (self.attribute_high & 0xFF00) | spread(0x02);
+    }
+
+    fn fetch_nametable(scroll: &ScrollRegisters, ppu_bus: &PPUBus) -> u8 {
+        ppu_bus.set_fetch_source(PpuFetchSource::Background);
+        ppu_bus.read_byte(NAMETABLE_START | (scroll.v & 0x0FFF))
+    }
+
+    fn fetch_pattern(scroll: &ScrollRegisters, ppu_bus: &PPUBus, tile: u8, plane: u16) -> u8 {
+        let table = if ppu_bus.ppu_ctrl & CONTROL_BACKGROUND_TABLE != 0 { PATTERN_TABLE_SIZE } else { 0 };
+        let fine_y = (scroll.v >> 12) & 0x07;
+
+        ppu_bus.set_fetch_source(PpuFetchSource::Background);
+        ppu_bus.read_byte(table + tile as u16 * TILE_SIZE + plane + fine_y)
+    }
+}
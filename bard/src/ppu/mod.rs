@@ -1,4 +1,8 @@
+mod background;
 mod ppu;
 mod ppu_memory_sections;
+mod scroll;
+mod sprites;
 
 pub use ppu::PPU;
+pub use scroll::ScrollRegisters;
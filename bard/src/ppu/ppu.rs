@@ -1,157 +1,273 @@
-use crate::{cartridge::Cartridge, memory::{Bus, PPUBus}};
+use crate::{cartridge::Cartridge, memory::{Bus, PPUBus}, region::Region};
+
+use super::{background::Background, sprites::{self, Sprites}, ScrollRegisters};
 
 // TODO: Move these constants into PPU if possible.
 const PPU_FRAME_BUFFER_HEIGHT: usize = 240;
 const PPU_FRAME_BUFFER_WIDTH: usize = 256;
 const PPU_CYCLES_PER_SCANLINE: u16 = 341;
+const PPU_ODD_FRAME_SKIP_DOT: u16 = 339; // Odd frames go from here to the next frame when rendering
 const PPU_VISIBLE_SCANLINES: u16 = 240; // Scanlines where pixels are drawn
 const PPU_POST_RENDER_SCANLINE: u16 = 240; // Idle scanline
-const PPU_VBLANK_START_SCANLINE: u16 = 241; // VBlank begins
-const PPU_VBLANK_END_SCANLINE: u16 = 260; // Last VBlank scanline
-const PPU_PRE_RENDER_SCANLINE: u16 = 261; // Prepares for next frame
-const PPU_TOTAL_SCANLINES: u16 = 262; // Total scanlines per frame
-const STATUS_VBLANK_FLAG: u8 = 0b1000_0000; // Bit 7 in PPUSTATUS ($2002)
-const CONTROL_NMI_ENABLE_FLAG: u8 = 0b1000_0000; // Bit 7 in PPUCTRL ($2000)
+// VBlank, the pre-render line and the number of scanlines depend on the region
+const PPU_LAST_VISIBLE_DOT: u16 = 256; // Last dot of a scanline that draws a pixel
+const PPU_PREFETCH_START_DOT: u16 = 321; // First tiles of the next scanline are fetched
+const PPU_PREFETCH_END_DOT: u16 = 336;
+const PPU_COPY_VERTICAL_DOTS: std::ops::RangeInclusive<u16> = 280..=304; // Pre-render line only
 const CHR_ROM_SIZE: usize = 8192; // 8 KB of CHR-ROM (Pattern Table Data)
 
 pub struct PPU {
-    pub frame_buffer: [u8; PPU_FRAME_BUFFER_WIDTH * PPU_FRAME_BUFFER_HEIGHT],
+    /// The colour of each pixel in bits 0-5, with PPUMASK's emphasis bits in
+    /// bits 6-8.
+    pub frame_buffer: [u16; PPU_FRAME_BUFFER_WIDTH * PPU_FRAME_BUFFER_HEIGHT],
     cycle: u16,
     scanline: u16,
-
-    /*    
-        ------------------------------------------------------------------------------------------
-        | Bit | Name                         | Function                                          |
-        ------------------------------------------------------------------------------------------
-        | 1-0 | Base Nametable Address       | Controls scrolling area ($2000-$2C00)             |
-        |  2  | VRAM Address Increment       | 0 = increment by 1, 1 = increment by 32           |
-        |  3  | Pattern Table for Background | 0 = $0000, 1 = $1000                              |
-        |  4  | Pattern Table for Sprites    | 0 = $0000, 1 = $1000                              |
-        |  5  | Sprite Size                  | 0 = 8x8, 1 = 8x16                                 |
-        |  6  | PPU Master/Slave Mode        | Unused on NES                                     |
-        |  7  | VBlank NMI Enable            | Triggers an NMI when entering VBlank (1 = enable) |
-        ------------------------------------------------------------------------------------------
-     */
-    control_register: u8,
-
-    /*
-        Indicates whether an NMI (Non-Maskable Interrupt) should be triggered.
-     */
-    nmi_triggered: bool,
-
-    /*
-        -------------------------------------------------------------------------------------------------------------------
-        | Bit | Name                                       | Function                                                     |
-        -------------------------------------------------------------------------------------------------------------------
-        | 4-0 | Unused (returns stale PPU open bus values) |                                                              |
-        |  5  | Sprite Overflow                            | Set if too many sprites are on one scanline                  |
-        |  6  | Sprit 0 Hit                                | Set when sprite 0 collides with background pixels            |
-        |  7  | VBlank Flag                                | Set to 1 when entering VBlank, cleared when CPU reads $2002  |
-        -------------------------------------------------------------------------------------------------------------------
-     */
-    status_register: u8,
+    region: Region,
+    /// Master clock cycles run by the CPU that the PPU has not caught up with.
+    master_cycles: u32,
+    // The registers ($2000-$2007) live on the PPU bus, where the CPU reaches them
     frame_count: u64,
+    background: Background,
+    sprites: Sprites,
 }
 
 impl PPU {
 
-    const ATTRIBUTE_TABLE_BASE_ADDRESS: u16 = 0x23C0;
     const PATTERN_TABLE_BASE_ADDRESS: u16 = 0x0000;
     const NAME_TABLE_BASE_ADDRESS: u16 = 0x2000;
     const PALETTE_BASE_ADDRESS: u16 = 0x3F00;
     pub const ADDRESS_MASK: u16 = 0x3FFF;
+    /// The palette index of a `frame_buffer` pixel.
+    pub const COLOR_MASK: u16 = 0x003F;
+    /// Where the emphasis bits sit in a `frame_buffer` pixel.
+    pub const EMPHASIS_SHIFT: u16 = 6;
 
-    pub fn load_from_cartridge(cartridge: &Cartridge) -> Self {
-        let mut chr_rom = [0u8; CHR_ROM_SIZE];
-        let cartridge_chr = cartridge.get_chr_rom();
-
-        let copy_size = std::cmp::min(CHR_ROM_SIZE, cartridge_chr.len());
-        chr_rom[..copy_size].copy_from_slice(&cartridge_chr[..copy_size]);
+    /// PPUMASK's greyscale bit keeps only the column of grey colours.
+    const GREYSCALE_MASK: u8 = 0x30;
 
+    /// Creates the PPU for a cartridge. Pattern data (CHR-ROM or CHR-RAM) is
+    /// owned by the cartridge's mapper and read through the PPU bus.
+    pub fn load_from_cartridge(_cartridge: &Cartridge) -> Self {
         PPU {
             frame_buffer: [0x00; PPU_FRAME_BUFFER_WIDTH * PPU_FRAME_BUFFER_HEIGHT],   // Initialize frame buffer to empty
             cycle: 0,                                                                 // Start at the first PPU cycle
-            scanline: PPU_PRE_RENDER_SCANLINE,                                        // Pre-render scanline
+            scanline: Region::Ntsc.scanlines() - 1,                                   // Pre-render scanline
+            region: Region::Ntsc,                                                     // Changed by set_region
+            master_cycles: 0,                                                         // In step with the CPU
             frame_count: 0,                                                           // First frame has not started
-            nmi_triggered: false,                                                     // No pending NMI interrupt
-            control_register: 0x00,                                                   // All bits start cleared
-            status_register: 0xA,
+            background: Background::new(),                                            // Empty shift registers
+            sprites: Sprites::new(),                                                  // No sprites found yet
         }
     }
 
+    /// Changes the console region of the PPU and its registers, restarting
+    /// it at the pre-render line.
+    pub fn set_region(&mut self, ppu_bus: &mut PPUBus, region: Region) {
+        self.region = region;
+        self.scanline = self.pre_render_scanline();
+        self.cycle = 0;
+        self.master_cycles = 0;
+        ppu_bus.set_region(region);
+    }
+
+    /// Runs the PPU for the given number of CPU cycles: 3 dots each on NTSC
+    /// and Dendy consoles, 3.2 on PAL.
     pub fn tick(&mut self, ppu_bus: &mut PPUBus, cpu_cycles: u8) {
-        for _ in 0..(cpu_cycles * 3) { // Each CPU cycle advances the PPU by ~3
+        self.master_cycles += cpu_cycles as u32 * self.region.cpu_divider();
+        while self.master_cycles >= self.region.ppu_divider() {
+            self.master_cycles -= self.region.ppu_divider();
+            self.step(ppu_bus);
+        }
+    }
+
+    /// Runs the PPU for a single dot.
+    pub fn step(&mut self, ppu_bus: &mut PPUBus) {
+        self.advance(ppu_bus);
+
+        // VBlank and the sprite flags last until the pre-render line
+        if self.cycle == 1 {
+            if self.scanline == self.region.vblank_scanline() {
+                ppu_bus.start_vblank();
+            } else if self.scanline == self.pre_render_scanline() {
+                ppu_bus.end_vblank();
+            }
+        }
+
+        let rendering_line = self.scanline < PPU_VISIBLE_SCANLINES || self.scanline == self.pre_render_scanline();
+        let rendering = rendering_line && ppu_bus.rendering_enabled();
+        if rendering {
+            self.run_background_pipeline(ppu_bus);
+            self.run_sprite_pipeline(ppu_bus);
+        }
+
+        if self.scanline < PPU_VISIBLE_SCANLINES && (1..=PPU_LAST_VISIBLE_DOT).contains(&self.cycle) {
+            self.render_pixel(ppu_bus);
+        }
+
+        if rendering {
+            self.update_scroll(&mut ppu_bus.scroll);
+        }
+
+        ppu_bus.set_position(self.scanline, self.cycle);
+    }
+
+    /// Moves on to the next dot. Each scanline is 341 dots (0-340), but with
+    /// rendering enabled the NTSC pre-render line of odd frames ends a dot
+    /// early, after dot 339.
+    fn advance(&mut self, ppu_bus: &mut PPUBus) {
+        let skip_last_dot = self.region.skips_odd_frame_dot()
+            && self.scanline == self.pre_render_scanline()
+            && self.cycle == PPU_ODD_FRAME_SKIP_DOT
+            && self.frame_count % 2 == 1
+            && ppu_bus.rendering_enabled();
+
+        if self.cycle < PPU_CYCLES_PER_SCANLINE - 1 && !skip_last_dot {
             self.cycle += 1;
-    
-            if self.scanline < PPU_VISIBLE_SCANLINES && self.cycle < PPU_FRAME_BUFFER_WIDTH as u16 {
-                self.render_pixel(ppu_bus);
+            return;
+        }
+
+        self.cycle = 0;
+        self.scanline += 1;
+        if self.scanline == self.region.scanlines() {
+            self.start_new_frame(ppu_bus);
+        }
+    }
+
+    /// The number of frames started since power on. The PPU powers on at the
+    /// pre-render line of frame 0.
+    pub fn frame(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// The scanline being drawn (0-261 on NTSC, 0-311 on PAL and Dendy
+    /// consoles). The last is the pre-render line.
+    pub fn scanline(&self) -> u16 {
+        self.scanline
+    }
+
+    pub fn region(&self) -> Region {
+        self.region
+    }
+
+    fn pre_render_scanline(&self) -> u16 {
+        self.region.scanlines() - 1
+    }
+
+    /// The last dot run on the scanline (0-340).
+    pub fn dot(&self) -> u16 {
+        self.cycle
+    }
+
+    /// The frame, scanline and dot the PPU has just run.
+    pub fn position(&self) -> (u64, u16, u16) {
+        (self.frame_count, self.scanline, self.cycle)
+    }
+
+    /// Shifts the background shift registers and makes the dot's fetch. The
+    /// registers shift on every dot a pixel is drawn or prefetched, except
+    /// the first of each stretch.
+    fn run_background_pipeline(&mut self, ppu_bus: &PPUBus) {
+        let dot = self.cycle;
+
+        if matches!(dot, 2..=257 | 322..=337) {
+            self.background.shift();
+        }
+
+        match dot {
+            1..=PPU_LAST_VISIBLE_DOT | PPU_PREFETCH_START_DOT..=PPU_PREFETCH_END_DOT => {
+                self.background.fetch(dot, ppu_bus);
             }
+            337 | 339 => self.background.fetch_unused_nametable(ppu_bus),
+            _ => {}
+        }
+    }
     
-            if self.cycle >= PPU_CYCLES_PER_SCANLINE {
-                self.cycle = 0;
-                self.scanline += 1;
-    
-                match self.scanline {
-                    PPU_VBLANK_START_SCANLINE => self.enter_vblank(),
-                    PPU_TOTAL_SCANLINES => self.start_new_frame(),
-                    _ => {}
-                }
+    /// Finds the sprites on the next scanline once this one is drawn, then
+    /// fetches their patterns. Nothing is found on the pre-render line, so
+    /// sprites are never drawn on the first visible line.
+    fn run_sprite_pipeline(&mut self, ppu_bus: &mut PPUBus) {
+        if self.cycle == sprites::FETCH_START_DOT {
+            if self.scanline == self.pre_render_scanline() {
+                self.sprites.clear();
+            } else if self.sprites.evaluate(ppu_bus.oam(), self.scanline, Sprites::height(ppu_bus.ppu_ctrl)) {
+                ppu_bus.set_status_flag(PPUBus::STATUS_SPRITE_OVERFLOW, true);
             }
         }
+
+        if (sprites::FETCH_START_DOT..=sprites::FETCH_END_DOT).contains(&self.cycle) {
+            self.sprites.fetch(self.cycle, self.scanline, ppu_bus);
+        }
     }
-    
-    
+
+    /// Steps `v` as the fetches of a rendering scanline do: one tile across
+    /// every 8 dots, one row down at dot 256, back to the left edge at dot
+    /// 257, and back to the top during the pre-render line.
+    fn update_scroll(&self, scroll: &mut ScrollRegisters) {
+        match self.cycle {
+            PPU_LAST_VISIBLE_DOT => {
+                scroll.increment_x();
+                scroll.increment_y();
+            }
+            257 => scroll.copy_horizontal(),
+            dot if self.scanline == self.pre_render_scanline() && PPU_COPY_VERTICAL_DOTS.contains(&dot) => {
+                scroll.copy_vertical();
+            }
+            dot if dot % 8 == 0 && ((1..PPU_LAST_VISIBLE_DOT).contains(&dot) || (PPU_PREFETCH_START_DOT..=PPU_PREFETCH_END_DOT).contains(&dot)) => {
+                scroll.increment_x();
+            }
+            _ => {}
+        }
+    }
+
+    /// Draws the pixel for the current dot. Dot 1 draws the leftmost pixel.
+    /// A layer that PPUMASK hides, in full or in the leftmost 8 pixels, is
+    /// treated as transparent.
     fn render_pixel(&mut self, ppu_bus: &mut PPUBus) {
-        let x = self.cycle as usize;
+        let x = (self.cycle - 1) as usize;
         let y = self.scanline as usize;
-    
-        if x >= PPU_FRAME_BUFFER_WIDTH || y >= PPU_FRAME_BUFFER_HEIGHT {
-            return; // Skip if out of bounds
+        let left_column = x < 8;
+
+        let show_background = ppu_bus.show_background() && (!left_column || ppu_bus.show_background_left());
+        let (background_pixel, background_palette) = if show_background {
+            self.background.pixel(ppu_bus.scroll.fine_x)
+        } else {
+            (0, 0)
+        };
+
+        let show_sprites = ppu_bus.show_sprites() && (!left_column || ppu_bus.show_sprites_left());
+        let sprite = if show_sprites { self.sprites.pixel(x as u16) } else { None };
+
+        if let Some(sprite) = sprite {
+            if sprite.sprite_zero && background_pixel != 0 && Self::can_hit_sprite_zero(ppu_bus, x) {
+                ppu_bus.set_status_flag(PPUBus::STATUS_SPRITE_ZERO_HIT, true);
+            }
         }
-    
-        let color = self.fetch_background_pixel(ppu_bus, x, y);
-        self.frame_buffer[y * PPU_FRAME_BUFFER_WIDTH + x] = color;
-    }
-    
 
-    fn fetch_background_pixel(&self, ppu_bus: &PPUBus, x: usize, y: usize) -> u8 {
-        // Get tile index from nametable
-        let tile_index = self.read_nametable(ppu_bus, x, y);
-    
-        // Get tile pixel data from CHR-ROM
-        let tile_data = self.read_pattern_table(ppu_bus, tile_index, y % 8);
-    
-        // Get color palette from attribute table
-        let color_palette = self.read_attribute_table(x, y, ppu_bus);
-    
-        // Extract the correct pixel from the tile row
-        let pixel = (tile_data >> (7 - (x % 8))) & 1;
+        // Sprites behind the background only show through its transparent pixels
+        let (pixel, palette) = match sprite {
+            Some(sprite) if background_pixel == 0 || !sprite.behind_background => (sprite.pixel, sprite.palette),
+            _ => (background_pixel, background_palette),
+        };
 
-        // Convert to correct color
-        let color = self.get_final_pixel_color(ppu_bus, pixel, color_palette); 
+        let mut color = self.get_final_pixel_color(ppu_bus, pixel, palette);
+        if ppu_bus.greyscale() {
+            color &= Self::GREYSCALE_MASK;
+        }
+        self.frame_buffer[y * PPU_FRAME_BUFFER_WIDTH + x] = color as u16 | ((ppu_bus.emphasis() as u16) << Self::EMPHASIS_SHIFT);
+    }
 
-        color
-    } 
-    
-    fn read_attribute_table(&self, x: usize, y: usize, ppu_bus: &PPUBus) -> u8 {
-        let tile_x = x / 16; // 16x16 pixel block
-        let tile_y = y / 16;
-    
-        let attribute_address = Self::ATTRIBUTE_TABLE_BASE_ADDRESS + ((tile_y * 8) + tile_x) as u16;
-    
-        let attribute_byte = ppu_bus.read_byte(attribute_address);
-    
-        // Extract the correct 2-bit palette for this 8x8 tile
-        let quadrant_x = (x % 16) / 8;
-        let quadrant_y = (y % 16) / 8;
-        let shift = (quadrant_y * 2 + quadrant_x) * 2;
-    
-        (attribute_byte >> shift) & 0b11 // Extract the 2-bit palette index
+    /// Whether sprite 0 can hit the background at x. Both layers must be
+    /// shown there: neither is in a hidden left column, and the last column
+    /// (x = 255) never hits.
+    fn can_hit_sprite_zero(ppu_bus: &PPUBus, x: usize) -> bool {
+        let left_column_shown = x >= 8 || (ppu_bus.show_background_left() && ppu_bus.show_sprites_left());
+        ppu_bus.show_background() && ppu_bus.show_sprites() && left_column_shown && x != PPU_FRAME_BUFFER_WIDTH - 1
     }
-    
+
     fn get_final_pixel_color(&self, ppu_bus: &PPUBus, pixel: u8, color_palette: u8) -> u8 {
         if pixel == 0 {
-            return 0; // Color 0 is transparent
+            // Transparent pixels show the backdrop colour
+            return ppu_bus.read_byte(Self::PALETTE_BASE_ADDRESS);
         } 
     
         let color_index = Self::PALETTE_BASE_ADDRESS + ((color_palette as u16 * 4) + pixel as u16) as u16;
@@ -160,57 +276,10 @@ impl PPU {
         color
     }
     
-    fn read_pattern_table(&self, ppu_bus: &PPUBus, tile_index: u8, row: usize) -> u8 {
-        let pattern_table_base = if self.control_register & 0b0001_0000 != 0 {
-            0x1000 // Pattern Table 1
-        } else {
-            0x0000 // Pattern Table 0
-        };
-    
-        let tile_address = pattern_table_base + (tile_index as u16 * 16) + row as u16;
-        let low_byte = ppu_bus.read_byte(tile_address);
-        let high_byte = ppu_bus.read_byte(tile_address + 8);
-    
-        low_byte | (high_byte << 1) // This is how NES forms a 2-bit color index
-    }
-    
-    fn read_nametable(&self, ppu_bus: &PPUBus, x: usize, y: usize) -> u8 {
-        let tile_x = x / 8;
-        let tile_y = y / 8;
-    
-        let base_address = ((tile_y * 32) + tile_x) as u16;
-        let tile_index = ppu_bus.read_byte(base_address);
-    
-        tile_index
-    }
-    
-    
-    fn read_palette(&self, ppu_bus: &PPUBus, address: u16) -> u8 {
-        let mirrored_address = match address {
-            0x3F10 | 0x3F14 | 0x3F18 | 0x3F1C => address - 0x10, // Mirror sprite palettes
-            _ => address,
-        };
-    
-        ppu_bus.memory()[mirrored_address as usize]
-    }
-
-    fn enter_vblank(&mut self) {
-        self.status_register |= 0x80;
-        
-        if self.control_register & 0x80 != 0 {
-            self.nmi_triggered = true;
-        }
-    }
-    
-
-    fn start_new_frame(&mut self) {
+    fn start_new_frame(&mut self, ppu_bus: &mut PPUBus) {
         self.scanline = 0;
         self.frame_count += 1;
-    
-        // Clear VBlank flag only if it hasn't been cleared by CPU read
-        if self.status_register & STATUS_VBLANK_FLAG != 0 {
-            self.status_register &= !STATUS_VBLANK_FLAG;
-        }
+        ppu_bus.decay_io_latch();
     }
     
     
@@ -237,8 +306,8 @@ impl PPU {
     
         for row in 0..8 {
             let tile_address = tile_index * TILE_SIZE + row;
-            let low_byte = ppu_bus.memory()[tile_address];
-            let high_byte = ppu_bus.memory()[tile_address + 8];
+            let low_byte = ppu_bus.read_byte(tile_address as u16);
+            let high_byte = ppu_bus.read_byte(tile_address as u16 + 8);
     
             let mut row_str = String::new();
     
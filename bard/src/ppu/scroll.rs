@@ -0,0 +1,146 @@
+//! # scroll.rs
+//!
+//!  Author: Paul Hazen
+//! Created: 2026-10-19
+//! License: MIT (see LICENSE file)
+//!
+//! ## Description
+//! The PPU's internal scroll registers, known as the "Loopy" registers after
+//! the person who first documented them. `v` is the VRAM address the PPU
+//! fetches from, `t` the address rendering restarts from each scanline and
+//! frame, `fine_x` the pixel within the first tile, and `w` the write toggle
+//! shared by $2005 and $2006.
+
+/*
+    v / t
+    ----------------------------------------------------------------
+    | Bits  | Function                                             |
+    ----------------------------------------------------------------
+    |  4-0  | Coarse X scroll (tile column)                        |
+    |  9-5  | Coarse Y scroll (tile row)                           |
+    | 11-10 | Nametable select                                     |
+    | 14-12 | Fine Y scroll (row within the tile)                  |
+    ----------------------------------------------------------------
+ */
+const COARSE_X: u16 = 0x001F;
+const COARSE_Y: u16 = 0x03E0;
+const NAMETABLE_X: u16 = 0x0400;
+const NAMETABLE_Y: u16 = 0x0800;
+const NAMETABLE: u16 = NAMETABLE_X | NAMETABLE_Y;
+const FINE_Y: u16 = 0x7000;
+
+const HORIZONTAL_BITS: u16 = COARSE_X | NAMETABLE_X;
+const VERTICAL_BITS: u16 = FINE_Y | NAMETABLE_Y | COARSE_Y;
+
+/// `v` and `t` are 15 bits wide.
+const REGISTER_MASK: u16 = 0x7FFF;
+/// The part of `v` that reaches the PPU's address bus.
+const ADDRESS_MASK: u16 = 0x3FFF;
+
+/// The last tile row of a nametable; the attribute table follows it.
+const LAST_TILE_ROW: u16 = 29;
+/// The last row coarse Y can hold.
+const LAST_COARSE_Y: u16 = 31;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ScrollRegisters {
+    /// The current VRAM address.
+    pub v: u16,
+    /// The temporary VRAM address, the top left of the screen.
+    pub t: u16,
+    /// The fine X scroll (0-7).
+    pub fine_x: u8,
+    /// Whether the next $2005 / $2006 write is the second of the pair.
+    pub w: bool,
+}
+
+impl ScrollRegisters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The address `v` puts on the PPU's address bus.
+    pub fn address(&self) -> u16 {
+        self.v & ADDRESS_MASK
+    }
+
+    /// $2000 writes select the nametable rendering starts from.
+    pub fn write_control(&mut self, value: u8) {
+        self.t = (self.t & !NAMETABLE) | (((value & 0x03) as u16) << 10);
+    }
+
+    /// $2005 writes set the X scroll, then the Y scroll.
+    pub fn write_scroll(&mut self, value: u8) {
+        if !self.w {
+            self.t = (self.t & !COARSE_X) | (value >> 3) as u16;
+            self.fine_x = value & 0x07;
+        } else {
+            self.t = (self.t & !(COARSE_Y | FINE_Y)) | (((value & 0xF8) as u16) << 2) | (((value & 0x07) as u16) << 12);
+        }
+        self.w = !self.w;
+    }
+
+    /// $2006 writes set the high 6 bits, then the low byte of `t`, which is
+    /// then copied to `v`. The first write clears bit 14.
+    pub fn write_address(&mut self, value: u8) {
+        if !self.w {
+            self.t = (self.t & 0x00FF) | (((value & 0x3F) as u16) << 8);
+        } else {
+            self.t = (self.t & 0xFF00) | value as u16;
+            self.v = self.t;
+        }
+        self.w = !self.w;
+    }
+
+    /// Reading $2002 resets the write toggle.
+    pub fn reset_latch(&mut self) {
+        self.w = false;
+    }
+
+    /// Steps `v` after a $2007 access.
+    pub fn increment(&mut self, amount: u16) {
+        self.v = self.v.wrapping_add(amount) & REGISTER_MASK;
+    }
+
+    /// Moves `v` to the next tile column, into the next nametable across
+    /// after column 31.
+    pub fn increment_x(&mut self) {
+        if self.v & COARSE_X == COARSE_X {
+            self.v &= !COARSE_X;
+            self.v ^= NAMETABLE_X;
+        } else {
+            self.v += 1;
+        }
+    }
+
+    /// Moves `v` to the next pixel row. After tile row 29 it wraps into the
+    /// nametable below; a coarse Y pointed into the attribute table instead
+    /// runs on to row 31 and wraps to 0 without switching nametable.
+    pub fn increment_y(&mut self) {
+        if self.v & FINE_Y != FINE_Y {
+            self.v += 0x1000;
+            return;
+        }
+
+        self.v &= !FINE_Y;
+        let coarse_y = match (self.v & COARSE_Y) >> 5 {
+            LAST_TILE_ROW => {
+                self.v ^= NAMETABLE_Y;
+                0
+            }
+            LAST_COARSE_Y => 0,
+            row => row + 1,
+        };
+        self.v = (self.v & !COARSE_Y) | (coarse_y << 5);
+    }
+
+    /// Restarts the scanline at the left edge given by `t`.
+    pub fn copy_horizontal(&mut self) {
+        self.v = (self.v & !HORIZONTAL_BITS) | (self.t & HORIZONTAL_BITS);
+    }
+
+    /// Restarts the frame at the top edge given by `t`.
+    pub fn copy_vertical(&mut self) {
+        self.v = (self.v & !VERTICAL_BITS) | (self.t & VERTICAL_BITS);
+    }
+}
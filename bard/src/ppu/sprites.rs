@@ -0,0 +1,237 @@
+//! # sprites.rs
+//!
+//!  Author: Paul Hazen
+//! Created: 2026-10-19
+//! License: MIT (see LICENSE file)
+//!
+//! ## Description
+//! The sprite half of the PPU's rendering pipeline. While a scanline is
+//! drawn the PPU searches OAM for the (at most 8) sprites on the next line
+//! and copies them into secondary OAM. During dots 257-320 it fetches their
+//! pattern rows into 8 output units, which are drawn over or under the
+//! background on the next line.
+use crate::{mapper::PpuFetchSource, memory::{Bus, PPUBus}};
+
+/// The number of sprites in OAM.
+const OAM_SPRITES: usize = 64;
+/// The number of sprites that can be drawn on one scanline.
+pub const SPRITES_PER_LINE: usize = 8;
+const OAM_ENTRY_SIZE: usize = 4;
+
+/// PPUCTRL bit 3 - the pattern table 8x8 sprites use.
+const CONTROL_SPRITE_TABLE: u8 = 0b0000_1000;
+/// PPUCTRL bit 5 - 8x16 sprites.
+const CONTROL_TALL_SPRITES: u8 = 0b0010_0000;
+const PATTERN_TABLE_SIZE: u16 = 0x1000;
+const NAMETABLE_START: u16 = 0x2000;
+const TILE_SIZE: u16 = 16;
+const HIGH_PLANE_OFFSET: u16 = 8;
+
+/* OAM byte 2 - Attributes
+ * --- | Bit | Function
+ *     | 1-0 | Palette (4 to 7)
+ *     |  5  | Priority (0 = in front of background, 1 = behind)
+ *     |  6  | Flip horizontally
+ *     |  7  | Flip vertically
+ */
+const ATTRIBUTE_PALETTE: u8 = 0b0000_0011;
+const ATTRIBUTE_BEHIND_BACKGROUND: u8 = 0b0010_0000;
+const ATTRIBUTE_FLIP_HORIZONTAL: u8 = 0b0100_0000;
+const ATTRIBUTE_FLIP_VERTICAL: u8 = 0b1000_0000;
+
+/// The sprite palettes follow the 4 background palettes.
+const SPRITE_PALETTE_OFFSET: u8 = 4;
+
+/// The first dot of the sprite fetches, and how many dots each sprite takes.
+pub const FETCH_START_DOT: u16 = 257;
+pub const FETCH_END_DOT: u16 = 320;
+const DOTS_PER_SPRITE: u16 = 8;
+
+/// A sprite pixel that is not transparent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SpritePixel {
+    /// The pixel value (1-3).
+    pub pixel: u8,
+    /// The palette (4-7).
+    pub palette: u8,
+    pub behind_background: bool,
+    /// Whether the pixel belongs to sprite 0, for the sprite 0 hit flag.
+    pub sprite_zero: bool,
+}
+
+/// A sprite loaded for the current scanline.
+#[derive(Clone, Copy, Default)]
+struct SpriteUnit {
+    x: u8,
+    attributes: u8,
+    // Already flipped, so bit 7 is always the leftmost pixel
+    pattern_low: u8,
+    pattern_high: u8,
+}
+
+#[derive(Default)]
+pub struct Sprites {
+    /// The sprites found on the next scanline, in OAM order.
+    secondary_oam: [[u8; OAM_ENTRY_SIZE]; SPRITES_PER_LINE],
+    found: usize,
+    /// Whether OAM's first sprite is among those found (always in slot 0).
+    sprite_zero_found: bool,
+    /// The sprites being drawn on this scanline.
+    units: [SpriteUnit; SPRITES_PER_LINE],
+    unit_count: usize,
+    sprite_zero_loaded: bool,
+    /// The sprites fetched so far for the next scanline.
+    next_units: [SpriteUnit; SPRITES_PER_LINE],
+}
+
+impl Sprites {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The height of the sprites PPUCTRL selects.
+    pub fn height(ppu_ctrl: u8) -> u16 {
+        if ppu_ctrl & CONTROL_TALL_SPRITES != 0 { 16 } else { 8 }
+    }
+
+    /// Finds the first 8 sprites (in OAM order) on the scanline after this
+    /// one. A sprite's Y is one less than the first line it is drawn on.
+    ///
+    /// Once 8 are found the PPU goes on looking for a 9th to set the overflow
+    /// flag, but steps through the bytes of each sprite as it steps through
+    /// the sprites. It compares tile numbers, attributes and X positions as
+    /// if they were Y positions, so misses some overflows and reports others
+    /// that did not happen.
+    ///
+    /// # Arguments
+    ///
+    /// * `oam` - The 64 sprites, 4 bytes each.
+    /// * `scanline` - The scanline being drawn.
+    /// * `height` - 8 or 16.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether the sprite overflow flag should be set.
+    pub fn evaluate(&mut self, oam: &[u8; 256], scanline: u16, height: u16) -> bool {
+        let in_range = |y: u8| scanline.wrapping_sub(y as u16) < height;
+
+        self.found = 0;
+        self.sprite_zero_found = false;
+
+        let mut sprite = 0;
+        while sprite < OAM_SPRITES && self.found < SPRITES_PER_LINE {
+            let entry = &oam[sprite * OAM_ENTRY_SIZE..(sprite + 1) * OAM_ENTRY_SIZE];
+            if in_range(entry[0]) {
+                self.secondary_oam[self.found].copy_from_slice(entry);
+                self.sprite_zero_found |= sprite == 0;
+                self.found += 1;
+            }
+            sprite += 1;
+        }
+
+        let mut byte = 0;
+        while sprite < OAM_SPRITES {
+            if in_range(oam[sprite * OAM_ENTRY_SIZE + byte]) {
+                return true;
+            }
+            sprite += 1;
+            byte = (byte + 1) % OAM_ENTRY_SIZE;
+        }
+
+        false
+    }
+
+    /// Forgets the sprites found, as on the pre-render line where no
+    /// evaluation takes place.
+    pub fn clear(&mut self) {
+        self.found = 0;
+        self.sprite_zero_found = false;
+    }
+
+    /// Makes the dot's sprite fetch (dots 257-320): two unused nametable
+    /// reads, then the two pattern planes of one sprite's row. Slots without
+    /// a sprite fetch tile $FF and draw nothing.
+    pub fn fetch(&mut self, dot: u16, scanline: u16, ppu_bus: &PPUBus) {
+        let slot = ((dot - FETCH_START_DOT) / DOTS_PER_SPRITE) as usize;
+        let [y, tile, attributes, x] = if slot < self.found { self.secondary_oam[slot] } else { [0xFF; 4] };
+
+        ppu_bus.set_fetch_source(PpuFetchSource::Sprite);
+        match (dot - FETCH_START_DOT) % DOTS_PER_SPRITE {
+            0 | 2 => {
+                ppu_bus.read_byte(NAMETABLE_START | (ppu_bus.scroll.v & 0x0FFF));
+            }
+            4 => {
+                let address = Self::pattern_address(ppu_bus.ppu_ctrl, scanline, y, tile, attributes);
+                let pattern = ppu_bus.read_byte(address);
+                self.next_units[slot] = SpriteUnit { x, attributes, pattern_low: 0, pattern_high: 0 };
+                if slot < self.found {
+                    self.next_units[slot].pattern_low = Self::flip(pattern, attributes);
+                }
+            }
+            6 => {
+                let address = Self::pattern_address(ppu_bus.ppu_ctrl, scanline, y, tile, attributes);
+                let pattern = ppu_bus.read_byte(address + HIGH_PLANE_OFFSET);
+                if slot < self.found {
+                    self.next_units[slot].pattern_high = Self::flip(pattern, attributes);
+                }
+            }
+            _ => {}
+        }
+
+        // The fetched sprites are drawn on the next scanline
+        if dot == FETCH_END_DOT {
+            self.units = self.next_units;
+            self.unit_count = self.found;
+            self.sprite_zero_loaded = self.sprite_zero_found;
+        }
+    }
+
+    /// The frontmost sprite pixel at the given x on this scanline, or `None`
+    /// if every sprite there is transparent.
+    pub fn pixel(&self, x: u16) -> Option<SpritePixel> {
+        self.units[..self.unit_count].iter().enumerate().find_map(|(slot, unit)| {
+            let column = x.wrapping_sub(unit.x as u16);
+            if column >= 8 {
+                return None;
+            }
+
+            let bit = 0x80 >> column;
+            let pixel = (u8::from(unit.pattern_high & bit != 0) << 1) | u8::from(unit.pattern_low & bit != 0);
+            (pixel != 0).then_some(SpritePixel {
+                pixel,
+                palette: SPRITE_PALETTE_OFFSET + (unit.attributes & ATTRIBUTE_PALETTE),
+                behind_background: unit.attributes & ATTRIBUTE_BEHIND_BACKGROUND != 0,
+                sprite_zero: slot == 0 && self.sprite_zero_loaded,
+            })
+        })
+    }
+
+    /// The address of a sprite's pattern row. 8x16 sprites take their
+    /// pattern table from bit 0 of the tile number and use the tile pair
+    /// starting at the even tile.
+    fn pattern_address(ppu_ctrl: u8, scanline: u16, y: u8, tile: u8, attributes: u8) -> u16 {
+        let height = Self::height(ppu_ctrl);
+        let mut row = scanline.wrapping_sub(y as u16) % height;
+        if attributes & ATTRIBUTE_FLIP_VERTICAL != 0 {
+            row = height - 1 - row;
+        }
+
+        let (table, tile) = if height == 16 {
+            let table = if tile & 0x01 != 0 { PATTERN_TABLE_SIZE } else { 0 };
+            (table, (tile & 0xFE) as u16 + row / 8)
+        } else {
+            let table = if ppu_ctrl & CONTROL_SPRITE_TABLE != 0 { PATTERN_TABLE_SIZE } else { 0 };
+            (table, tile as u16)
+        };
+
+        table + tile * TILE_SIZE + (row % 8)
+    }
+
+    fn flip(pattern: u8, attributes: u8) -> u8 {
+        if attributes & ATTRIBUTE_FLIP_HORIZONTAL != 0 {
+            pattern.reverse_bits()
+        } else {
+            pattern
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! # region.rs
+//!
+//!  Author: Paul Hazen
+//! Created: 2026-10-19
+//! License: MIT (see LICENSE file)
+//!
+//! ## Description
+//! The timing of the three families of consoles. Each divides its master
+//! clock down for the CPU and the PPU: the NTSC PPU runs 3 dots per CPU
+//! cycle, the PAL PPU 3.2. PAL and Dendy consoles draw 312 scanlines a frame,
+//! which PAL spends on a long VBlank and the Dendy on idle lines before a
+//! VBlank as long as NTSC's, so that NTSC games keep their timing.
+use crate::cartridge::TimingRegion;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Region {
+    /// The RP2A03 and RP2C02 of the NTSC NES and the Famicom.
+    #[default]
+    Ntsc,
+    /// The RP2A07 and RP2C07 of the PAL NES.
+    Pal,
+    /// The UA6527P and UA6538 of the Dendy and other PAL Famiclones.
+    Dendy,
+}
+
+impl Region {
+    /// The region a header's timing asks for. Games that run on any console
+    /// are run as NTSC.
+    pub fn from_timing(timing: TimingRegion) -> Self {
+        match timing {
+            TimingRegion::Ntsc | TimingRegion::MultiRegion => Region::Ntsc,
+            TimingRegion::Pal => Region::Pal,
+            TimingRegion::Dendy => Region::Dendy,
+        }
+    }
+
+    /// The master clock in Hz.
+    pub fn master_clock(self) -> f64 {
+        match self {
+            Region::Ntsc => 21_477_272.0,
+            Region::Pal | Region::Dendy => 26_601_712.0,
+        }
+    }
+
+    /// Master clock cycles per CPU cycle.
+    pub fn cpu_divider(self) -> u32 {
+        match self {
+            Region::Ntsc => 12,
+            Region::Pal => 16,
+            Region::Dendy => 15,
+        }
+    }
+
+    /// Master clock cycles per PPU dot.
+    pub fn ppu_divider(self) -> u32 {
+        match self {
+            Region::Ntsc => 4,
+            Region::Pal | Region::Dendy => 5,
+        }
+    }
+
+    /// Frames drawn per second: about 60.1 on NTSC consoles, 50 on the others.
+    pub fn frame_rate(self) -> f64 {
+        let dots_per_frame = 341.0 * self.scanlines() as f64;
+        self.master_clock() / (self.ppu_divider() as f64 * dots_per_frame)
+    }
+
+    /// The CPU clock in Hz.
+    pub fn cpu_clock(self) -> f64 {
+        self.master_clock() / self.cpu_divider() as f64
+    }
+
+    /// The scanlines in a frame, the last being the pre-render line.
+    pub fn scanlines(self) -> u16 {
+        match self {
+            Region::Ntsc => 262,
+            Region::Pal | Region::Dendy => 312,
+        }
+    }
+
+    /// The scanline VBlank starts on. It lasts until the pre-render line:
+    /// 20 scanlines on NTSC and Dendy consoles, 70 on PAL.
+    pub fn vblank_scanline(self) -> u16 {
+        match self {
+            Region::Ntsc | Region::Pal => 241,
+            Region::Dendy => 291,
+        }
+    }
+
+    /// Whether odd frames are a dot shorter while rendering, which only the
+    /// NTSC PPU does.
+    pub fn skips_odd_frame_dot(self) -> bool {
+        self == Region::Ntsc
+    }
+
+    /// Whether PPUMASK bit 5 emphasises green and bit 6 red, the other way
+    /// round from NTSC.
+    pub fn swaps_red_green_emphasis(self) -> bool {
+        self != Region::Ntsc
+    }
+}
@@ -0,0 +1,36 @@
+//! # settings.rs
+//!
+//!  Author: Paul Hazen
+//! Created: 2026-10-19
+//! License: MIT (see LICENSE file)
+//!
+//! ## Description
+//! User settings that change how the emulator runs a ROM.
+use std::path::PathBuf;
+
+use crate::region::Region;
+
+/// Options passed to `NES::open_rom_with_settings`.
+#[derive(Clone, Debug, Default)]
+pub struct Settings {
+    /// Directory that battery saves (`.sav` files) are kept in. When `None`
+    /// saves are written next to the ROM.
+    pub saves_directory: Option<PathBuf>,
+    /// A NES 2.0 or No-Intro XML database. None is bundled, so without one
+    /// headers cannot be corrected or regions looked up.
+    pub rom_database: Option<PathBuf>,
+    /// Whether header fields are replaced with the values from the ROM
+    /// database before the mapper is chosen.
+    pub correct_headers: bool,
+    /// The Famicom Disk System BIOS. When `None`, `disksys.rom` is looked for
+    /// beside the disk image and in the working directory.
+    pub fds_bios: Option<PathBuf>,
+    /// The eight DIP switches of a Vs. System board, switch 1 in bit 0. Each
+    /// game uses them for its own options (coins per credit, difficulty, ...).
+    pub vs_dip_switches: u8,
+    /// A `.pal` file to use instead of a Vs. System RP2C04 PPU's own palette.
+    pub vs_palette: Option<PathBuf>,
+    /// The console region to emulate. When `None` it is taken from the
+    /// header, or from the ROM database for headers without one.
+    pub region: Option<Region>,
+}
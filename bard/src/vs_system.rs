@@ -0,0 +1,247 @@
+//! # vs_system.rs
+//!
+//!  Author: Paul Hazen
+//! Created: 2026-10-19
+//! License: MIT (see LICENSE file)
+//!
+//! ## Description
+//! The parts of the Vs. System mainboard that a home console does not have:
+//! the DIP switches, coin slots and service button read through $4016 /
+//! $4017, the coin counter at $4020, the protection chips some games check
+//! for, and the palettes of the RGB PPUs.
+//!
+//! Controllers are not emulated, so their data bits always read 0. TKO Boxing's
+//! protection chip is not emulated either.
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::cartridge::{VsHardwareType, VsPpuType, VsSystemType};
+
+/* $4016 - Read
+ * --- | Bit | Function
+ *     |  0  | Controller 1 data
+ *     |  2  | Service button
+ *     | 3-4 | DIP switches 1 and 2
+ *     |  5  | Coin slot 1
+ *     |  6  | Coin slot 2
+ */
+const INPUT_SERVICE: u8 = 0b0000_0100;
+const INPUT_DIP_SHIFT: u8 = 3;
+const INPUT_DIP_MASK: u8 = 0b0001_1000;
+const INPUT_COIN_1: u8 = 0b0010_0000;
+const INPUT_COIN_2: u8 = 0b0100_0000;
+
+/* $4017 - Read
+ * --- | Bit | Function
+ *     |  0  | Controller 2 data
+ *     | 2-7 | DIP switches 3 to 8
+ */
+const INPUT_2_DIP_MASK: u8 = 0b1111_1100;
+
+/// $4020 bit 0 - the coin counter's coil.
+const COIN_COUNTER: u8 = 0b0000_0001;
+
+pub const CONTROLLER_1: u16 = 0x4016;
+pub const CONTROLLER_2: u16 = 0x4017;
+pub const COIN_COUNTER_ADDRESS: u16 = 0x4020;
+
+/// The palette shared by the RP2C03, RC2C03 and RC2C05, and the PlayChoice-10.
+/// Each octal digit is one 3 bit channel: red, green, blue.
+const RGB_PALETTE: [u16; 64] = [
+    0o333, 0o014, 0o006, 0o326, 0o403, 0o503, 0o510, 0o420, 0o320, 0o120, 0o031, 0o040, 0o022, 0o000, 0o000, 0o000,
+    0o555, 0o036, 0o027, 0o407, 0o507, 0o704, 0o700, 0o630, 0o430, 0o140, 0o040, 0o053, 0o044, 0o000, 0o000, 0o000,
+    0o777, 0o357, 0o447, 0o637, 0o707, 0o737, 0o740, 0o750, 0o660, 0o360, 0o070, 0o276, 0o077, 0o000, 0o000, 0o000,
+    0o777, 0o567, 0o657, 0o757, 0o747, 0o755, 0o764, 0o772, 0o773, 0o572, 0o473, 0o276, 0o467, 0o000, 0o000, 0o000,
+];
+
+/// The colours of the RP2C04-0001 to RP2C04-0004, as indices into
+/// `RGB_PALETTE`. Each PPU puts the same colours in its own order.
+const RP2C04_PALETTES: [[u8; 64]; 4] = [
+    [
+        0x35, 0x23, 0x16, 0x22, 0x1C, 0x09, 0x1D, 0x15, 0x20, 0x00, 0x27, 0x05, 0x04, 0x28, 0x08, 0x20,
+        0x21, 0x3E, 0x1F, 0x29, 0x3C, 0x32, 0x36, 0x12, 0x3F, 0x2B, 0x2E, 0x1E, 0x3D, 0x2D, 0x24, 0x01,
+        0x0E, 0x31, 0x33, 0x2A, 0x2C, 0x0C, 0x1B, 0x14, 0x2E, 0x07, 0x34, 0x06, 0x13, 0x02, 0x26, 0x2E,
+        0x2E, 0x19, 0x10, 0x0A, 0x39, 0x03, 0x37, 0x17, 0x0F, 0x11, 0x0B, 0x0D, 0x38, 0x25, 0x18, 0x3A,
+    ],
+    [
+        0x2E, 0x27, 0x18, 0x39, 0x3A, 0x25, 0x1C, 0x31, 0x16, 0x13, 0x38, 0x34, 0x20, 0x23, 0x3C, 0x0B,
+        0x0F, 0x21, 0x06, 0x3D, 0x1B, 0x29, 0x1E, 0x22, 0x1D, 0x24, 0x0E, 0x2B, 0x32, 0x08, 0x2E, 0x03,
+        0x04, 0x36, 0x26, 0x33, 0x11, 0x1F, 0x10, 0x02, 0x14, 0x3F, 0x00, 0x09, 0x12, 0x2E, 0x28, 0x20,
+        0x3E, 0x0D, 0x2A, 0x17, 0x0C, 0x01, 0x15, 0x19, 0x2E, 0x2C, 0x07, 0x37, 0x35, 0x05, 0x0A, 0x2D,
+    ],
+    [
+        0x14, 0x25, 0x3A, 0x10, 0x0B, 0x20, 0x31, 0x09, 0x01, 0x2E, 0x36, 0x08, 0x15, 0x3D, 0x3E, 0x3C,
+        0x22, 0x1C, 0x05, 0x12, 0x19, 0x18, 0x17, 0x1B, 0x00, 0x03, 0x2E, 0x02, 0x16, 0x06, 0x34, 0x35,
+        0x23, 0x0F, 0x0E, 0x37, 0x0D, 0x27, 0x26, 0x20, 0x29, 0x04, 0x21, 0x24, 0x11, 0x2D, 0x2E, 0x1F,
+        0x2C, 0x1E, 0x39, 0x33, 0x07, 0x2A, 0x28, 0x1D, 0x0A, 0x2E, 0x32, 0x38, 0x13, 0x2B, 0x3F, 0x0C,
+    ],
+    [
+        0x18, 0x03, 0x1C, 0x28, 0x2E, 0x35, 0x01, 0x17, 0x10, 0x1F, 0x2A, 0x0E, 0x36, 0x37, 0x0B, 0x39,
+        0x25, 0x1E, 0x12, 0x34, 0x2E, 0x1D, 0x06, 0x26, 0x3E, 0x1B, 0x22, 0x19, 0x04, 0x2E, 0x3A, 0x21,
+        0x05, 0x0A, 0x07, 0x02, 0x13, 0x14, 0x00, 0x15, 0x0C, 0x3D, 0x11, 0x0F, 0x0D, 0x38, 0x2D, 0x24,
+        0x33, 0x20, 0x08, 0x16, 0x3F, 0x2B, 0x20, 0x3C, 0x2E, 0x27, 0x23, 0x31, 0x29, 0x32, 0x2C, 0x09,
+    ],
+];
+
+/// The size of a `.pal` file: 64 colours of red, green and blue bytes.
+const PALETTE_FILE_SIZE: usize = 64 * 3;
+
+pub struct VsSystem {
+    system: VsSystemType,
+    /// Switch 1 is bit 0. A set bit is a switch turned on.
+    dip_switches: u8,
+    coins: [bool; 2],
+    service: bool,
+    coin_counter: bool,
+    coins_counted: u32,
+    protection_counter: u8,
+}
+
+impl VsSystem {
+    pub fn new(system: VsSystemType, dip_switches: u8) -> Self {
+        if system.hardware == VsHardwareType::UnisystemTkoBoxing {
+            println!("WARNING: The TKO Boxing protection chip is not emulated.");
+        }
+
+        Self {
+            system,
+            dip_switches,
+            coins: [false; 2],
+            service: false,
+            coin_counter: false,
+            coins_counted: 0,
+            protection_counter: 0,
+        }
+    }
+
+    pub fn system(&self) -> VsSystemType {
+        self.system
+    }
+
+    pub fn dip_switches(&self) -> u8 {
+        self.dip_switches
+    }
+
+    pub fn set_dip_switches(&mut self, dip_switches: u8) {
+        self.dip_switches = dip_switches;
+    }
+
+    /// Holds a coin in one of the two slots (0 or 1) while `inserted` is set.
+    pub fn set_coin(&mut self, slot: usize, inserted: bool) {
+        self.coins[slot] = inserted;
+    }
+
+    pub fn set_service_button(&mut self, pressed: bool) {
+        self.service = pressed;
+    }
+
+    /// The number of coins the game has counted through $4020.
+    pub fn coins_counted(&self) -> u32 {
+        self.coins_counted
+    }
+
+    /// Reads $4016 or $4017.
+    pub fn read_input(&self, address: u16) -> u8 {
+        if address == CONTROLLER_2 {
+            return self.dip_switches & INPUT_2_DIP_MASK;
+        }
+
+        let mut value = (self.dip_switches << INPUT_DIP_SHIFT) & INPUT_DIP_MASK;
+        for (pressed, bit) in [(self.service, INPUT_SERVICE), (self.coins[0], INPUT_COIN_1), (self.coins[1], INPUT_COIN_2)] {
+            if pressed {
+                value |= bit;
+            }
+        }
+        value
+    }
+
+    /// Writes $4020. The counter advances when its coil is energised.
+    pub fn write_coin_counter(&mut self, value: u8) {
+        let coin_counter = value & COIN_COUNTER != 0;
+        if coin_counter && !self.coin_counter {
+            self.coins_counted += 1;
+        }
+        self.coin_counter = coin_counter;
+    }
+
+    /// Reads a protection chip on the game board.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(u8)` if the board's protection chip answers at the address.
+    /// * `None` if the cartridge should answer instead.
+    pub fn read_protection(&mut self, address: u16) -> Option<u8> {
+        match (self.system.hardware, address) {
+            (VsHardwareType::UnisystemRbiBaseball, 0x5E00) => {
+                self.protection_counter = 0;
+                None
+            }
+            (VsHardwareType::UnisystemRbiBaseball, 0x5E01) => {
+                let value = if self.protection_counter == 9 { 0x6F } else { 0xB4 };
+                self.protection_counter = self.protection_counter.wrapping_add(1);
+                Some(value)
+            }
+            (VsHardwareType::UnisystemSuperXevious, 0x54FF) => Some(0x05),
+            (VsHardwareType::UnisystemSuperXevious, 0x5678) => Some(if self.protection_counter != 0 { 0x00 } else { 0x01 }),
+            (VsHardwareType::UnisystemSuperXevious, 0x578F) => Some(if self.protection_counter != 0 { 0xD1 } else { 0x89 }),
+            (VsHardwareType::UnisystemSuperXevious, 0x5567) => {
+                self.protection_counter ^= 1;
+                Some(if self.protection_counter != 0 { 0x37 } else { 0x3E })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The RGB palette of the RP2C03, RC2C03, RC2C05 and the PlayChoice-10's PPU.
+pub fn rgb_palette() -> [u32; 64] {
+    RGB_PALETTE.map(|color| {
+        let channel = |shift: u16| ((color >> shift) & 0o7) as u32 * 255 / 7;
+        (channel(6) << 16) | (channel(3) << 8) | channel(0)
+    })
+}
+
+/// Chooses the palette for a Vs. System PPU. The RP2C04s each scramble the
+/// colours in their own order.
+///
+/// # Arguments
+///
+/// * `ppu` - The PPU fitted to the board.
+/// * `palette_file` - A `.pal` file used instead of an RP2C04's own palette.
+pub fn palette(ppu: VsPpuType, palette_file: Option<&Path>) -> [u32; 64] {
+    let VsPpuType::Rp2c04(variant) = ppu else {
+        return rgb_palette();
+    };
+
+    let Some(order) = RP2C04_PALETTES.get(variant.wrapping_sub(1) as usize) else {
+        println!("WARNING: There is no RP2C04-000{}, using the RGB palette.", variant);
+        return rgb_palette();
+    };
+    let rgb = rgb_palette();
+    let own_palette = order.map(|index| rgb[index as usize]);
+
+    match palette_file.map(load_palette) {
+        Some(Ok(palette)) => palette,
+        Some(Err(error)) => {
+            println!("WARNING: Could not read palette: {}, using the RP2C04-000{} palette.", error, variant);
+            own_palette
+        }
+        None => own_palette,
+    }
+}
+
+/// Reads a `.pal` file of 64 RGB triplets. Larger files (with the emphasis
+/// variants) only have their first 64 colours used.
+pub fn load_palette(path: &Path) -> io::Result<[u32; 64]> {
+    let data = fs::read(path)?;
+    if data.len() < PALETTE_FILE_SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "A palette needs 64 RGB colours."));
+    }
+
+    let mut palette = [0; 64];
+    for (color, rgb) in palette.iter_mut().zip(data.chunks_exact(3)) {
+        *color = ((rgb[0] as u32) << 16) | ((rgb[1] as u32) << 8) | rgb[2] as u32;
+    }
+    Ok(palette)
+}
@@ -0,0 +1,163 @@
+mod common;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use bard::apu::Apu;
+use bard::cartridge::Cartridge;
+use bard::memory::{Bus, CPUBus};
+use bard::region::Region;
+use bard::nsf_player::{self, NsfPlayer};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// CPU cycles in one 4 step frame counter sequence.
+    const FRAME_CYCLES: usize = 29_830;
+
+    fn clock(apu: &mut Apu, cycles: usize) {
+        for _ in 0..cycles {
+            apu.clock();
+        }
+    }
+
+    #[test]
+    fn test_pulse_output() {
+        let mut apu = Apu::new();
+        apu.write_register(0x4015, 0x01);
+        apu.write_register(0x4000, 0xBF); // 50% duty, halted, constant volume 15
+        apu.write_register(0x4002, 0x80);
+        apu.write_register(0x4003, 0x00);
+
+        // The waveform alternates between silence and full volume
+        let mut levels = Vec::new();
+        for _ in 0..0x800 {
+            apu.clock();
+            levels.push(apu.channel_levels()[0]);
+        }
+        assert!(levels.contains(&15));
+        assert!(levels.contains(&0));
+        assert_eq!(apu.read_status() & 0x01, 0x01);
+
+        // Periods below 8 are muted
+        apu.write_register(0x4002, 0x07);
+        clock(&mut apu, 0x100);
+        assert_eq!(apu.channel_levels()[0], 0);
+
+        // Disabling the channel clears its length counter
+        apu.write_register(0x4015, 0x00);
+        assert_eq!(apu.read_status() & 0x01, 0x00);
+    }
+
+    #[test]
+    fn test_length_counter_expires() {
+        let mut apu = Apu::new();
+        apu.write_register(0x4015, 0x08);
+        apu.write_register(0x400C, 0x0F); // Not halted
+        apu.write_register(0x400F, 0x18); // Length index 3 (2 half frames)
+        assert_eq!(apu.read_status() & 0x08, 0x08);
+
+        clock(&mut apu, FRAME_CYCLES);
+        assert_eq!(apu.read_status() & 0x08, 0x00);
+    }
+
+    #[test]
+    fn test_frame_interrupt() {
+        let mut apu = Apu::new();
+        clock(&mut apu, FRAME_CYCLES);
+        assert!(apu.irq_pending());
+
+        // Reading the status acknowledges the interrupt
+        assert_eq!(apu.read_status() & 0x40, 0x40);
+        assert!(!apu.irq_pending());
+
+        // It can be inhibited, and never fires in 5 step mode
+        apu.write_register(0x4017, 0x40);
+        clock(&mut apu, FRAME_CYCLES);
+        assert!(!apu.irq_pending());
+        apu.write_register(0x4017, 0x80);
+        clock(&mut apu, FRAME_CYCLES * 2);
+        assert!(!apu.irq_pending());
+    }
+
+    #[test]
+    fn test_pal_frame_counter() {
+        let mut apu = Apu::new();
+        apu.set_region(Region::Pal);
+
+        // The 4 step sequence is longer on the slower PAL CPU
+        clock(&mut apu, FRAME_CYCLES);
+        assert!(!apu.irq_pending());
+        clock(&mut apu, 33_253 - FRAME_CYCLES);
+        assert!(apu.irq_pending());
+    }
+
+    #[test]
+    fn test_cpu_bus_routes_apu_registers() {
+        let apu = Rc::new(RefCell::new(Apu::new()));
+        let mut bus = CPUBus::load_cartridge(create_nsf(1, 0x60));
+        bus.set_apu(Rc::clone(&apu));
+
+        bus.write_byte(0x4015, 0x04);
+        bus.write_byte(0x400B, 0x08);
+        assert_eq!(bus.read_byte(0x4015), 0x04);
+        assert_eq!(apu.borrow_mut().read_status(), 0x04);
+    }
+
+    /// Helper function to create an NSF tune whose init and play routines are
+    /// the given opcode at $8000.
+    fn create_nsf(song_count: u8, opcode: u8) -> Cartridge {
+        let fields = common::NsfFields { song_count, ..Default::default() };
+        Cartridge::from_bytes(&common::create_nsf(&fields, &[opcode])).unwrap()
+    }
+
+    #[test]
+    fn test_nsf_player_renders_songs() {
+        let mut player = NsfPlayer::new(create_nsf(3, 0x60), 8_000).unwrap();
+        assert_eq!(player.song(), 1);
+
+        let samples = player.render(Duration::from_millis(500));
+        assert_eq!(samples.len(), 4_000);
+        assert!(samples.iter().all(|&sample| sample == 0));
+
+        // Changing song wraps around both ends
+        player.previous_song();
+        assert_eq!(player.song(), 3);
+        player.next_song();
+        assert_eq!(player.song(), 1);
+        assert_eq!(player.song_title(), "Song 1");
+
+        let mut wav = Vec::new();
+        nsf_player::write_wav_to(&mut wav, &samples, player.sample_rate()).unwrap();
+        assert_eq!(wav.len(), 44 + samples.len() * 2);
+        assert_eq!(&wav[0..4], b"RIFF");
+        assert_eq!(&wav[8..16], b"WAVEfmt ");
+        assert_eq!(u32::from_le_bytes([wav[24], wav[25], wav[26], wav[27]]), 8_000);
+    }
+
+    #[test]
+    fn test_nsf_player_keeps_pal_timing() {
+        let fields = common::NsfFields { song_count: 2, region: 1, ..Default::default() };
+        let cartridge = Cartridge::from_bytes(&common::create_nsf(&fields, &[0x60])).unwrap();
+        let mut player = NsfPlayer::new(cartridge, nsf_player::DEFAULT_SAMPLE_RATE).unwrap();
+
+        // Each song gets a fresh APU, which must still run at PAL speed
+        player.next_song();
+        let mut apu = player.apu().borrow_mut();
+        apu.write_register(0x4017, 0x00);
+        clock(&mut apu, FRAME_CYCLES);
+        assert!(!apu.irq_pending());
+        clock(&mut apu, 33_253 - FRAME_CYCLES);
+        assert!(apu.irq_pending());
+    }
+
+    #[test]
+    fn test_nsf_player_requires_a_tune() {
+        let mut rom = b"NES\x1A\x01\x00".to_vec();
+        rom.resize(16 + 0x4000, 0);
+        let cartridge = Cartridge::from_bytes(&rom).unwrap();
+        assert!(NsfPlayer::new(cartridge, nsf_player::DEFAULT_SAMPLE_RATE).is_err());
+    }
+}
@@ -4,9 +4,16 @@ use bard::Cartridge;
 #[cfg(test)]
 mod tests {
     use super::*;
+    use common::{create_disk_side, NsfFields};
     use std::fs::File;
     use std::io::{Write, Seek, SeekFrom};
     use bard::Cartridge;
+    use bard::cartridge::patch::{self, PatchFormat};
+    use bard::cartridge::unif;
+    use bard::cartridge::fds;
+    use bard::cartridge::nsf;
+    use bard::cartridge::{CartridgeError, CartridgeHeader, ConsoleType, HeaderFormat, Mirroring, RomDatabase, RomHashes, SaveFile, TimingRegion, VsHardwareType, VsPpuType, VsSystemType};
+    use std::path::Path;
     use tempfile::tempdir;
 
     /// Helper function to create a temporary NES ROM file.
@@ -95,5 +102,791 @@ mod tests {
         assert_eq!(chr_rom[0], 0xBB);
         assert_eq!(chr_rom[8_191], 0xBB);
     }
-}
 
+    /// Helper function to create a header buffer with the "NES\x1A" signature.
+    fn create_header(bytes: &[(usize, u8)]) -> [u8; 16] {
+        let mut header = [0x00; 16];
+        header[0..4].copy_from_slice(b"NES\x1A");
+        for &(index, value) in bytes {
+            header[index] = value;
+        }
+        header
+    }
+
+    #[test]
+    fn test_ines_header_flags() {
+        // Mapper 0x47 split across bytes 6/7, vertical mirroring, battery, trainer
+        let header = CartridgeHeader::parse(&create_header(&[(4, 2), (6, 0x77), (7, 0x40)])).unwrap();
+
+        assert_eq!(header.format, HeaderFormat::INes);
+        assert_eq!(header.mapper_id, 0x47);
+        assert_eq!(header.mirroring, Mirroring::Vertical);
+        assert!(header.has_battery);
+        assert!(header.has_trainer);
+        assert_eq!(header.prg_ram_size, 0);
+        assert_eq!(header.prg_nvram_size, 8_192);
+        assert_eq!(header.chr_ram_size, 8_192); // No CHR-ROM
+        assert_eq!(header.console_type, ConsoleType::Nes);
+        assert_eq!(header.timing, TimingRegion::Ntsc);
+    }
+
+    #[test]
+    fn test_ines_four_screen_overrides_mirroring() {
+        let header = CartridgeHeader::parse(&create_header(&[(6, 0x09)])).unwrap();
+        assert_eq!(header.mirroring, Mirroring::FourScreen);
+    }
+
+    #[test]
+    fn test_nes_20_header() {
+        let header = CartridgeHeader::parse(&create_header(&[
+            (4, 0x02),
+            (5, 0x01),
+            (6, 0x40),
+            (7, 0x19), // Mapper D7..D4 = 1, NES 2.0, Vs. System
+            (8, 0x31), // Submapper 3, mapper D11..D8 = 1
+            (9, 0x10), // CHR-ROM MSB = 1
+            (10, 0x70), // 8KB PRG-NVRAM
+            (11, 0x07), // 8KB CHR-RAM
+            (12, 0x03), // Dendy
+            (15, 0x01), // Standard controllers
+        ])).unwrap();
+
+        assert_eq!(header.format, HeaderFormat::Nes20);
+        assert_eq!(header.mapper_id, 0x114);
+        assert_eq!(header.submapper_id, 3);
+        assert_eq!(header.prg_rom_bytes, 32_768);
+        assert_eq!(header.chr_rom_bytes, 0x101 * 8_192);
+        assert_eq!(header.prg_ram_size, 0);
+        assert_eq!(header.prg_nvram_size, 8_192);
+        assert_eq!(header.chr_ram_size, 8_192);
+        assert_eq!(header.chr_nvram_size, 0);
+        assert_eq!(header.console_type, ConsoleType::VsSystem);
+        assert_eq!(header.timing, TimingRegion::Dendy);
+        assert_eq!(header.expansion_device, 1);
+    }
+
+    #[test]
+    fn test_nes_20_exponent_multiplier_rom_size() {
+        // $F MSB: 2^3 * (1 * 2 + 1) = 24 bytes
+        let header = CartridgeHeader::parse(&create_header(&[(4, 0b0000_1101), (7, 0x08), (9, 0x0F)])).unwrap();
+        assert_eq!(header.prg_rom_bytes, 24);
+    }
+
+    #[test]
+    fn test_nes_20_rejects_impossible_rom_sizes() {
+        // 2^63 and 2^40 bytes of PRG-ROM
+        for size in [0xFC, 0xA0] {
+            assert!(CartridgeHeader::parse(&create_header(&[(4, size), (7, 0x08), (9, 0x0F)])).is_none());
+        }
+
+        let mut rom = create_header(&[(4, 0xA0), (7, 0x08), (9, 0x0F)]).to_vec();
+        rom.resize(16 + 0x4000, 0);
+        assert!(matches!(Cartridge::from_bytes(&rom), Err(CartridgeError::InvalidHeader)));
+    }
+
+    #[test]
+    fn test_diskdude_header_ignores_bytes_7_to_15() {
+        let mut buffer = create_header(&[(4, 1), (6, 0x11)]);
+        buffer[7..16].copy_from_slice(b"DiskDude!");
+
+        let header = CartridgeHeader::parse(&buffer).unwrap();
+        assert_eq!(header.format, HeaderFormat::Archaic);
+        assert_eq!(header.mapper_id, 1); // 'D' = $44 must not leak into the mapper number
+        assert_eq!(header.console_type, ConsoleType::Nes);
+        assert_eq!(header.mirroring, Mirroring::Vertical);
+    }
+
+    /// Helper function to write a ROM file from a header and the data that follows it.
+    fn create_rom_file(header: [u8; 16], body: &[u8]) -> (tempfile::TempDir, String) {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("test_rom.nes");
+        let mut file = File::create(&file_path).unwrap();
+        file.write_all(&header).unwrap();
+        file.write_all(body).unwrap();
+
+        (dir, file_path.to_string_lossy().to_string())
+    }
+
+    #[test]
+    fn test_trainer_is_read_before_prg_rom() {
+        let mut body = vec![0x77; 512];
+        body.extend(vec![0xAA; 16_384]);
+        let (_dir, file_path) = create_rom_file(create_header(&[(4, 1), (6, 0x04)]), &body);
+
+        let cartridge = Cartridge::load_from_file(&file_path).unwrap();
+        assert_eq!(cartridge.trainer, vec![0x77; 512]);
+        assert_eq!(cartridge.prg_rom, vec![0xAA; 16_384]);
+        assert!(cartridge.chr_rom.is_empty());
+    }
+
+    #[test]
+    fn test_nes_20_misc_rom_is_preserved() {
+        let mut body = vec![0xAA; 16_384];
+        body.extend(vec![0xBB; 8_192]);
+        body.extend([0x01, 0x02, 0x03]);
+        let (_dir, file_path) = create_rom_file(create_header(&[(4, 1), (5, 1), (7, 0x08), (14, 0x01)]), &body);
+
+        let cartridge = Cartridge::load_from_file(&file_path).unwrap();
+        assert_eq!(cartridge.header.misc_rom_count, 1);
+        assert_eq!(cartridge.chr_rom.len(), 8_192);
+        assert_eq!(cartridge.misc_rom, vec![0x01, 0x02, 0x03]);
+    }
+
+    #[test]
+    fn test_truncated_rom_reports_missing_section() {
+        let mut body = vec![0xAA; 16_384];
+        body.extend(vec![0xBB; 100]);
+        let (_dir, file_path) = create_rom_file(create_header(&[(4, 1), (5, 1)]), &body);
+
+        let error = Cartridge::load_from_file(&file_path).err().expect("Truncated ROM should not load");
+        assert!(
+            matches!(error, CartridgeError::Truncated { section: "CHR-ROM", expected: 8_192, found: 100 }),
+            "{}", error
+        );
+    }
+
+    #[test]
+    fn test_load_from_bytes() {
+        let mut rom = create_header(&[(4, 1), (5, 1)]).to_vec();
+        rom.extend(vec![0xAA; 16_384]);
+        rom.extend(vec![0xBB; 8_192]);
+
+        let cartridge = Cartridge::from_bytes(&rom).unwrap();
+        assert_eq!(cartridge.prg_rom, vec![0xAA; 16_384]);
+        assert_eq!(cartridge.chr_rom, vec![0xBB; 8_192]);
+    }
+
+    #[test]
+    fn test_load_from_reader() {
+        let mut rom = create_header(&[(4, 1)]).to_vec();
+        rom.extend(vec![0xAA; 16_384]);
+
+        let cartridge = Cartridge::from_reader(std::io::Cursor::new(rom)).unwrap();
+        assert_eq!(cartridge.prg_rom.len(), 16_384);
+        assert!(cartridge.chr_rom.is_empty());
+    }
+
+    #[test]
+    fn test_load_errors() {
+        assert!(matches!(Cartridge::from_bytes(&[0x00; 16]), Err(CartridgeError::InvalidHeader)));
+        assert!(matches!(
+            Cartridge::from_bytes(b"NES\x1A"),
+            Err(CartridgeError::Truncated { section: "header", expected: 16, found: 4 })
+        ));
+        assert!(matches!(Cartridge::load_from_file("does/not/exist.nes"), Err(CartridgeError::Io(_))));
+    }
+
+    #[test]
+    fn test_oversized_exponent_header_is_truncated() {
+        // 2^25 bytes of PRG-ROM is a size a header can describe, but the file
+        // only holds 16 KB of it
+        let mut rom = create_header(&[(4, 0b0110_0100), (7, 0x08), (9, 0x0F)]).to_vec();
+        rom.resize(16 + 0x4000, 0);
+        assert!(matches!(
+            Cartridge::from_bytes(&rom),
+            Err(CartridgeError::Truncated { section: "PRG-ROM", expected: 33_554_432, found: 16_384 })
+        ));
+    }
+
+    #[test]
+    fn test_save_file_path() {
+        let save_file = SaveFile::for_rom(Path::new("roms/The Legend of Zelda.nes"), None);
+        assert_eq!(save_file.path(), Path::new("roms/The Legend of Zelda.sav"));
+
+        let save_file = SaveFile::for_rom(Path::new("roms/zelda.v1.nes"), Some(Path::new("saves")));
+        assert_eq!(save_file.path(), Path::new("saves/zelda.v1.sav"));
+    }
+
+    #[test]
+    fn test_save_file_round_trip() {
+        let dir = tempdir().unwrap();
+        let rom_path = dir.path().join("game.nes");
+        let saves_directory = dir.path().join("saves");
+
+        let mut save_file = SaveFile::for_rom(&rom_path, Some(&saves_directory));
+        assert_eq!(save_file.load().unwrap(), None);
+
+        let ram = vec![0x5A; 8_192];
+        assert!(save_file.flush(&ram).unwrap());
+        assert!(!save_file.flush(&ram).unwrap(), "Unchanged RAM should not be rewritten");
+
+        // Only the finished save is left behind
+        let files = std::fs::read_dir(&saves_directory).unwrap().count();
+        assert_eq!(files, 1);
+
+        let mut save_file = SaveFile::for_rom(&rom_path, Some(&saves_directory));
+        assert_eq!(save_file.load().unwrap(), Some(ram));
+    }
+
+    /// Helper function to create a minimal NROM image in memory.
+    fn create_rom_bytes(fill: u8) -> Vec<u8> {
+        let mut rom = create_header(&[(4, 1)]).to_vec();
+        rom.extend(vec![fill; 16_384]);
+        rom
+    }
+
+    /// Helper function to create a zip archive holding the given files.
+    fn create_zip(files: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for (name, data) in files {
+            writer.start_file(*name, zip::write::SimpleFileOptions::default()).unwrap();
+            writer.write_all(data).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_load_single_rom_from_zip() {
+        let zip = create_zip(&[("readme.txt", b"Hello".to_vec()), ("Game (U).NES", create_rom_bytes(0xAA))]);
+
+        let cartridge = Cartridge::from_bytes(&zip).unwrap();
+        assert_eq!(cartridge.prg_rom, vec![0xAA; 16_384]);
+    }
+
+    #[test]
+    fn test_zip_with_several_roms_must_be_chosen() {
+        let zip = create_zip(&[("a.nes", create_rom_bytes(0xAA)), ("b.nes", create_rom_bytes(0xBB))]);
+
+        match Cartridge::from_bytes(&zip) {
+            Err(CartridgeError::MultipleRomsInArchive(entries)) => assert_eq!(entries, vec!["a.nes", "b.nes"]),
+            other => panic!("Expected a list of ROMs, got {:?}", other.err()),
+        }
+
+        let (_dir, file_path) = create_rom_file(create_header(&[]), &[]);
+        std::fs::write(&file_path, &zip).unwrap();
+        let cartridge = Cartridge::load_archive_entry(&file_path, "b.nes").unwrap();
+        assert_eq!(cartridge.prg_rom[0], 0xBB);
+    }
+
+    #[test]
+    fn test_zip_without_rom() {
+        let zip = create_zip(&[("readme.txt", b"Hello".to_vec())]);
+        assert!(matches!(Cartridge::from_bytes(&zip), Err(CartridgeError::NoRomInArchive)));
+    }
+
+    #[test]
+    fn test_corrupt_zip() {
+        let mut zip = create_zip(&[("game.nes", create_rom_bytes(0xAA))]);
+        let length = zip.len();
+        zip.truncate(length - 30); // Cut off the central directory
+
+        assert!(matches!(Cartridge::from_bytes(&zip), Err(CartridgeError::CorruptArchive(_))));
+    }
+
+    #[test]
+    fn test_load_rom_from_gzip() {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&create_rom_bytes(0xAA)).unwrap();
+        let gzip = encoder.finish().unwrap();
+
+        let (_dir, file_path) = create_rom_file(create_header(&[]), &[]);
+        std::fs::write(&file_path, &gzip).unwrap();
+        let cartridge = Cartridge::load_from_file(&file_path).unwrap();
+        assert_eq!(cartridge.prg_rom, vec![0xAA; 16_384]);
+
+        let corrupt = [&gzip[..10], &[0xFF; 20][..]].concat();
+        assert!(matches!(Cartridge::from_bytes(&corrupt), Err(CartridgeError::CorruptArchive(_))));
+    }
+
+    #[test]
+    fn test_gzip_bomb_is_refused() {
+        // 129 MB of zeros compresses to a few hundred KB
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        let chunk = vec![0x00; 0x0010_0000];
+        for _ in 0..129 {
+            encoder.write_all(&chunk).unwrap();
+        }
+        let gzip = encoder.finish().unwrap();
+
+        assert!(matches!(Cartridge::from_bytes(&gzip), Err(CartridgeError::CorruptArchive(_))));
+    }
+
+    /// Helper function to write a UPS/BPS variable length number.
+    fn write_number(patch: &mut Vec<u8>, mut data: usize) {
+        loop {
+            let value = (data & 0x7F) as u8;
+            data >>= 7;
+            if data == 0 {
+                patch.push(0x80 | value);
+                return;
+            }
+            patch.push(value);
+            data -= 1;
+        }
+    }
+
+    /// Helper function to create a ROM with a mapper 2 header and patterned PRG.
+    fn create_modified_rom(original: &[u8]) -> Vec<u8> {
+        let mut modified = original.to_vec();
+        modified[6] = 0x20; // Mapper 2
+        modified[16 + 0x1234] = 0x42;
+        modified[16 + 0x3FFF] = 0x99;
+        modified
+    }
+
+    #[test]
+    fn test_ips_patch_round_trip() {
+        let original = create_rom_bytes(0xAA);
+        let modified = create_modified_rom(&original);
+
+        let ips = patch::create_ips(&original, &modified).unwrap();
+        assert!(ips.starts_with(b"PATCH") && ips.ends_with(b"EOF"));
+        assert_eq!(patch::apply(&original, &ips).unwrap(), modified);
+
+        // The header is parsed after the patch is applied
+        let cartridge = Cartridge::from_bytes_with_patch(&original, &ips).unwrap();
+        assert_eq!(cartridge.header.mapper_id, 2);
+        assert_eq!(cartridge.prg_rom[0x1234], 0x42);
+    }
+
+    #[test]
+    fn test_ips_rle_and_truncation() {
+        let rom = vec![0x00; 32];
+
+        let mut ips = b"PATCH".to_vec();
+        ips.extend_from_slice(&[0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x03, 0xEE]); // RLE: 3 x 0xEE at $04
+        ips.extend_from_slice(&[0x00, 0x00, 0x10, 0x00, 0x02, 0x12, 0x34]); // 0x12 0x34 at $10
+        ips.extend_from_slice(b"EOF");
+        ips.extend_from_slice(&[0x00, 0x00, 0x14]); // Truncate to 20 bytes
+
+        let patched = patch::apply(&rom, &ips).unwrap();
+        assert_eq!(patched.len(), 20);
+        assert_eq!(&patched[4..7], &[0xEE; 3]);
+        assert_eq!(&patched[0x10..0x12], &[0x12, 0x34]);
+
+        // Shrinking a file writes the truncation extension
+        let created = patch::create_ips(&rom, &patched).unwrap();
+        assert_eq!(patch::apply(&rom, &created).unwrap(), patched);
+    }
+
+    #[test]
+    fn test_ups_patch() {
+        let original = create_rom_bytes(0xAA);
+        let modified = create_modified_rom(&original);
+
+        let mut ups = b"UPS1".to_vec();
+        write_number(&mut ups, original.len());
+        write_number(&mut ups, modified.len());
+        let mut offset = 0;
+        for (index, (a, b)) in original.iter().zip(&modified).enumerate() {
+            if a != b {
+                write_number(&mut ups, index - offset);
+                ups.extend_from_slice(&[a ^ b, 0x00]);
+                offset = index + 2; // The terminator also covers a byte
+            }
+        }
+        ups.extend_from_slice(&crc32fast::hash(&original).to_le_bytes());
+        ups.extend_from_slice(&crc32fast::hash(&modified).to_le_bytes());
+        let patch_crc = crc32fast::hash(&ups);
+        ups.extend_from_slice(&patch_crc.to_le_bytes());
+
+        let cartridge = Cartridge::from_bytes_with_patch(&original, &ups).unwrap();
+        assert_eq!(cartridge.header.mapper_id, 2);
+        assert_eq!(cartridge.prg_rom[0x3FFF], 0x99);
+
+        // A UPS patch only applies to the ROM it was made for
+        let other = create_rom_bytes(0xBB);
+        assert!(matches!(
+            patch::apply(&other, &ups),
+            Err(CartridgeError::PatchChecksumMismatch { checksum: "source", .. })
+        ));
+    }
+
+    #[test]
+    fn test_bps_patch_round_trip() {
+        let original = create_rom_bytes(0xAA);
+        let mut modified = create_modified_rom(&original);
+        modified.extend_from_slice(&[0x77; 8]); // Grow the file
+
+        let bps = patch::create(PatchFormat::Bps, &original, &modified).unwrap();
+        assert_eq!(PatchFormat::detect(&bps), Some(PatchFormat::Bps));
+        assert_eq!(patch::apply(&original, &bps).unwrap(), modified);
+
+        let other = create_rom_bytes(0xBB);
+        assert!(matches!(
+            patch::apply(&other, &bps),
+            Err(CartridgeError::PatchChecksumMismatch { checksum: "source", .. })
+        ));
+
+        let mut corrupt = bps.clone();
+        corrupt[8] ^= 0xFF;
+        assert!(matches!(
+            patch::apply(&original, &corrupt),
+            Err(CartridgeError::PatchChecksumMismatch { checksum: "patch", .. })
+        ));
+    }
+
+    #[test]
+    fn test_patch_target_size_is_limited() {
+        let original = create_rom_bytes(0xAA);
+
+        // A 1 TiB target is refused before any memory is set aside for it
+        for magic in [&b"UPS1"[..], &b"BPS1"[..]] {
+            let mut patch = magic.to_vec();
+            write_number(&mut patch, original.len());
+            write_number(&mut patch, 1 << 40);
+            if magic == b"BPS1" {
+                write_number(&mut patch, 0); // No metadata
+            }
+            patch.extend_from_slice(&crc32fast::hash(&original).to_le_bytes());
+            patch.extend_from_slice(&0u32.to_le_bytes());
+            let patch_crc = crc32fast::hash(&patch);
+            patch.extend_from_slice(&patch_crc.to_le_bytes());
+
+            assert!(matches!(patch::apply(&original, &patch), Err(CartridgeError::InvalidPatch(_))));
+        }
+    }
+
+    #[test]
+    fn test_patch_beside_rom_is_applied() {
+        let original = create_rom_bytes(0xAA);
+        let modified = create_modified_rom(&original);
+
+        let (_dir, file_path) = create_rom_file(create_header(&[]), &[]);
+        std::fs::write(&file_path, &original).unwrap();
+        assert_eq!(patch::find_patch(Path::new(&file_path)), None);
+
+        let patch_path = Path::new(&file_path).with_extension("bps");
+        std::fs::write(&patch_path, patch::create_bps(&original, &modified)).unwrap();
+        assert_eq!(patch::find_patch(Path::new(&file_path)), Some(patch_path.clone()));
+
+        let cartridge = Cartridge::load_from_file(&file_path).unwrap();
+        assert_eq!(cartridge.header.mapper_id, 2);
+
+        // Loading explicitly without a patch leaves the ROM untouched
+        let cartridge = Cartridge::load(&file_path, None, None).unwrap();
+        assert_eq!(cartridge.header.mapper_id, 0);
+    }
+
+    #[test]
+    fn test_rom_hashes_ignore_header() {
+        let cartridge = Cartridge::from_bytes(&create_rom_bytes(0xAA)).unwrap();
+        let mut retagged = create_rom_bytes(0xAA);
+        retagged[6] = 0x41; // Different mapper and mirroring, same ROM data
+        let retagged = Cartridge::from_bytes(&retagged).unwrap();
+
+        let hashes = RomHashes::of(&cartridge);
+        assert_eq!(hashes, RomHashes::of(&retagged));
+        assert_eq!(hashes.crc32, crc32fast::hash(&vec![0xAA; 16_384]));
+        assert_eq!(hashes.sha1_hex().len(), 40);
+    }
+
+    #[test]
+    fn test_nes20db_corrects_header() {
+        let mut cartridge = Cartridge::from_bytes(&create_rom_bytes(0xAA)).unwrap();
+        let hashes = RomHashes::of(&cartridge);
+
+        let xml = format!(
+            r#"<nes20db>
+                <game>
+                    <!-- Publisher\Some Game (Europe).nes -->
+                    <rom size="16384" crc32="00000000" sha1="{}"/>
+                    <pcb mapper="4" submapper="1" mirroring="V" battery="1"/>
+                    <prgnvram size="8192"/>
+                    <chrram size="8192"/>
+                    <console type="0" region="1"/>
+                </game>
+            </nes20db>"#,
+            hashes.sha1_hex()
+        );
+        let database = RomDatabase::parse(&xml).unwrap();
+        assert_eq!(database.len(), 1);
+
+        let entry = database.lookup(&hashes).unwrap();
+        assert_eq!(entry.title, "Some Game (Europe)");
+        assert_eq!(entry.region.as_deref(), Some("Pal"));
+
+        let corrections = entry.overrides.apply(&mut cartridge.header);
+        let fields: Vec<&str> = corrections.iter().map(|correction| correction.field).collect();
+        assert_eq!(
+            fields,
+            vec!["mapper", "submapper", "mirroring", "battery", "PRG-RAM size", "PRG-NVRAM size", "timing"]
+        );
+        assert_eq!(corrections[0].to_string(), "mapper: 0 -> 4");
+
+        assert_eq!(cartridge.header.mapper_id, 4);
+        assert_eq!(cartridge.header.mirroring, Mirroring::Vertical);
+        assert!(cartridge.header.has_battery);
+        assert_eq!(cartridge.header.prg_ram_size, 0);
+        assert_eq!(cartridge.header.prg_nvram_size, 8192);
+        assert_eq!(cartridge.header.timing, TimingRegion::Pal);
+
+        // A corrected header needs no further corrections
+        assert!(entry.overrides.apply(&mut cartridge.header).is_empty());
+    }
+
+    #[test]
+    fn test_no_intro_lookup_by_crc32() {
+        let cartridge = Cartridge::from_bytes(&create_rom_bytes(0xAA)).unwrap();
+        let hashes = RomHashes::of(&cartridge);
+
+        let xml = format!(
+            r#"<?xml version="1.0"?>
+            <datafile>
+                <header><name>Nintendo - Nintendo Entertainment System (Headerless)</name></header>
+                <game name="Other Game (Japan)"><rom name="Other Game (Japan).nes" size="16" crc="DEADBEEF"/></game>
+                <game name="Some Game (USA) (Rev 1)"><rom name="Some Game (USA) (Rev 1).nes" size="16384" crc="{:08x}"/></game>
+            </datafile>"#,
+            hashes.crc32
+        );
+        let database = RomDatabase::parse(&xml).unwrap();
+
+        let entry = database.lookup(&hashes).unwrap();
+        assert_eq!(entry.title, "Some Game (USA) (Rev 1)");
+        assert_eq!(entry.region.as_deref(), Some("USA"));
+        assert_eq!(entry.board, None);
+        assert!(entry.overrides.apply(&mut cartridge.header.clone()).is_empty());
+
+        let other = Cartridge::from_bytes(&create_rom_bytes(0xBB)).unwrap();
+        assert!(database.lookup(&RomHashes::of(&other)).is_none());
+    }
+
+    #[test]
+    fn test_no_rom_database_is_bundled() {
+        assert!(RomDatabase::open(None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_invalid_rom_database() {
+        assert!(matches!(RomDatabase::parse("<games/>"), Err(CartridgeError::InvalidDatabase(_))));
+        assert!(matches!(RomDatabase::parse("<nes20db>"), Err(CartridgeError::InvalidDatabase(_))));
+    }
+
+    /// Helper function to create a UNIF image from a list of chunks.
+    fn create_unif(chunks: &[(&[u8; 4], Vec<u8>)]) -> Vec<u8> {
+        let mut unif = b"UNIF".to_vec();
+        unif.extend_from_slice(&7u32.to_le_bytes()); // Revision
+        unif.extend_from_slice(&[0; 24]);
+        for (id, data) in chunks {
+            unif.extend_from_slice(*id);
+            unif.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            unif.extend_from_slice(data);
+        }
+        unif
+    }
+
+    #[test]
+    fn test_load_unif() {
+        let unif = create_unif(&[
+            (b"MAPR", b"NES-NROM-256\0".to_vec()),
+            (b"NAME", b"Test Game\0".to_vec()),
+            (b"PRG1", vec![0xBB; 16_384]), // Chunks are ordered by number, not position
+            (b"PRG0", vec![0xAA; 16_384]),
+            (b"CHR0", vec![0xCC; 8_192]),
+            (b"MIRR", vec![1]),
+            (b"BATR", vec![1]),
+            (b"TVCI", vec![1]),
+        ]);
+
+        let cartridge = Cartridge::from_bytes(&unif).unwrap();
+        let header = &cartridge.header;
+        assert_eq!(header.format, HeaderFormat::Unif);
+        assert_eq!(header.board.as_deref(), Some("NES-NROM-256"));
+        assert_eq!(header.mapper_id, 0);
+        assert_eq!(header.prg_rom_size, 2);
+        assert_eq!(header.chr_rom_size, 1);
+        assert_eq!(header.mirroring, Mirroring::Vertical);
+        assert!(header.has_battery);
+        assert_eq!(header.prg_nvram_size, 8192);
+        assert_eq!(header.timing, TimingRegion::Pal);
+
+        assert_eq!(cartridge.prg_rom[0], 0xAA);
+        assert_eq!(cartridge.prg_rom[16_384], 0xBB);
+        assert_eq!(cartridge.chr_rom, vec![0xCC; 8_192]);
+    }
+
+    #[test]
+    fn test_unif_board_names() {
+        assert_eq!(unif::board_mapper("NES-NROM-128"), Some((0, 0)));
+        assert_eq!(unif::board_mapper("HVC-EKROM"), Some((5, 0)));
+        assert_eq!(unif::board_mapper("ELROM"), Some((5, 0)));
+        assert_eq!(unif::board_mapper("UNL-SOMETHING"), None);
+
+        let unif = create_unif(&[(b"MAPR", b"UNL-SOMETHING\0".to_vec()), (b"PRG0", vec![0; 16_384])]);
+        match Cartridge::from_bytes(&unif) {
+            Err(CartridgeError::UnsupportedBoard(board)) => assert_eq!(board, "UNL-SOMETHING"),
+            other => panic!("Expected an unsupported board, got {:?}", other.err()),
+        }
+
+        // CHR-RAM is assumed when there is no CHR chunk
+        let unif = create_unif(&[(b"MAPR", b"NES-EKROM\0".to_vec()), (b"PRG0", vec![0; 32_768])]);
+        let cartridge = Cartridge::from_bytes(&unif).unwrap();
+        assert_eq!(cartridge.header.mapper_id, 5);
+        assert_eq!(cartridge.header.chr_ram_size, 8192);
+    }
+
+    #[test]
+    fn test_truncated_unif() {
+        let mut unif = create_unif(&[(b"MAPR", b"NES-NROM-128\0".to_vec()), (b"PRG0", vec![0; 16_384])]);
+        unif.truncate(unif.len() - 100);
+
+        assert!(matches!(
+            Cartridge::from_bytes(&unif),
+            Err(CartridgeError::Truncated { section: "UNIF chunk", expected: 16_384, found: 16_284 })
+        ));
+
+        let unif = create_unif(&[(b"PRG0", vec![0; 16_384])]);
+        assert!(matches!(Cartridge::from_bytes(&unif), Err(CartridgeError::InvalidHeader)));
+    }
+
+    #[test]
+    fn test_load_fds_image() {
+        // fwNES header followed by two sides
+        let mut image = b"FDS\x1A\x02".to_vec();
+        image.resize(16, 0);
+        image.extend(create_disk_side(&[]));
+        image.extend(create_disk_side(&[]));
+
+        let cartridge = Cartridge::from_bytes(&image).unwrap();
+        assert_eq!(cartridge.header.format, HeaderFormat::Fds);
+        assert_eq!(cartridge.header.mapper_id, fds::FDS_MAPPER);
+        assert_eq!(cartridge.header.prg_ram_size, 0x8000);
+        assert_eq!(cartridge.header.chr_ram_size, 0x2000);
+        assert_eq!(cartridge.disk.as_ref().unwrap().sides.len(), 2);
+        assert!(cartridge.prg_rom.is_empty());
+
+        // Headerless images hold the sides directly
+        let cartridge = Cartridge::from_bytes(&create_disk_side(&[])).unwrap();
+        assert_eq!(cartridge.disk.unwrap().sides.len(), 1);
+
+        let mut not_a_disk = create_disk_side(&[]);
+        not_a_disk.extend(vec![0; 65_500]);
+        assert!(matches!(Cartridge::from_bytes(&not_a_disk), Err(CartridgeError::InvalidHeader)));
+        assert!(matches!(Cartridge::from_bytes(&not_a_disk[..1000]), Err(CartridgeError::Truncated { section: "disk side", .. })));
+    }
+
+    #[test]
+    fn test_fds_bios() {
+        let dir = tempdir().unwrap();
+        let image_path = dir.path().join("game.fds");
+        std::fs::write(&image_path, create_disk_side(&[])).unwrap();
+        let image_path = image_path.to_string_lossy().to_string();
+
+        let mut cartridge = Cartridge::load_from_file(&image_path).unwrap();
+        assert!(matches!(cartridge.load_bios(&image_path, None), Err(CartridgeError::MissingBios)));
+
+        std::fs::write(dir.path().join("disksys.rom"), vec![0xEE; 0x2000]).unwrap();
+        cartridge.load_bios(&image_path, None).unwrap();
+        assert_eq!(cartridge.prg_rom, vec![0xEE; 0x2000]);
+
+        let short_bios = dir.path().join("short.rom");
+        std::fs::write(&short_bios, vec![0xEE; 0x100]).unwrap();
+        assert!(matches!(
+            cartridge.load_bios(&image_path, Some(&short_bios)),
+            Err(CartridgeError::Truncated { section: "BIOS", .. })
+        ));
+
+        // Cartridges need no BIOS
+        let mut cartridge = Cartridge::from_bytes(&create_rom_bytes(0xAA)).unwrap();
+        cartridge.load_bios(&image_path, Some(&short_bios)).unwrap();
+        assert_eq!(cartridge.prg_rom, vec![0xAA; 16_384]);
+    }
+
+    /// Helper function to create a 3 song PAL tune starting with the second.
+    fn create_nsf(load_address: u16, banks: [u8; 8], expansion_chips: u8, data: &[u8]) -> Vec<u8> {
+        let fields = NsfFields {
+            song_count: 3,
+            starting_song: 2,
+            load_address,
+            play_address: 0x8003,
+            banks,
+            region: 0x01,
+            expansion_chips,
+            ..NsfFields::default()
+        };
+        common::create_nsf(&fields, data)
+    }
+
+    #[test]
+    fn test_load_nsf() {
+        let cartridge = Cartridge::from_bytes(&create_nsf(0x8400, [0; 8], 0x00, &[0x60; 0x100])).unwrap();
+        assert_eq!(cartridge.header.format, HeaderFormat::Nsf);
+        assert_eq!(cartridge.header.mapper_id, nsf::NSF_MAPPER);
+        assert_eq!(cartridge.header.timing, TimingRegion::Pal);
+
+        let header = cartridge.nsf.unwrap();
+        assert_eq!((header.song_count, header.starting_song), (3, 2));
+        assert_eq!((header.load_address, header.init_address, header.play_address), (0x8400, 0x8000, 0x8003));
+        assert_eq!((header.title.as_str(), header.artist.as_str(), header.copyright.as_str()), ("Title", "Artist", "Copyright"));
+        assert_eq!((header.ntsc_play_speed, header.pal_play_speed), (16_639, 20_000));
+        assert!(!header.is_bank_switched());
+
+        // Unbanked tunes sit at their load address within the image
+        assert_eq!(cartridge.prg_rom.len(), 0x1000);
+        assert_eq!(cartridge.prg_rom[0x3FF], 0x00);
+        assert_eq!(cartridge.prg_rom[0x400], 0x60);
+
+        // Bank switched tunes keep the load address' offset within its bank
+        let cartridge = Cartridge::from_bytes(&create_nsf(0x8010, [0, 1, 0, 0, 0, 0, 0, 1], 0x05, &[0x60; 0x1000])).unwrap();
+        let header = cartridge.nsf.unwrap();
+        assert!(header.is_bank_switched());
+        assert_eq!(header.expansion_chips, nsf::ExpansionChips::VRC6 | nsf::ExpansionChips::FDS);
+        assert_eq!(cartridge.prg_rom.len(), 0x2000);
+        assert_eq!(cartridge.prg_rom[0x10], 0x60);
+
+        // Unbanked tunes cannot load below $8000
+        assert!(matches!(Cartridge::from_bytes(&create_nsf(0x7000, [0; 8], 0x00, &[0x60])), Err(CartridgeError::InvalidHeader)));
+        assert!(matches!(Cartridge::from_bytes(&create_nsf(0x8000, [0; 8], 0x00, &[])[..0x40]), Err(CartridgeError::Truncated { .. })));
+    }
+
+    fn nsfe_chunk(id: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut chunk = (data.len() as u32).to_le_bytes().to_vec();
+        chunk.extend(id);
+        chunk.extend(data);
+        chunk
+    }
+
+    #[test]
+    fn test_load_nsfe() {
+        let mut info = vec![0x00, 0x80, 0x00, 0x80, 0x03, 0x80, 0x00, 0x08];
+        info.extend([2, 1]); // 2 songs, starting with the second (counted from 0)
+
+        let mut file = b"NSFE".to_vec();
+        file.extend(nsfe_chunk(b"INFO", &info));
+        file.extend(nsfe_chunk(b"DATA", &[0x60; 0x20]));
+        file.extend(nsfe_chunk(b"RATE", &10_000u16.to_le_bytes()));
+        file.extend(nsfe_chunk(b"auth", b"Game\0Composer\0Company\0Ripper\0"));
+        file.extend(nsfe_chunk(b"tlbl", b"Title Theme\0Ending\0"));
+        file.extend(nsfe_chunk(b"plst", &[1, 0]));
+        file.extend(nsfe_chunk(b"NEND", &[]));
+
+        let cartridge = Cartridge::from_bytes(&file).unwrap();
+        let header = cartridge.nsf.as_ref().unwrap();
+        assert_eq!((header.song_count, header.starting_song), (2, 2));
+        assert_eq!(header.ntsc_play_speed, 10_000);
+        assert_eq!((header.title.as_str(), header.artist.as_str()), ("Game", "Composer"));
+        assert_eq!(header.track_titles, vec!["Title Theme".to_string(), "Ending".to_string()]);
+        assert_eq!(header.expansion_chips, nsf::ExpansionChips::MMC5);
+        assert_eq!(cartridge.prg_rom[..0x20], [0x60; 0x20]);
+
+        // Capitalised chunks must be understood
+        let mut unknown = file.clone();
+        unknown.truncate(file.len() - 8);
+        unknown.extend(nsfe_chunk(b"VRC7", &[0]));
+        assert!(matches!(Cartridge::from_bytes(&unknown), Err(CartridgeError::InvalidHeader)));
+
+        // The INFO chunk is required
+        let mut no_info = b"NSFE".to_vec();
+        no_info.extend(nsfe_chunk(b"DATA", &[0x60]));
+        assert!(matches!(Cartridge::from_bytes(&no_info), Err(CartridgeError::InvalidHeader)));
+    }
+
+    #[test]
+    fn test_vs_system_header() {
+        // NES 2.0 byte 13: RC2C05-03 on the RBI Baseball board
+        let header = CartridgeHeader::parse(&create_header(&[(7, 0x09), (13, 0x1A)])).unwrap();
+        let vs_system = header.vs_system.unwrap();
+        assert_eq!(vs_system.ppu, VsPpuType::Rc2c05(3));
+        assert_eq!(vs_system.hardware, VsHardwareType::UnisystemRbiBaseball);
+
+        let header = CartridgeHeader::parse(&create_header(&[(7, 0x09), (13, 0x04)])).unwrap();
+        assert_eq!(header.vs_system.unwrap().ppu, VsPpuType::Rp2c04(3));
+
+        // iNES only says it is a Vs. System game
+        let header = CartridgeHeader::parse(&create_header(&[(7, 0x01)])).unwrap();
+        assert_eq!(header.vs_system, Some(VsSystemType::default()));
+
+        let header = CartridgeHeader::parse(&create_header(&[])).unwrap();
+        assert_eq!(header.vs_system, None);
+    }
+}
@@ -1,3 +1,6 @@
+// Each test binary only uses some of these helpers
+#![allow(dead_code)]
+
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -39,3 +42,74 @@ pub fn setup_test_rom(rom_filename: &str) -> PathBuf {
 
     dest_path
 }
+
+/// The header fields of an NSF tune that the tests vary.
+pub struct NsfFields {
+    pub song_count: u8,
+    pub starting_song: u8,
+    pub load_address: u16,
+    pub init_address: u16,
+    pub play_address: u16,
+    /// The initial bank of each 4 KB slot at $8000-$FFFF, all 0 for unbanked tunes.
+    pub banks: [u8; 8],
+    /// Byte $7A: 0 for NTSC, 1 for PAL.
+    pub region: u8,
+    pub expansion_chips: u8,
+}
+
+impl Default for NsfFields {
+    /// One NTSC song loaded, initialised and played at $8000.
+    fn default() -> Self {
+        Self {
+            song_count: 1,
+            starting_song: 1,
+            load_address: 0x8000,
+            init_address: 0x8000,
+            play_address: 0x8000,
+            banks: [0; 8],
+            region: 0,
+            expansion_chips: 0,
+        }
+    }
+}
+
+/// Helper function to create an NSF file: a header with the given fields
+/// followed by the tune's data.
+pub fn create_nsf(fields: &NsfFields, data: &[u8]) -> Vec<u8> {
+    let mut nsf = b"NESM\x1A\x01".to_vec();
+    nsf.extend([fields.song_count, fields.starting_song]);
+    nsf.extend(fields.load_address.to_le_bytes());
+    nsf.extend(fields.init_address.to_le_bytes());
+    nsf.extend(fields.play_address.to_le_bytes());
+    for text in ["Title", "Artist", "Copyright"] {
+        let mut field = text.as_bytes().to_vec();
+        field.resize(32, 0);
+        nsf.extend(field);
+    }
+    nsf.extend(0u16.to_le_bytes()); // NTSC speed, 0 for the default
+    nsf.extend(fields.banks);
+    nsf.extend(20_000u16.to_le_bytes());
+    nsf.extend([fields.region, fields.expansion_chips, 0, 0, 0, 0]);
+    nsf.extend(data);
+    nsf
+}
+
+/// Helper function to create an FDS disk side holding the given files, each
+/// loaded to $6000.
+pub fn create_disk_side(files: &[&[u8]]) -> Vec<u8> {
+    let mut side = b"\x01*NINTENDO-HVC*".to_vec();
+    side.resize(56, 0);
+    side.extend_from_slice(&[0x02, files.len() as u8]);
+    for (number, data) in files.iter().enumerate() {
+        side.extend_from_slice(&[0x03, number as u8, number as u8]);
+        side.extend_from_slice(b"TESTFILE");
+        side.extend_from_slice(&[0x00, 0x60]);
+        side.extend_from_slice(&(data.len() as u16).to_le_bytes());
+        side.push(0x00); // Program file
+        side.push(0x04);
+        side.extend_from_slice(data);
+    }
+    side.resize(65_500, 0);
+    side
+}
+
@@ -0,0 +1,668 @@
+mod common;
+
+use std::rc::Rc;
+
+use bard::memory::Bus;
+use bard::memory::CPUBus;
+use bard::memory::PPUBus;
+use bard::mapper;
+use bard::mapper::PpuFetchSource;
+use bard::ppu::PPU;
+use bard::cartridge::Cartridge;
+use bard::cartridge::CartridgeError;
+use bard::cartridge::CartridgeHeader;
+use bard::cartridge::Mirroring;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Helper function to create a test cartridge for the given mapper where
+    /// every byte of PRG-ROM holds its 8KB bank number and every byte of
+    /// CHR-ROM holds its 1KB bank number.
+    fn create_banked_cartridge(mapper_number: u8, prg_banks: usize, chr_banks: usize) -> Cartridge {
+        let mut buffer = [0x00; 16];
+        buffer[0..4].copy_from_slice(b"NES\x1A");
+        buffer[4] = (prg_banks / 2) as u8;
+        buffer[5] = (chr_banks / 8) as u8;
+        buffer[6] = (mapper_number & 0x0F) << 4;
+        buffer[7] = mapper_number & 0xF0;
+
+        let prg_rom = (0..prg_banks).flat_map(|bank| vec![bank as u8; 0x2000]).collect::<Vec<u8>>();
+        let chr_rom = (0..chr_banks).flat_map(|bank| vec![bank as u8; 0x0400]).collect::<Vec<u8>>();
+
+        Cartridge {
+            header: CartridgeHeader::parse(&buffer).unwrap(),
+            prg_rom,
+            chr_rom,
+            trainer: vec![],
+            misc_rom: vec![],
+            disk: None,
+            nsf: None,
+        }
+    }
+
+    #[test]
+    fn test_unsupported_mapper_is_reported() {
+        let cartridge = create_banked_cartridge(4, 16, 8);
+        assert!(matches!(mapper::try_create(&cartridge), Err(CartridgeError::UnsupportedMapper(4))));
+
+        // create() still runs it as NROM
+        let mapper = mapper::create(&cartridge);
+        assert_eq!(mapper.borrow_mut().cpu_read(0xC000), Some(2));
+    }
+
+    #[test]
+    fn test_vrc4_prg_swap_mode() {
+        let cartridge = create_banked_cartridge(21, 16, 8);
+        let mut bus = CPUBus::load_cartridge(cartridge);
+
+        bus.write_byte(0x8000, 3);
+        bus.write_byte(0xA000, 5);
+        assert_eq!(bus.read_byte(0x8000), 3);
+        assert_eq!(bus.read_byte(0xA000), 5);
+        assert_eq!(bus.read_byte(0xC000), 14); // Second last bank
+        assert_eq!(bus.read_byte(0xE000), 15); // Last bank
+
+        // $9004 is $9002 on VRC4a (A2 drives the chip's A1 input)
+        bus.write_byte(0x9004, 0x02);
+        assert_eq!(bus.read_byte(0x8000), 14);
+        assert_eq!(bus.read_byte(0xC000), 3);
+        assert_eq!(bus.read_byte(0xE000), 15);
+    }
+
+    #[test]
+    fn test_vrc4_chr_banking_with_swapped_address_lines() {
+        let cartridge = create_banked_cartridge(25, 16, 32);
+        let mapper = mapper::create(&cartridge);
+        let mut mapper = mapper.borrow_mut();
+
+        // On mapper 25 A0 drives the chip's A1 input, so $B001/$B003 are the
+        // low/high halves of CHR bank 1.
+        mapper.cpu_write(0xB001, 0x02);
+        mapper.cpu_write(0xB003, 0x01);
+
+        assert_eq!(mapper.ppu_read(0x0000), 0);
+        assert_eq!(mapper.ppu_read(0x0400), 0x12);
+    }
+
+    #[test]
+    fn test_vrc4_mirroring() {
+        let cartridge = create_banked_cartridge(23, 16, 8);
+        let mapper = mapper::create(&cartridge);
+        let mut mapper = mapper.borrow_mut();
+
+        mapper.cpu_write(0x9000, 0x01);
+        assert_eq!(mapper.mirroring(), Mirroring::Horizontal);
+
+        mapper.cpu_write(0x9000, 0x03);
+        assert_eq!(mapper.mirroring(), Mirroring::SingleScreenB);
+    }
+
+    #[test]
+    fn test_vrc2a_ignores_low_chr_bit() {
+        let cartridge = create_banked_cartridge(22, 16, 32);
+        let mapper = mapper::create(&cartridge);
+        let mut mapper = mapper.borrow_mut();
+
+        mapper.cpu_write(0xB000, 0x07);
+        assert_eq!(mapper.ppu_read(0x0000), 3);
+    }
+
+    #[test]
+    fn test_vrc4_irq_cycle_mode() {
+        let cartridge = create_banked_cartridge(21, 16, 8);
+        let mut bus = CPUBus::load_cartridge(cartridge);
+
+        // VRC4a: $F000 latch low, $F002 latch high, $F004 control, $F006 ack
+        bus.write_byte(0xF000, 0x0D);
+        bus.write_byte(0xF002, 0x0F);
+        bus.write_byte(0xF004, 0x06); // Enable, cycle mode
+
+        bus.tick(2);
+        assert!(!bus.irq_pending());
+
+        bus.tick(1);
+        assert!(bus.irq_pending());
+
+        bus.write_byte(0xF006, 0x00);
+        assert!(!bus.irq_pending());
+    }
+
+    #[test]
+    fn test_vrc6_irq_scanline_mode() {
+        let cartridge = create_banked_cartridge(24, 16, 8);
+        let mut bus = CPUBus::load_cartridge(cartridge);
+
+        bus.write_byte(0xF000, 0xFF);
+        bus.write_byte(0xF001, 0x02); // Enable, scanline mode
+
+        // The prescaler reaches zero after 341 / 3 CPU cycles
+        bus.tick(113);
+        assert!(!bus.irq_pending());
+
+        bus.tick(1);
+        assert!(bus.irq_pending());
+    }
+
+    #[test]
+    fn test_vrc6b_swaps_register_lines() {
+        let cartridge = create_banked_cartridge(26, 16, 32);
+        let mapper = mapper::create(&cartridge);
+        let mut mapper = mapper.borrow_mut();
+
+        mapper.cpu_write(0x8000, 0x02); // 16KB bank 2 = 8KB banks 4 and 5
+        mapper.cpu_write(0xD002, 0x05); // $D001 on VRC6b
+
+        assert_eq!(mapper.cpu_read(0x8000), Some(4));
+        assert_eq!(mapper.cpu_read(0xA000), Some(5));
+        assert_eq!(mapper.ppu_read(0x0400), 5);
+    }
+
+    #[test]
+    fn test_vrc7_accepts_both_register_layouts() {
+        let cartridge = create_banked_cartridge(85, 16, 8);
+        let mapper = mapper::create(&cartridge);
+        let mut mapper = mapper.borrow_mut();
+
+        mapper.cpu_write(0x8010, 0x02); // VRC7a
+        assert_eq!(mapper.cpu_read(0xA000), Some(2));
+
+        mapper.cpu_write(0x8008, 0x06); // VRC7b
+        assert_eq!(mapper.cpu_read(0xA000), Some(6));
+        assert_eq!(mapper.cpu_read(0xE000), Some(15));
+
+        mapper.cpu_write(0xE000, 0x01);
+        assert_eq!(mapper.mirroring(), Mirroring::Horizontal);
+    }
+
+    #[test]
+    fn test_mmc5_prg_banking_modes() {
+        let cartridge = create_banked_cartridge(5, 16, 8);
+        let mut bus = CPUBus::load_cartridge(cartridge);
+
+        // Mode 3 at power on, $5117 selects the last bank
+        assert_eq!(bus.read_byte(0xE000), 15);
+
+        bus.write_byte(0x5100, 0x01);
+        bus.write_byte(0x5115, 0x85); // 16KB window ignores bit 0
+        bus.write_byte(0x5117, 0x06);
+        assert_eq!(bus.read_byte(0x8000), 4);
+        assert_eq!(bus.read_byte(0xA000), 5);
+        assert_eq!(bus.read_byte(0xC000), 6);
+        assert_eq!(bus.read_byte(0xE000), 7);
+    }
+
+    #[test]
+    fn test_mmc5_prg_ram_write_protect() {
+        let cartridge = create_banked_cartridge(5, 16, 8);
+        let mut bus = CPUBus::load_cartridge(cartridge);
+
+        bus.write_byte(0x6000, 0x42);
+        assert_eq!(bus.read_byte(0x6000), 0x00);
+
+        bus.write_byte(0x5102, 0x02);
+        bus.write_byte(0x5103, 0x01);
+        bus.write_byte(0x6000, 0x42);
+        assert_eq!(bus.read_byte(0x6000), 0x42);
+
+        // RAM bank 0 can also be mapped into $8000 in mode 3
+        bus.write_byte(0x5114, 0x00);
+        assert_eq!(bus.read_byte(0x8000), 0x42);
+    }
+
+    #[test]
+    fn test_mmc5_multiplier() {
+        let cartridge = create_banked_cartridge(5, 16, 8);
+        let mut bus = CPUBus::load_cartridge(cartridge);
+
+        bus.write_byte(0x5205, 12);
+        bus.write_byte(0x5206, 34);
+        assert_eq!(bus.read_byte(0x5205), 0x98);
+        assert_eq!(bus.read_byte(0x5206), 0x01);
+    }
+
+    #[test]
+    fn test_mmc5_separate_sprite_and_background_chr_banks() {
+        let cartridge = create_banked_cartridge(5, 16, 64);
+        let mapper = mapper::create(&cartridge);
+        let mut mapper = mapper.borrow_mut();
+
+        mapper.cpu_write(0x5101, 0x03); // 1KB CHR banks
+        mapper.cpu_write(0x5120, 3);
+        mapper.cpu_write(0x5128, 9);
+        mapper.set_ppu_tall_sprites(true);
+
+        mapper.set_ppu_fetch_source(PpuFetchSource::Sprite);
+        assert_eq!(mapper.ppu_read(0x0000), 3);
+
+        mapper.set_ppu_fetch_source(PpuFetchSource::Background);
+        assert_eq!(mapper.ppu_read(0x0000), 9);
+        assert_eq!(mapper.ppu_read(0x1000), 9);
+    }
+
+    #[test]
+    fn test_mmc5_fill_mode_nametable() {
+        let cartridge = create_banked_cartridge(5, 16, 8);
+        let mapper = mapper::create(&cartridge);
+        let mut mapper = mapper.borrow_mut();
+
+        mapper.cpu_write(0x5105, 0b11_11_00_11); // $2400 stays in CIRAM
+        mapper.cpu_write(0x5106, 0x42);
+        mapper.cpu_write(0x5107, 0x02);
+
+        assert_eq!(mapper.nametable_read(0x2000), Some(0x42));
+        assert_eq!(mapper.nametable_read(0x23C0), Some(0xAA));
+        assert_eq!(mapper.nametable_read(0x2400), None);
+    }
+
+    #[test]
+    fn test_mmc5_extended_attributes() {
+        let cartridge = create_banked_cartridge(5, 16, 64);
+        let mapper = mapper::create(&cartridge);
+        let mut mapper = mapper.borrow_mut();
+
+        mapper.cpu_write(0x5104, 0x02);
+        mapper.cpu_write(0x5C00, 0b11_000101); // Palette 3, 4KB bank 5
+        mapper.cpu_write(0x5104, 0x01);
+
+        mapper.set_ppu_fetch_source(PpuFetchSource::Background);
+        assert_eq!(mapper.nametable_read(0x2000), None);
+        assert_eq!(mapper.nametable_read(0x23C0), Some(0xFF));
+        assert_eq!(mapper.ppu_read(0x0010), 20); // 4KB bank 5 = 1KB bank 20
+    }
+
+    #[test]
+    fn test_mmc5_scanline_irq_from_fetch_pattern() {
+        let cartridge = create_banked_cartridge(5, 16, 8);
+        let mapper = mapper::create(&cartridge);
+        let mut mmc5 = mapper.borrow_mut();
+        mmc5.cpu_write(0x5203, 2);
+        mmc5.cpu_write(0x5204, 0x80);
+        mmc5.set_ppu_fetch_source(PpuFetchSource::Background);
+
+        // The start of each scanline reads the same nametable byte 3 times
+        let render_scanline_start = |mmc5: &mut dyn bard::mapper::Mapper| {
+            for _ in 0..3 {
+                mmc5.nametable_read(0x2002);
+            }
+            mmc5.ppu_read(0x0000);
+        };
+
+        render_scanline_start(&mut *mmc5); // Scanline 0
+        render_scanline_start(&mut *mmc5); // Scanline 1
+        assert!(!mmc5.irq_pending());
+
+        render_scanline_start(&mut *mmc5); // Scanline 2
+        assert!(mmc5.irq_pending());
+
+        // Reading $5204 reports in-frame and acknowledges the IRQ
+        assert_eq!(mmc5.cpu_read(0x5204), Some(0xC0));
+        assert!(!mmc5.irq_pending());
+
+        // Once the PPU stops reading the MMC5 leaves the frame
+        for _ in 0..4 {
+            mmc5.cpu_clock();
+        }
+        assert_eq!(mmc5.cpu_read(0x5204), Some(0x00));
+    }
+
+    #[test]
+    fn test_chr_ram_written_through_ppudata() {
+        let cartridge = create_banked_cartridge(0, 2, 0);
+        let mut bus = PPUBus::load_cartridge(cartridge);
+
+        bus.write_register(0x2006, 0x01);
+        bus.write_register(0x2006, 0x23);
+        bus.write_register(0x2007, 0x5A);
+        bus.write_register(0x2007, 0xA5);
+
+        assert_eq!(bus.read_byte(0x0123), 0x5A);
+        assert_eq!(bus.read_byte(0x0124), 0xA5);
+    }
+
+    #[test]
+    fn test_chr_rom_ignores_writes() {
+        let cartridge = create_banked_cartridge(0, 2, 8);
+        let mut bus = PPUBus::load_cartridge(cartridge);
+
+        bus.write_byte(0x0400, 0x5A);
+        assert_eq!(bus.read_byte(0x0400), 1);
+    }
+
+    #[test]
+    fn test_chr_ram_size_from_nes_20_header_is_bankable() {
+        let mut cartridge = create_banked_cartridge(21, 16, 0);
+        let mut buffer = [0x00; 16];
+        buffer.copy_from_slice(&cartridge.header.buffer);
+        buffer[7] |= 0x08; // NES 2.0
+        buffer[11] = 0x09; // 64 << 9 = 32KB of CHR-RAM
+        cartridge.header = CartridgeHeader::parse(&buffer).unwrap();
+
+        let mapper = mapper::create(&cartridge);
+        let mut mapper = mapper.borrow_mut();
+
+        // Bank 31 (the last 1KB of 32KB) into $0000
+        mapper.cpu_write(0xB000, 0x0F);
+        mapper.cpu_write(0xB002, 0x01);
+        mapper.ppu_write(0x0010, 0x42);
+        assert_eq!(mapper.ppu_read(0x0010), 0x42);
+
+        mapper.cpu_write(0xB000, 0x00);
+        mapper.cpu_write(0xB002, 0x00);
+        assert_eq!(mapper.ppu_read(0x0010), 0x00);
+
+        mapper.cpu_write(0xB000, 0x0F);
+        mapper.cpu_write(0xB002, 0x01);
+        assert_eq!(mapper.ppu_read(0x0010), 0x42);
+    }
+
+    #[test]
+    fn test_trainer_mapped_to_7000() {
+        let mut cartridge = create_banked_cartridge(0, 2, 1);
+        cartridge.trainer = (0..512).map(|i| i as u8).collect();
+        let bus = CPUBus::load_cartridge(cartridge);
+
+        assert_eq!(bus.read_byte(0x7000), 0x00);
+        assert_eq!(bus.read_byte(0x7001), 0x01);
+        assert_eq!(bus.read_byte(0x71FF), 0xFF);
+        assert_eq!(bus.read_byte(0x6000), 0x00); // Rest of the RAM starts cleared
+    }
+
+    #[test]
+    fn test_battery_backed_nrom_exposes_save_ram() {
+        let mut cartridge = create_banked_cartridge(0, 2, 1);
+        let mut buffer = [0x00; 16];
+        buffer.copy_from_slice(&cartridge.header.buffer);
+        buffer[6] |= 0x02; // Battery
+        cartridge.header = CartridgeHeader::parse(&buffer).unwrap();
+
+        let mapper = mapper::create(&cartridge);
+        mapper.borrow_mut().save_ram_mut().unwrap()[0x0010] = 0x42;
+
+        let mut bus = CPUBus::with_mapper(mapper.clone());
+        assert_eq!(bus.read_byte(0x6010), 0x42);
+
+        bus.write_byte(0x7FFF, 0x24);
+        assert_eq!(mapper.borrow().save_ram().unwrap().len(), 0x2000);
+        assert_eq!(mapper.borrow().save_ram().unwrap()[0x1FFF], 0x24);
+    }
+
+    #[test]
+    fn test_nrom_without_battery_has_no_save_ram() {
+        let cartridge = create_banked_cartridge(0, 2, 1);
+        let mapper = mapper::create(&cartridge);
+        assert!(mapper.borrow().save_ram().is_none());
+    }
+
+    /// Helper function to create a two sided disk, each side holding one 4
+    /// byte file, with the BIOS loaded.
+    fn create_fds_cartridge() -> Cartridge {
+        let image = [common::create_disk_side(&[&[1, 2, 3, 4]]), common::create_disk_side(&[&[5, 6, 7, 8]])].concat();
+        let mut cartridge = Cartridge::from_bytes(&image).unwrap();
+        cartridge.prg_rom = (0..0x2000).map(|index| (index >> 8) as u8).collect();
+        cartridge
+    }
+
+    /// Clocks the mapper until it raises an IRQ, returning the cycles taken.
+    fn clock_until_irq(mapper: &mapper::SharedMapper, limit: usize) -> Option<usize> {
+        (1..=limit).find(|_| {
+            mapper.borrow_mut().cpu_clock();
+            mapper.borrow().irq_pending()
+        })
+    }
+
+    #[test]
+    fn test_fds_memory_map() {
+        let mut bus = CPUBus::load_cartridge(create_fds_cartridge());
+
+        bus.write_byte(0x6000, 0x11);
+        bus.write_byte(0xDFFF, 0x22);
+        assert_eq!(bus.read_byte(0x6000), 0x11);
+        assert_eq!(bus.read_byte(0xDFFF), 0x22);
+
+        // The BIOS is read only
+        assert_eq!(bus.read_byte(0xE123), 0x01);
+        bus.write_byte(0xE123, 0xFF);
+        assert_eq!(bus.read_byte(0xFFFF), 0x1F);
+    }
+
+    #[test]
+    fn test_fds_irq_timer() {
+        let mapper = mapper::create(&create_fds_cartridge());
+        let mut bus = CPUBus::with_mapper(mapper.clone());
+
+        // The timer only runs with the disk registers enabled
+        bus.write_byte(0x4020, 10);
+        bus.write_byte(0x4022, 0x02);
+        assert_eq!(clock_until_irq(&mapper, 100), None);
+
+        bus.write_byte(0x4023, 0x01);
+        bus.write_byte(0x4020, 10);
+        bus.write_byte(0x4021, 0);
+        bus.write_byte(0x4022, 0x03); // Enabled, repeating
+        assert_eq!(clock_until_irq(&mapper, 100), Some(11));
+
+        // Reading the status acknowledges the IRQ
+        assert_eq!(bus.read_byte(0x4030) & 0x01, 0x01);
+        assert!(!mapper.borrow().irq_pending());
+        assert_eq!(clock_until_irq(&mapper, 100), Some(11));
+    }
+
+    #[test]
+    fn test_fds_drive_reads_blocks() {
+        let mapper = mapper::create(&create_fds_cartridge());
+        let mut bus = CPUBus::with_mapper(mapper.clone());
+
+        bus.write_byte(0x4023, 0x01);
+        assert_eq!(bus.read_byte(0x4032) & 0x01, 0x00); // Disk inserted
+
+        // Motor on, read mode, drive ready, IRQ on each byte
+        bus.write_byte(0x4025, 0xE5);
+
+        // The gap and start mark pass without an IRQ, then the block arrives
+        let cycles = clock_until_irq(&mapper, 2_000_000).unwrap();
+        assert!(cycles > 50_000 + 3_000 * 149, "Read before the lead-in gap passed");
+        assert_eq!(bus.read_byte(0x4031), 0x01);
+        assert!(!mapper.borrow().irq_pending());
+
+        assert_eq!(clock_until_irq(&mapper, 200), Some(150));
+        assert_eq!(bus.read_byte(0x4031), b'*');
+    }
+
+    #[test]
+    fn test_fds_disk_writes_are_saved() {
+        let mapper = mapper::create(&create_fds_cartridge());
+        let mut bus = CPUBus::with_mapper(mapper.clone());
+
+        assert!(!mapper.borrow().save_ram().unwrap().contains(&0xAB));
+
+        bus.write_byte(0x4023, 0x01);
+        bus.write_byte(0x4024, 0xAB);
+        bus.write_byte(0x4025, 0xC1); // Motor on, write mode, drive ready, IRQ
+        clock_until_irq(&mapper, 200_000).unwrap();
+
+        assert!(mapper.borrow().save_ram().unwrap().contains(&0xAB));
+    }
+
+    #[test]
+    fn test_fds_side_switching() {
+        let mapper = mapper::create(&create_fds_cartridge());
+        let mut bus = CPUBus::with_mapper(mapper.clone());
+        bus.write_byte(0x4023, 0x01);
+
+        assert_eq!(mapper.borrow().disk_side_count(), 2);
+        assert_eq!(mapper.borrow().inserted_disk_side(), Some(0));
+
+        mapper.borrow_mut().insert_disk_side(Some(1));
+        assert_eq!(mapper.borrow().inserted_disk_side(), None);
+        assert_eq!(bus.read_byte(0x4032) & 0x01, 0x01); // No disk
+
+        for _ in 0..2_000_000 {
+            mapper.borrow_mut().cpu_clock();
+        }
+        assert_eq!(mapper.borrow().inserted_disk_side(), Some(1));
+        assert_eq!(bus.read_byte(0x4032) & 0x01, 0x00);
+    }
+
+    /// Helper function to create an NSF tune where every byte of each 4 KB bank
+    /// holds its bank number.
+    fn create_nsf_cartridge(banks: [u8; 8], expansion_chips: u8, bank_count: u8) -> Cartridge {
+        let fields = common::NsfFields { banks, expansion_chips, ..Default::default() };
+        let data = (0..bank_count).flat_map(|bank| vec![bank; 0x1000]).collect::<Vec<u8>>();
+        Cartridge::from_bytes(&common::create_nsf(&fields, &data)).unwrap()
+    }
+
+    #[test]
+    fn test_nsf_bank_switching() {
+        let mut bus = CPUBus::load_cartridge(create_nsf_cartridge([1, 2, 3, 4, 5, 6, 7, 0], 0x00, 8));
+
+        assert_eq!(bus.read_byte(0x8000), 1);
+        assert_eq!(bus.read_byte(0xE000), 7);
+        assert_eq!(bus.read_byte(0xFFFF), 0);
+
+        bus.write_byte(0x5FF8, 6);
+        bus.write_byte(0x5FFF, 3);
+        assert_eq!(bus.read_byte(0x8FFF), 6);
+        assert_eq!(bus.read_byte(0xF000), 3);
+
+        // The board mirrors are not NSF registers
+        bus.write_byte(0x5000, 2);
+        assert_eq!(bus.read_byte(0x8000), 6);
+
+        // 8 KB of RAM at $6000
+        bus.write_byte(0x6000, 0x12);
+        bus.write_byte(0x7FFF, 0x34);
+        assert_eq!(bus.read_byte(0x6000), 0x12);
+        assert_eq!(bus.read_byte(0x7FFF), 0x34);
+    }
+
+    #[test]
+    fn test_unbanked_nsf_is_mapped_at_load_address() {
+        let bus = CPUBus::load_cartridge(create_nsf_cartridge([0; 8], 0x00, 2));
+
+        assert_eq!(bus.read_byte(0x8000), 0);
+        assert_eq!(bus.read_byte(0x9000), 1);
+    }
+
+    #[test]
+    fn test_nsf_fds_tunes_run_from_ram() {
+        let mut bus = CPUBus::load_cartridge(create_nsf_cartridge([1, 2, 3, 4, 5, 6, 7, 0], 0x04, 8));
+
+        // Banks 6 and 7 are also copied to $6000-$7FFF
+        assert_eq!(bus.read_byte(0x6000), 7);
+        assert_eq!(bus.read_byte(0x7000), 0);
+
+        // $8000-$DFFF is RAM, $E000-$FFFF stays ROM
+        bus.write_byte(0x8000, 0xAA);
+        bus.write_byte(0xE000, 0xAA);
+        assert_eq!(bus.read_byte(0x8000), 0xAA);
+        assert_eq!(bus.read_byte(0xE000), 7);
+
+        // Selecting a bank reloads the RAM
+        bus.write_byte(0x5FF6, 3);
+        bus.write_byte(0x5FF8, 5);
+        assert_eq!(bus.read_byte(0x6000), 3);
+        assert_eq!(bus.read_byte(0x8000), 5);
+    }
+
+    #[test]
+    fn test_mapper_31_board() {
+        let mut bus = CPUBus::load_cartridge(create_banked_cartridge(31, 8, 0));
+
+        // The last 4 KB bank starts at $F000
+        assert_eq!(bus.read_byte(0xF000), 7);
+
+        // 4 KB banks, so 8 KB bank n is 4 KB banks 2n and 2n + 1
+        bus.write_byte(0x5002, 5);
+        assert_eq!(bus.read_byte(0xA000), 2);
+        bus.write_byte(0x5FFA, 6);
+        assert_eq!(bus.read_byte(0xA000), 3);
+    }
+
+    #[test]
+    fn test_vs_unisystem_bank_select() {
+        let cartridge = create_banked_cartridge(99, 5, 16);
+        let mapper = mapper::create(&cartridge);
+        let mut bus = CPUBus::with_mapper(Rc::clone(&mapper));
+
+        assert_eq!(bus.read_byte(0x8000), 0);
+        assert_eq!(bus.read_byte(0xE000), 3);
+        assert_eq!(mapper.borrow_mut().ppu_read(0x0000), 0);
+
+        // OUT2 swaps in the fifth PRG bank and the second CHR bank
+        bus.write_byte(0x4016, 0x04);
+        assert_eq!(bus.read_byte(0x8000), 4);
+        assert_eq!(bus.read_byte(0xA000), 1);
+        assert_eq!(mapper.borrow_mut().ppu_read(0x0000), 8);
+
+        // 2 KB of work RAM mirrored across $6000-$7FFF
+        bus.write_byte(0x6001, 0x5A);
+        assert_eq!(bus.read_byte(0x7801), 0x5A);
+    }
+
+    #[test]
+    fn test_uxrom_bank_select() {
+        let cartridge = create_banked_cartridge(2, 8, 0);
+        let mapper = mapper::create(&cartridge);
+        let mut bus = CPUBus::with_mapper(Rc::clone(&mapper));
+
+        assert_eq!(bus.read_byte(0x8000), 0);
+        assert_eq!(bus.read_byte(0xE000), 7); // Last 16 KB fixed
+
+        bus.write_byte(0xC000, 2);
+        assert_eq!(bus.read_byte(0x8000), 4);
+        assert_eq!(bus.read_byte(0xA000), 5);
+        assert_eq!(bus.read_byte(0xC000), 6);
+
+        // CHR-RAM, and no work RAM off the Vs. System
+        mapper.borrow_mut().ppu_write(0x0010, 0x5A);
+        assert_eq!(mapper.borrow_mut().ppu_read(0x0010), 0x5A);
+        assert_eq!(mapper.borrow_mut().cpu_read(0x6000), None);
+    }
+
+    #[test]
+    fn test_vs_uxrom_has_work_ram_and_four_screen_vram() {
+        let mut cartridge = create_banked_cartridge(2, 8, 0);
+        let mut buffer = [0x00; 16];
+        buffer.copy_from_slice(&cartridge.header.buffer);
+        buffer[7] |= 0x01; // Vs. System
+        cartridge.header = CartridgeHeader::parse(&buffer).unwrap();
+
+        let mapper = mapper::try_create(&cartridge).unwrap();
+        let mut bus = CPUBus::with_mapper(Rc::clone(&mapper));
+
+        // 2 KB of work RAM mirrored across $6000-$7FFF
+        bus.write_byte(0x6001, 0x5A);
+        assert_eq!(bus.read_byte(0x7801), 0x5A);
+
+        // Nametables 2 and 3 come from the mainboard's extra VRAM
+        let mut mapper = mapper.borrow_mut();
+        assert_eq!(mapper.mirroring(), Mirroring::FourScreen);
+        assert!(mapper.nametable_write(0x2C00, 0x33));
+        assert_eq!(mapper.nametable_read(0x2C00), Some(0x33));
+        assert_eq!(mapper.nametable_read(0x2400), None);
+    }
+
+    #[test]
+    fn test_mmc5_sees_scanlines_from_ppu_rendering() {
+        let cartridge = create_banked_cartridge(5, 16, 8);
+        let mapper = mapper::create(&cartridge);
+        let mut ppu = PPU::load_from_cartridge(&cartridge);
+        let mut ppu_bus = PPUBus::with_mapper(Rc::clone(&mapper));
+        mapper.borrow_mut().cpu_write(0x5203, 100);
+        mapper.borrow_mut().cpu_write(0x5204, 0x80);
+        ppu_bus.write_register(0x2001, 0x08);
+
+        // The pre-render line and the first 50 visible lines
+        for _ in 0..(341 * 51 / 3) {
+            ppu.tick(&mut ppu_bus, 1);
+        }
+        assert!(!mapper.borrow().irq_pending());
+
+        for _ in 0..(341 * 60 / 3) {
+            ppu.tick(&mut ppu_bus, 1);
+        }
+        assert!(mapper.borrow().irq_pending());
+    }
+}
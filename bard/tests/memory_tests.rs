@@ -1,7 +1,14 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
 use bard::memory::Bus;
 use bard::memory::CPUBus;
+use bard::memory::PPUBus;
 use bard::cartridge::Cartridge;
 use bard::cartridge::CartridgeHeader;
+use bard::cartridge::VsSystemType;
+use bard::cartridge::VsPpuType;
+use bard::vs_system::{self, VsSystem};
 
 #[cfg(test)]
 mod tests {
@@ -9,15 +16,18 @@ mod tests {
 
     /// Helper function to create a test cartridge with specified PRG-ROM data.
     fn create_test_cartridge(prg_rom_data: Vec<u8>) -> Cartridge {
+        let mut buffer = [0x00; 16];
+        buffer[0..4].copy_from_slice(b"NES\x1A");
+        buffer[4] = (prg_rom_data.len() / 16_384) as u8; // Calculate size in 16KB units, mapper 0 (NROM)
+
         Cartridge {
-            header: CartridgeHeader {
-                prg_rom_size: (prg_rom_data.len() / 16_384) as u8, // Calculate size in 16KB units
-                chr_rom_size: 0, // No CHR-ROM needed for CPU memory tests
-                mapper_id: 0, // NROM (No mapper)
-                buffer: Box::new([0x00; 16]),
-            },
+            header: CartridgeHeader::parse(&buffer).unwrap(),
             prg_rom: prg_rom_data,
             chr_rom: vec![], // Empty CHR-ROM
+            trainer: vec![],
+            misc_rom: vec![],
+            disk: None,
+            nsf: None,
         }
     }
 
@@ -194,5 +204,79 @@ mod tests {
         assert_eq!(bus.read_byte(0x6000), 0xFF);
     }
 
+    #[test]
+    fn test_vs_system_inputs() {
+        let cartridge = create_test_cartridge(vec![0; 16 * 1024]);
+        let mut bus = CPUBus::load_cartridge(cartridge);
+        let vs_system = Rc::new(RefCell::new(VsSystem::new(VsSystemType::default(), 0b1010_0110)));
+        bus.set_vs_system(Rc::clone(&vs_system));
+
+        // Switches 1 and 2 in $4016, the rest in $4017
+        assert_eq!(bus.read_byte(0x4016), 0x10);
+        assert_eq!(bus.read_byte(0x4017), 0xA4);
+
+        vs_system.borrow_mut().set_coin(0, true);
+        vs_system.borrow_mut().set_service_button(true);
+        assert_eq!(bus.read_byte(0x4016), 0x34);
+
+        // The counter counts each pulse once
+        bus.write_byte(0x4020, 0x01);
+        bus.write_byte(0x4020, 0x01);
+        bus.write_byte(0x4020, 0x00);
+        bus.write_byte(0x4020, 0x01);
+        assert_eq!(vs_system.borrow().coins_counted(), 2);
+    }
+
+    #[test]
+    fn test_vs_system_protection() {
+        let system = VsSystemType::from_byte(0x10); // RBI Baseball
+        let mut bus = CPUBus::load_cartridge(create_test_cartridge(vec![0; 16 * 1024]));
+        bus.set_vs_system(Rc::new(RefCell::new(VsSystem::new(system, 0))));
+
+        bus.read_byte(0x5E00);
+        let values: Vec<u8> = (0..10).map(|_| bus.read_byte(0x5E01)).collect();
+        assert_eq!(values[0], 0xB4);
+        assert_eq!(values[9], 0x6F);
+
+        // Super Xevious answers in pairs
+        let system = VsSystemType::from_byte(0x30);
+        bus.set_vs_system(Rc::new(RefCell::new(VsSystem::new(system, 0))));
+        assert_eq!(bus.read_byte(0x54FF), 0x05);
+        assert_eq!(bus.read_byte(0x5567), 0x37);
+        assert_eq!(bus.read_byte(0x5678), 0x00);
+        assert_eq!(bus.read_byte(0x5567), 0x3E);
+        assert_eq!(bus.read_byte(0x578F), 0x89);
+    }
 
+    #[test]
+    fn test_vs_system_rp2c04_palettes() {
+        let rgb = vs_system::rgb_palette();
+        assert_eq!(vs_system::palette(VsPpuType::Rc2c05(3), None), rgb);
+
+        // Each RP2C04 reorders the RGB colours without needing a .pal file
+        for variant in 1..=4 {
+            let palette = vs_system::palette(VsPpuType::Rp2c04(variant), None);
+            assert_ne!(palette, rgb);
+            assert!(palette.iter().all(|colour| rgb.contains(colour)));
+        }
+
+        // RP2C04-0004 colour $00 is the RGB colour $18
+        assert_eq!(vs_system::palette(VsPpuType::Rp2c04(4), None)[0], rgb[0x18]);
+    }
+
+    #[test]
+    fn test_oam_dma_copies_a_page_to_oam() {
+        let cartridge = create_test_cartridge(vec![0; 16 * 1024]);
+        let ppu_bus = Rc::new(RefCell::new(PPUBus::load_cartridge(cartridge.clone())));
+        let mut bus = CPUBus::load_cartridge(cartridge);
+        bus.set_ppu_bus(Rc::clone(&ppu_bus));
+
+        for offset in 0..256 {
+            bus.write_byte(0x0200 + offset, offset as u8);
+        }
+        bus.write_byte(0x4014, 0x02);
+
+        let oam = *ppu_bus.borrow().oam();
+        assert!(oam.iter().enumerate().all(|(index, &value)| value == index as u8));
+    }
 }
@@ -1,7 +1,45 @@
+use std::rc::Rc;
 use bard::memory::PPUBus;
 use bard::memory::Bus;
+use bard::mapper;
+use bard::cartridge::Cartridge;
+use bard::cartridge::CartridgeHeader;
+use bard::cartridge::VsPpuType;
+use bard::ppu::{ScrollRegisters, PPU};
+use bard::region::Region;
 mod common;
 
+/// Helper function to create a cartridge with the given mapper and header
+/// flags 6 (mirroring bits), 32KB of PRG-ROM and 8KB of CHR-ROM.
+fn create_test_cartridge(mapper_number: u8, flags_6: u8) -> Cartridge {
+    let mut buffer = [0x00; 16];
+    buffer[0..4].copy_from_slice(b"NES\x1A");
+    buffer[4] = 2;
+    buffer[5] = 1;
+    buffer[6] = ((mapper_number & 0x0F) << 4) | flags_6;
+    buffer[7] = mapper_number & 0xF0;
+
+    Cartridge {
+        header: CartridgeHeader::parse(&buffer).unwrap(),
+        prg_rom: vec![0; 32_768],
+        chr_rom: vec![0; 8_192],
+        trainer: vec![],
+        misc_rom: vec![],
+        disk: None,
+        nsf: None,
+    }
+}
+
+/// Writes a distinct value to each of the four nametables and returns what
+/// each one reads back afterwards.
+fn nametable_contents(ppu_bus: &mut PPUBus) -> [u8; 4] {
+    for (table, value) in [(0x2000, 0x10), (0x2400, 0x11), (0x2800, 0x12), (0x2C00, 0x13)] {
+        ppu_bus.write_byte(table + 0x21, value);
+    }
+
+    [0x2000, 0x2400, 0x2800, 0x2C00].map(|table| ppu_bus.read_byte(table + 0x21))
+}
+
 #[test]
 fn test_nametable_read_write() {
     let cartridge = common::load_test_rom("dk.nes");
@@ -46,14 +84,16 @@ fn test_ppu_bus_read_write_full_range() {
     for range in PPUBus::writeable_ranges() {
         for addr in range.clone() {
             let test_value = (addr & 0xFF) as u8; // Unique test value per address
+            // Palette entries only hold 6 bits
+            let expected_value = if addr >= 0x3F00 { test_value & 0x3F } else { test_value };
             
             ppu_bus.write_byte(addr, test_value);
             let read_value = ppu_bus.read_byte(addr);
 
             assert_eq!(
-                read_value, test_value,
+                read_value, expected_value,
                 "Mismatch at address 0x{:04X}: expected 0x{:02X}, got 0x{:02X}",
-                addr, test_value, read_value
+                addr, expected_value, read_value
             );
         }
     }
@@ -65,3 +105,909 @@ fn test_ppu_bus_read_write_full_range() {
         }
     }
 }
+
+#[test]
+fn test_horizontal_mirroring_from_header() {
+    let mut ppu_bus = PPUBus::load_cartridge(create_test_cartridge(0, 0x00));
+    assert_eq!(nametable_contents(&mut ppu_bus), [0x11, 0x11, 0x13, 0x13]);
+}
+
+#[test]
+fn test_vertical_mirroring_from_header() {
+    let mut ppu_bus = PPUBus::load_cartridge(create_test_cartridge(0, 0x01));
+    assert_eq!(nametable_contents(&mut ppu_bus), [0x12, 0x13, 0x12, 0x13]);
+}
+
+#[test]
+fn test_four_screen_vram_on_cartridge() {
+    let mut ppu_bus = PPUBus::load_cartridge(create_test_cartridge(0, 0x08));
+    assert_eq!(nametable_contents(&mut ppu_bus), [0x10, 0x11, 0x12, 0x13]);
+}
+
+#[test]
+fn test_nametable_mirror_region() {
+    let mut ppu_bus = PPUBus::load_cartridge(create_test_cartridge(0, 0x01));
+
+    ppu_bus.write_byte(0x2C05, 0x42);
+    assert_eq!(ppu_bus.read_byte(0x3C05), 0x42);
+
+    ppu_bus.write_byte(0x3005, 0x24);
+    assert_eq!(ppu_bus.read_byte(0x2805), 0x24);
+}
+
+#[test]
+fn test_mapper_changes_mirroring_at_runtime() {
+    let cartridge = create_test_cartridge(23, 0x00); // VRC4
+    let mapper = mapper::create(&cartridge);
+    let mut ppu_bus = PPUBus::with_mapper(Rc::clone(&mapper));
+
+    mapper.borrow_mut().cpu_write(0x9000, 0x00);
+    assert_eq!(nametable_contents(&mut ppu_bus), [0x12, 0x13, 0x12, 0x13]);
+
+    mapper.borrow_mut().cpu_write(0x9000, 0x03); // One-screen B
+    assert_eq!(nametable_contents(&mut ppu_bus), [0x13, 0x13, 0x13, 0x13]);
+
+    // Switching back to one-screen A exposes the other page of VRAM
+    mapper.borrow_mut().cpu_write(0x9000, 0x02);
+    assert_eq!(ppu_bus.read_byte(0x2000), 0x00);
+}
+
+#[test]
+fn test_mmc5_maps_each_nametable_to_a_ciram_page() {
+    let cartridge = create_test_cartridge(5, 0x00);
+    let mapper = mapper::create(&cartridge);
+    let mut ppu_bus = PPUBus::with_mapper(Rc::clone(&mapper));
+
+    // Nametables 0 and 3 use page B, 1 and 2 use page A
+    mapper.borrow_mut().cpu_write(0x5105, 0b01_00_00_01);
+    assert_eq!(nametable_contents(&mut ppu_bus), [0x13, 0x12, 0x12, 0x13]);
+
+    // A,B,B,A and A,A,A,B have no Mirroring equivalent
+    mapper.borrow_mut().cpu_write(0x5105, 0b00_01_01_00);
+    assert_eq!(nametable_contents(&mut ppu_bus), [0x13, 0x12, 0x12, 0x13]);
+    mapper.borrow_mut().cpu_write(0x5105, 0b01_00_00_00);
+    assert_eq!(nametable_contents(&mut ppu_bus), [0x12, 0x12, 0x12, 0x13]);
+}
+
+#[test]
+fn test_rc2c05_swaps_control_registers() {
+    let mut ppu_bus = PPUBus::load_cartridge(create_test_cartridge(99, 0x00));
+    ppu_bus.set_vs_ppu(VsPpuType::Rc2c05(3));
+
+    // $2001 is PPUCTRL on the RC2C05
+    ppu_bus.write_register(0x2001, 0x80);
+    assert_eq!(ppu_bus.ppu_ctrl, 0x80);
+    ppu_bus.write_register(0x2000, 0x1E);
+    assert_eq!(ppu_bus.ppu_ctrl, 0x80);
+
+    // The low bits of PPUSTATUS hold the PPU's ID
+    assert_eq!(ppu_bus.read_register(0x2002), 0x9C);
+}
+
+#[test]
+fn test_scroll_register_writes() {
+    let mut ppu_bus = PPUBus::load_cartridge(create_test_cartridge(0, 0x00));
+
+    // The sequence from the nesdev wiki's "PPU scrolling" page
+    ppu_bus.write_register(0x2000, 0x00);
+    assert_eq!(ppu_bus.scroll.t & 0x0C00, 0x0000);
+    ppu_bus.read_register(0x2002);
+    assert!(!ppu_bus.scroll.w);
+
+    ppu_bus.write_register(0x2005, 0x7D); // X = 125
+    assert_eq!(ppu_bus.scroll.t, 0x000F);
+    assert_eq!(ppu_bus.scroll.fine_x, 0x05);
+    assert!(ppu_bus.scroll.w);
+
+    ppu_bus.write_register(0x2005, 0x5E); // Y = 94
+    assert_eq!(ppu_bus.scroll.t, 0x616F);
+    assert!(!ppu_bus.scroll.w);
+
+    ppu_bus.write_register(0x2006, 0x3D);
+    assert_eq!(ppu_bus.scroll.t, 0x3D6F);
+    ppu_bus.write_register(0x2006, 0xF0);
+    assert_eq!(ppu_bus.scroll.t, 0x3DF0);
+    assert_eq!(ppu_bus.scroll.v, 0x3DF0);
+
+    // PPUCTRL selects the nametable without touching the rest of t
+    ppu_bus.write_register(0x2000, 0x00);
+    assert_eq!(ppu_bus.scroll.t, 0x31F0);
+    assert_eq!(ppu_bus.scroll.v, 0x3DF0);
+}
+
+#[test]
+fn test_status_read_resets_write_toggle() {
+    let mut ppu_bus = PPUBus::load_cartridge(create_test_cartridge(0, 0x00));
+    let mut other_bus = PPUBus::load_cartridge(create_test_cartridge(0, 0x00));
+
+    // The toggle belongs to each PPU
+    ppu_bus.write_register(0x2006, 0x21);
+    other_bus.write_register(0x2006, 0x22);
+    other_bus.write_register(0x2006, 0x00);
+    assert_eq!(other_bus.scroll.v, 0x2200);
+
+    ppu_bus.read_register(0x2002);
+    ppu_bus.write_register(0x2006, 0x23);
+    ppu_bus.write_register(0x2006, 0x45);
+    assert_eq!(ppu_bus.scroll.v, 0x2345);
+}
+
+#[test]
+fn test_scroll_increments_wrap_nametables() {
+    let mut scroll = ScrollRegisters::new();
+
+    // Coarse X wraps into the horizontally adjacent nametable
+    scroll.v = 0x001F;
+    scroll.increment_x();
+    assert_eq!(scroll.v, 0x0400);
+
+    // Fine Y carries into coarse Y
+    scroll.v = 0x7000;
+    scroll.increment_y();
+    assert_eq!(scroll.v, 0x0020);
+
+    // Row 29 wraps into the vertically adjacent nametable, row 31 does not
+    scroll.v = 0x7000 | (29 << 5);
+    scroll.increment_y();
+    assert_eq!(scroll.v, 0x0800);
+    scroll.v = 0x7000 | (31 << 5);
+    scroll.increment_y();
+    assert_eq!(scroll.v, 0x0000);
+
+    // The horizontal and vertical halves of t are copied separately
+    scroll.t = 0x7FFF;
+    scroll.v = 0x0000;
+    scroll.copy_horizontal();
+    assert_eq!(scroll.v, 0x041F);
+    scroll.copy_vertical();
+    assert_eq!(scroll.v, 0x7FFF);
+}
+
+/// Helper function to create an NROM cartridge with 8KB of CHR-RAM, so tests
+/// can write their own tiles.
+fn create_chr_ram_cartridge() -> Cartridge {
+    let mut cartridge = create_test_cartridge(0, 0x00);
+    cartridge.header.chr_rom_bytes = 0;
+    cartridge.header.chr_ram_size = 8_192;
+    cartridge.chr_rom = vec![];
+    cartridge
+}
+
+/// Writes bytes to PPU memory through PPUADDR and PPUDATA.
+fn write_vram(ppu_bus: &mut PPUBus, address: u16, data: &[u8]) {
+    ppu_bus.read_register(0x2002);
+    ppu_bus.write_register(0x2006, (address >> 8) as u8);
+    ppu_bus.write_register(0x2006, address as u8);
+    for &value in data {
+        ppu_bus.write_register(0x2007, value);
+    }
+}
+
+/// Sets up a screen of tile 1, whose rows are the pixels 3 3 1 1 2 2 0 0,
+/// with the top left 2x2 tiles using palette 1 and the rest palette 0.
+fn create_background_scene() -> (PPU, PPUBus) {
+    let cartridge = create_chr_ram_cartridge();
+    let ppu = PPU::load_from_cartridge(&cartridge);
+    let mut ppu_bus = PPUBus::load_cartridge(cartridge);
+
+    write_vram(&mut ppu_bus, 0x0010, &[0xF0; 8]);
+    write_vram(&mut ppu_bus, 0x0018, &[0xCC; 8]);
+    write_vram(&mut ppu_bus, 0x2000, &[0x01; 960]);
+    write_vram(&mut ppu_bus, 0x23C0, &[0x01]);
+    write_vram(&mut ppu_bus, 0x3F00, &[0x0F, 0x01, 0x02, 0x03, 0x0F, 0x11, 0x12, 0x13]);
+
+    (ppu, ppu_bus)
+}
+
+/// Runs the PPU from power on (the pre-render line) through one frame.
+fn render_frame(ppu: &mut PPU, ppu_bus: &mut PPUBus) {
+    for _ in 0..(341 * 262 / 3) {
+        ppu.tick(ppu_bus, 1);
+    }
+}
+
+#[test]
+fn test_background_pipeline_draws_tiles() {
+    let (mut ppu, mut ppu_bus) = create_background_scene();
+    ppu_bus.read_register(0x2002);
+    ppu_bus.write_register(0x2000, 0x00);
+    ppu_bus.write_register(0x2005, 0x00);
+    ppu_bus.write_register(0x2005, 0x00);
+    ppu_bus.write_register(0x2001, 0x0A); // Background, including the left 8 pixels
+
+    render_frame(&mut ppu, &mut ppu_bus);
+
+    let palette_1 = [0x13, 0x13, 0x11, 0x11, 0x12, 0x12, 0x0F, 0x0F];
+    let palette_0 = [0x03, 0x03, 0x01, 0x01, 0x02, 0x02, 0x0F, 0x0F];
+    assert_eq!(ppu.frame_buffer[0..8], palette_1);
+    assert_eq!(ppu.frame_buffer[8..16], palette_1);
+    assert_eq!(ppu.frame_buffer[16..24], palette_0);
+    assert_eq!(ppu.frame_buffer[15 * 256..15 * 256 + 8], palette_1);
+    assert_eq!(ppu.frame_buffer[16 * 256..16 * 256 + 8], palette_0);
+    assert_eq!(ppu.frame_buffer[239 * 256 + 248..240 * 256], palette_0);
+}
+
+#[test]
+fn test_background_fine_x_scroll() {
+    let (mut ppu, mut ppu_bus) = create_background_scene();
+    ppu_bus.read_register(0x2002);
+    ppu_bus.write_register(0x2000, 0x00);
+    ppu_bus.write_register(0x2005, 0x0A); // Tile column 1, fine X 2
+    ppu_bus.write_register(0x2005, 0x00);
+    ppu_bus.write_register(0x2001, 0x0A);
+
+    render_frame(&mut ppu, &mut ppu_bus);
+
+    // Starting 2 pixels into the second tile, the third tile is palette 0
+    assert_eq!(ppu.frame_buffer[0..8], [0x11, 0x11, 0x12, 0x12, 0x0F, 0x0F, 0x03, 0x03]);
+}
+
+#[test]
+fn test_rendering_disabled_shows_backdrop() {
+    let (mut ppu, mut ppu_bus) = create_background_scene();
+    render_frame(&mut ppu, &mut ppu_bus);
+    assert!(ppu.frame_buffer.iter().all(|&color| color == 0x0F));
+}
+
+/// Writes sprites to OAM through OAMADDR and OAMDATA. The rest of OAM is
+/// moved below the screen.
+fn write_oam(ppu_bus: &mut PPUBus, sprites: &[[u8; 4]]) {
+    ppu_bus.write_register(0x2003, 0x00);
+    for index in 0..64 {
+        for value in sprites.get(index).copied().unwrap_or([0xF0, 0x00, 0x00, 0x00]) {
+            ppu_bus.write_register(0x2004, value);
+        }
+    }
+}
+
+/// Sets up a blank background with sprite tiles: tile 2 is a single pixel
+/// in the top left corner, tile 3 is solid, and tiles $44 / $45 in the
+/// second pattern table are the top and bottom of a tall sprite.
+fn create_sprite_scene() -> (PPU, PPUBus) {
+    let cartridge = create_chr_ram_cartridge();
+    let ppu = PPU::load_from_cartridge(&cartridge);
+    let mut ppu_bus = PPUBus::load_cartridge(cartridge);
+
+    write_vram(&mut ppu_bus, 0x0020, &[0x80, 0, 0, 0, 0, 0, 0, 0]);
+    write_vram(&mut ppu_bus, 0x0030, &[0xFF; 8]);
+    write_vram(&mut ppu_bus, 0x1440, &[0xFF; 8]);
+    write_vram(&mut ppu_bus, 0x1458, &[0xFF; 8]);
+    write_vram(&mut ppu_bus, 0x3F00, &[0x0F, 0x01, 0x02, 0x03]);
+    write_vram(&mut ppu_bus, 0x3F11, &[0x21, 0x22, 0x23, 0x0F, 0x25, 0x26, 0x27]);
+
+    (ppu, ppu_bus)
+}
+
+/// Resets the scroll and shows the background and sprites.
+fn enable_rendering(ppu_bus: &mut PPUBus, ppu_ctrl: u8) {
+    ppu_bus.read_register(0x2002);
+    ppu_bus.write_register(0x2000, ppu_ctrl);
+    ppu_bus.write_register(0x2005, 0x00);
+    ppu_bus.write_register(0x2005, 0x00);
+    ppu_bus.write_register(0x2001, 0x1E);
+}
+
+fn pixel(ppu: &PPU, x: usize, y: usize) -> u16 {
+    ppu.frame_buffer[y * 256 + x]
+}
+
+#[test]
+fn test_sprites_are_drawn_flipped() {
+    let (mut ppu, mut ppu_bus) = create_sprite_scene();
+    write_oam(&mut ppu_bus, &[
+        [9, 0x02, 0x00, 20],  // Drawn from line 10
+        [9, 0x02, 0xC1, 40],  // Flipped both ways, palette 5
+        [0, 0x03, 0x00, 100], // Top line, which sprites never reach
+    ]);
+    enable_rendering(&mut ppu_bus, 0x00);
+
+    render_frame(&mut ppu, &mut ppu_bus);
+
+    assert_eq!(pixel(&ppu, 20, 10), 0x21);
+    assert_eq!(pixel(&ppu, 21, 10), 0x0F);
+    assert_eq!(pixel(&ppu, 20, 9), 0x0F);
+    assert_eq!(pixel(&ppu, 47, 17), 0x25);
+    assert_eq!(pixel(&ppu, 40, 10), 0x0F);
+    assert_eq!(pixel(&ppu, 100, 0), 0x0F);
+    assert_eq!(pixel(&ppu, 100, 1), 0x21);
+}
+
+#[test]
+fn test_sprite_priority() {
+    let (mut ppu, mut ppu_bus) = create_background_scene();
+    write_vram(&mut ppu_bus, 0x0030, &[0xFF; 8]);
+    write_vram(&mut ppu_bus, 0x3F11, &[0x21, 0x22, 0x23]);
+    write_oam(&mut ppu_bus, &[
+        [49, 0x03, 0x20, 40], // Behind the background
+        [49, 0x03, 0x00, 48], // In front
+        [49, 0x03, 0x20, 48], // Hidden by the sprite before it
+    ]);
+    enable_rendering(&mut ppu_bus, 0x00);
+
+    render_frame(&mut ppu, &mut ppu_bus);
+
+    // The background's transparent columns are 6 and 7 of each tile
+    let behind: Vec<u16> = (40..48).map(|x| pixel(&ppu, x, 50)).collect();
+    assert_eq!(behind, [0x03, 0x03, 0x01, 0x01, 0x02, 0x02, 0x21, 0x21]);
+    assert!((48..56).all(|x| pixel(&ppu, x, 50) == 0x21));
+}
+
+#[test]
+fn test_eight_sprites_per_scanline() {
+    let (mut ppu, mut ppu_bus) = create_sprite_scene();
+    let sprites: Vec<[u8; 4]> = (0..9).map(|index| [99, 0x03, 0x00, index * 10]).collect();
+    write_oam(&mut ppu_bus, &sprites);
+    enable_rendering(&mut ppu_bus, 0x00);
+
+    render_frame(&mut ppu, &mut ppu_bus);
+
+    assert_eq!(pixel(&ppu, 70, 100), 0x21);
+    assert_eq!(pixel(&ppu, 80, 100), 0x0F);
+}
+
+#[test]
+fn test_tall_sprites() {
+    let (mut ppu, mut ppu_bus) = create_sprite_scene();
+    write_oam(&mut ppu_bus, &[
+        [29, 0x45, 0x00, 10], // Tiles $44 and $45 of the second pattern table
+        [29, 0x45, 0x80, 30], // Flipped vertically
+    ]);
+    enable_rendering(&mut ppu_bus, 0x20);
+
+    render_frame(&mut ppu, &mut ppu_bus);
+
+    // The top tile draws with plane 0, the bottom with plane 1
+    assert_eq!(pixel(&ppu, 10, 30), 0x21);
+    assert_eq!(pixel(&ppu, 10, 45), 0x22);
+    assert_eq!(pixel(&ppu, 10, 46), 0x0F);
+    assert_eq!(pixel(&ppu, 30, 30), 0x22);
+    assert_eq!(pixel(&ppu, 30, 45), 0x21);
+}
+
+/// Runs the PPU for about the given number of scanlines.
+fn run_scanlines(ppu: &mut PPU, ppu_bus: &mut PPUBus, scanlines: usize) {
+    for _ in 0..(341 * scanlines / 3) {
+        ppu.tick(ppu_bus, 1);
+    }
+}
+
+fn status_flags(ppu_bus: &mut PPUBus) -> u8 {
+    ppu_bus.read_register(0x2002) & 0x60
+}
+
+#[test]
+fn test_sprite_zero_hit() {
+    let (mut ppu, mut ppu_bus) = create_background_scene();
+    write_vram(&mut ppu_bus, 0x0030, &[0xFF; 8]);
+    write_oam(&mut ppu_bus, &[[49, 0x03, 0x20, 40]]); // Behind the background still hits
+    enable_rendering(&mut ppu_bus, 0x00);
+
+    // The pre-render line and the 50 lines before the sprite
+    run_scanlines(&mut ppu, &mut ppu_bus, 51);
+    assert_eq!(status_flags(&mut ppu_bus), 0x00);
+
+    run_scanlines(&mut ppu, &mut ppu_bus, 2);
+    assert_eq!(status_flags(&mut ppu_bus), 0x40);
+
+    // Reading PPUSTATUS leaves it set, the pre-render line clears it
+    run_scanlines(&mut ppu, &mut ppu_bus, 205);
+    assert_eq!(status_flags(&mut ppu_bus), 0x40);
+    run_scanlines(&mut ppu, &mut ppu_bus, 10);
+    assert_eq!(status_flags(&mut ppu_bus), 0x00);
+}
+
+/// Renders a frame with sprite 0 solid at x over a solid background, and
+/// returns whether it hit.
+fn sprite_zero_hits(x: u8, ppu_mask: u8) -> bool {
+    let (mut ppu, mut ppu_bus) = create_background_scene();
+    write_vram(&mut ppu_bus, 0x0010, &[0xFF; 8]);
+    write_vram(&mut ppu_bus, 0x0030, &[0xFF; 8]);
+    write_oam(&mut ppu_bus, &[[49, 0x03, 0x00, x]]);
+    enable_rendering(&mut ppu_bus, 0x00);
+    ppu_bus.write_register(0x2001, ppu_mask);
+
+    render_frame(&mut ppu, &mut ppu_bus);
+    status_flags(&mut ppu_bus) & 0x40 != 0
+}
+
+#[test]
+fn test_sprite_zero_hit_exceptions() {
+    assert!(sprite_zero_hits(0, 0x1E));
+    assert!(sprite_zero_hits(248, 0x1E));
+
+    // x = 255 never hits
+    assert!(!sprite_zero_hits(255, 0x1E));
+    assert!(sprite_zero_hits(254, 0x1E));
+
+    // Hidden left columns don't hit
+    assert!(!sprite_zero_hits(0, 0x1C));
+    assert!(!sprite_zero_hits(0, 0x1A));
+    assert!(sprite_zero_hits(1, 0x1A));
+
+    // Both layers must be shown
+    assert!(!sprite_zero_hits(40, 0x0E));
+    assert!(!sprite_zero_hits(40, 0x16));
+}
+
+#[test]
+fn test_sprite_overflow() {
+    let (mut ppu, mut ppu_bus) = create_sprite_scene();
+    let mut sprites: Vec<[u8; 4]> = (0..8).map(|index| [99, 0x03, 0x00, index * 10]).collect();
+    write_oam(&mut ppu_bus, &sprites);
+    enable_rendering(&mut ppu_bus, 0x00);
+
+    render_frame(&mut ppu, &mut ppu_bus);
+    assert_eq!(status_flags(&mut ppu_bus), 0x00);
+
+    // A ninth sprite on the line
+    sprites.push([99, 0x03, 0x00, 80]);
+    write_oam(&mut ppu_bus, &sprites);
+    render_frame(&mut ppu, &mut ppu_bus);
+    assert_eq!(status_flags(&mut ppu_bus), 0x20);
+}
+
+#[test]
+fn test_sprite_overflow_diagonal_scan() {
+    let on_line: Vec<[u8; 4]> = (0..8).map(|index| [99, 0x03, 0x00, index * 10]).collect();
+
+    // The sprite after the 8th is checked by its Y, the next one by its tile
+    let mut false_positive = on_line.clone();
+    false_positive.extend([[0xF0, 0x00, 0x00, 0x00], [0xF0, 95, 0x00, 0x00]]);
+
+    let (mut ppu, mut ppu_bus) = create_sprite_scene();
+    write_oam(&mut ppu_bus, &false_positive);
+    enable_rendering(&mut ppu_bus, 0x00);
+    render_frame(&mut ppu, &mut ppu_bus);
+    assert_eq!(status_flags(&mut ppu_bus), 0x20);
+
+    // So a ninth sprite there is missed
+    let mut false_negative = on_line;
+    false_negative.extend([[0xF0, 0x00, 0x00, 0x00], [99, 0x03, 0x00, 0x00]]);
+
+    let (mut ppu, mut ppu_bus) = create_sprite_scene();
+    write_oam(&mut ppu_bus, &false_negative);
+    enable_rendering(&mut ppu_bus, 0x00);
+    render_frame(&mut ppu, &mut ppu_bus);
+    assert_eq!(status_flags(&mut ppu_bus), 0x00);
+}
+
+/// Renders a frame of the background scene with a solid sprite at x = 0 and
+/// another at x = 16, both on lines 50-57, using the given PPUMASK.
+fn render_masked_frame(ppu_mask: u8) -> PPU {
+    let (mut ppu, mut ppu_bus) = create_background_scene();
+    write_vram(&mut ppu_bus, 0x0030, &[0xFF; 8]);
+    write_vram(&mut ppu_bus, 0x3F11, &[0x21, 0x22, 0x23]);
+    write_oam(&mut ppu_bus, &[[49, 0x03, 0x00, 0], [49, 0x03, 0x00, 16]]);
+    enable_rendering(&mut ppu_bus, 0x00);
+    ppu_bus.write_register(0x2001, ppu_mask);
+
+    render_frame(&mut ppu, &mut ppu_bus);
+    ppu
+}
+
+#[test]
+fn test_mask_hides_the_left_column() {
+    // The background without its left 8 pixels shows the backdrop there
+    let ppu = render_masked_frame(0x08);
+    assert!((0..8).all(|x| pixel(&ppu, x, 0) == 0x0F));
+    assert_eq!(pixel(&ppu, 8, 0), 0x13);
+
+    // The same for sprites, over the background
+    let ppu = render_masked_frame(0x1A);
+    assert_eq!(pixel(&ppu, 0, 50), 0x03);
+    assert_eq!(pixel(&ppu, 16, 50), 0x21);
+
+    // Both layers in the left column
+    let ppu = render_masked_frame(0x1E);
+    assert_eq!(pixel(&ppu, 0, 50), 0x21);
+}
+
+#[test]
+fn test_mask_enables_each_layer() {
+    // Sprites only
+    let ppu = render_masked_frame(0x14);
+    assert_eq!(pixel(&ppu, 0, 50), 0x21);
+    assert_eq!(pixel(&ppu, 0, 0), 0x0F);
+    assert_eq!(pixel(&ppu, 24, 50), 0x0F);
+
+    // Background only
+    let ppu = render_masked_frame(0x0A);
+    assert_eq!(pixel(&ppu, 16, 50), 0x03);
+}
+
+#[test]
+fn test_mask_greyscale_and_emphasis() {
+    let ppu = render_masked_frame(0x0B);
+    assert_eq!(pixel(&ppu, 0, 0), 0x10);
+    assert_eq!(pixel(&ppu, 2, 0), 0x10);
+    assert_eq!(pixel(&ppu, 16, 0), 0x00);
+
+    // The emphasis bits are carried above the colour
+    let ppu = render_masked_frame(0xAA);
+    assert_eq!(pixel(&ppu, 0, 0), 0x13 | (0b101 << PPU::EMPHASIS_SHIFT));
+    assert_eq!(pixel(&ppu, 0, 0) & PPU::COLOR_MASK, 0x13);
+}
+
+#[test]
+fn test_rendering_disabled_leaves_v_alone() {
+    let (mut ppu, mut ppu_bus) = create_background_scene();
+    ppu_bus.read_register(0x2002);
+    ppu_bus.write_register(0x2006, 0x21);
+    ppu_bus.write_register(0x2006, 0x08);
+
+    render_frame(&mut ppu, &mut ppu_bus);
+    assert_eq!(ppu_bus.scroll.v, 0x2108);
+
+    // Either layer alone is enough for rendering to move v
+    ppu_bus.write_register(0x2001, 0x10);
+    render_frame(&mut ppu, &mut ppu_bus);
+    assert_ne!(ppu_bus.scroll.v, 0x2108);
+}
+
+/// Reads bytes from PPU memory through PPUADDR and PPUDATA, including the
+/// stale byte the first read returns.
+fn read_vram(ppu_bus: &mut PPUBus, address: u16, count: usize) -> Vec<u8> {
+    ppu_bus.read_register(0x2002);
+    ppu_bus.write_register(0x2006, (address >> 8) as u8);
+    ppu_bus.write_register(0x2006, address as u8);
+    (0..count).map(|_| ppu_bus.read_register(0x2007)).collect()
+}
+
+#[test]
+fn test_palette_backdrop_mirrors() {
+    let mut ppu_bus = PPUBus::load_cartridge(create_chr_ram_cartridge());
+
+    // The sprite palettes' first entries are the background palettes'
+    write_vram(&mut ppu_bus, 0x3F10, &[0x21, 0x22]);
+    write_vram(&mut ppu_bus, 0x3F0C, &[0x2C]);
+    write_vram(&mut ppu_bus, 0x3F14, &[0x24]);
+    assert_eq!(read_vram(&mut ppu_bus, 0x3F00, 2), [0x21, 0x00]);
+    assert_eq!(ppu_bus.read_byte(0x3F04), 0x24);
+    assert_eq!(ppu_bus.read_byte(0x3F1C), 0x2C);
+    assert_eq!(ppu_bus.read_byte(0x3F11), 0x22);
+}
+
+#[test]
+fn test_palette_mirrors_every_32_bytes() {
+    let mut ppu_bus = PPUBus::load_cartridge(create_chr_ram_cartridge());
+
+    write_vram(&mut ppu_bus, 0x3F05, &[0x15]);
+    write_vram(&mut ppu_bus, 0x3FE6, &[0x16]);
+    assert_eq!(ppu_bus.read_byte(0x3F25), 0x15);
+    assert_eq!(ppu_bus.read_byte(0x3FC5), 0x15);
+    assert_eq!(ppu_bus.read_byte(0x3F06), 0x16);
+
+    // The address bus is 14 bits wide
+    assert_eq!(ppu_bus.read_byte(0x7F05), 0x15);
+}
+
+#[test]
+fn test_palette_values_are_six_bits() {
+    let mut ppu_bus = PPUBus::load_cartridge(create_chr_ram_cartridge());
+
+    write_vram(&mut ppu_bus, 0x3F01, &[0xFF, 0xC5]);
+    assert_eq!(read_vram(&mut ppu_bus, 0x3F01, 2), [0x3F, 0x05]);
+}
+
+#[test]
+fn test_ppudata_reads_are_buffered() {
+    let mut ppu_bus = PPUBus::load_cartridge(create_chr_ram_cartridge());
+    write_vram(&mut ppu_bus, 0x2000, &[0xAA, 0xBB]);
+    write_vram(&mut ppu_bus, 0x0040, &[0xCC]);
+
+    // Each read returns what the previous one fetched
+    assert_eq!(read_vram(&mut ppu_bus, 0x2000, 3)[1..], [0xAA, 0xBB]);
+    assert_eq!(read_vram(&mut ppu_bus, 0x0040, 2)[1], 0xCC);
+
+    // $3000-$3EFF mirrors the nametables
+    assert_eq!(read_vram(&mut ppu_bus, 0x3001, 2)[1], 0xBB);
+}
+
+#[test]
+fn test_palette_reads_refill_buffer_from_nametable() {
+    let mut ppu_bus = PPUBus::load_cartridge(create_chr_ram_cartridge());
+    write_vram(&mut ppu_bus, 0x2F02, &[0x55]);
+    write_vram(&mut ppu_bus, 0x3F02, &[0x21]);
+    write_vram(&mut ppu_bus, 0x2000, &[0xAA]);
+
+    // The palette read is not delayed, but fills the buffer from $2F02
+    assert_eq!(read_vram(&mut ppu_bus, 0x3F02, 1), [0x21]);
+    assert_eq!(read_vram(&mut ppu_bus, 0x2000, 1), [0x55]);
+}
+
+#[test]
+fn test_ppudata_increment_modes() {
+    let mut ppu_bus = PPUBus::load_cartridge(create_chr_ram_cartridge());
+
+    // PPUCTRL bit 2 steps down a row of the nametable
+    ppu_bus.write_register(0x2000, 0x04);
+    write_vram(&mut ppu_bus, 0x2000, &[0x01, 0x02, 0x03]);
+    assert_eq!(ppu_bus.read_byte(0x2020), 0x02);
+    assert_eq!(ppu_bus.read_byte(0x2040), 0x03);
+    assert_eq!(ppu_bus.read_byte(0x2001), 0x00);
+
+    ppu_bus.write_register(0x2000, 0x00);
+    write_vram(&mut ppu_bus, 0x2100, &[0x04, 0x05]);
+    assert_eq!(ppu_bus.read_byte(0x2101), 0x05);
+}
+
+/// Runs the PPU a dot at a time until it has just run the given dot.
+fn run_to(ppu: &mut PPU, ppu_bus: &mut PPUBus, scanline: u16, dot: u16) {
+    while ppu.scanline() != scanline || ppu.dot() != dot {
+        ppu.step(ppu_bus);
+    }
+}
+
+fn vblank_flag(ppu_bus: &mut PPUBus) -> bool {
+    ppu_bus.read_register(0x2002) & 0x80 != 0
+}
+
+#[test]
+fn test_vblank_flag_is_read_through_status() {
+    let (mut ppu, mut ppu_bus) = create_background_scene();
+    vblank_flag(&mut ppu_bus); // Clears the flag set at power on
+
+    run_to(&mut ppu, &mut ppu_bus, 241, 10);
+    assert!(vblank_flag(&mut ppu_bus));
+    assert!(!vblank_flag(&mut ppu_bus));
+
+    // Left unread, it is cleared on the pre-render line
+    run_to(&mut ppu, &mut ppu_bus, 0, 0);
+    run_to(&mut ppu, &mut ppu_bus, 241, 10);
+    run_to(&mut ppu, &mut ppu_bus, 261, 1);
+    assert!(!vblank_flag(&mut ppu_bus));
+}
+
+#[test]
+fn test_nmi_at_vblank() {
+    let (mut ppu, mut ppu_bus) = create_background_scene();
+
+    run_to(&mut ppu, &mut ppu_bus, 241, 10);
+    assert!(!ppu_bus.take_nmi());
+
+    // Enabling NMIs during VBlank raises one at once
+    ppu_bus.write_register(0x2000, 0x80);
+    assert!(ppu_bus.take_nmi());
+    assert!(!ppu_bus.take_nmi());
+
+    // And one is raised as the next VBlank starts
+    run_to(&mut ppu, &mut ppu_bus, 0, 0);
+    run_to(&mut ppu, &mut ppu_bus, 241, 10);
+    assert!(ppu_bus.take_nmi());
+
+    // Rewriting PPUCTRL with NMIs already enabled does not raise another
+    ppu_bus.write_register(0x2000, 0x80);
+    assert!(!ppu_bus.take_nmi());
+}
+
+/// Reads $2002 having just run the given dot of scanline 241, then runs on
+/// into VBlank.
+///
+/// # Returns
+///
+/// * `(bool, bool)` - Whether the read saw VBlank, and whether an NMI followed.
+fn read_status_at_vblank(dot: u16) -> (bool, bool) {
+    let (mut ppu, mut ppu_bus) = create_background_scene();
+    ppu_bus.write_register(0x2000, 0x80);
+
+    run_to(&mut ppu, &mut ppu_bus, 241, dot);
+    let vblank = vblank_flag(&mut ppu_bus);
+    run_to(&mut ppu, &mut ppu_bus, 241, 10);
+    (vblank, ppu_bus.take_nmi())
+}
+
+#[test]
+fn test_status_read_races_vblank() {
+    // The dot before VBlank starts: never set, no NMI
+    assert_eq!(read_status_at_vblank(0), (false, false));
+
+    // The dot it starts and the one after: set, but no NMI
+    assert_eq!(read_status_at_vblank(1), (true, false));
+    assert_eq!(read_status_at_vblank(2), (true, false));
+
+    // Later reads do not stop the NMI
+    assert_eq!(read_status_at_vblank(3), (true, true));
+
+    // The NMI is held back until the race is over
+    let (mut ppu, mut ppu_bus) = create_background_scene();
+    ppu_bus.write_register(0x2000, 0x80);
+    run_to(&mut ppu, &mut ppu_bus, 241, 1);
+    assert!(!ppu_bus.take_nmi());
+    run_to(&mut ppu, &mut ppu_bus, 241, 3);
+    assert!(ppu_bus.take_nmi());
+}
+
+/// Runs the PPU from the start of one frame to the start of the next.
+///
+/// # Returns
+///
+/// * `usize` - The number of dots in the frame.
+fn dots_in_frame(ppu: &mut PPU, ppu_bus: &mut PPUBus) -> usize {
+    let frame = ppu.frame();
+    let mut dots = 0;
+    while ppu.frame() == frame {
+        ppu.step(ppu_bus);
+        dots += 1;
+    }
+    dots
+}
+
+#[test]
+fn test_tick_runs_three_dots_per_cpu_cycle() {
+    let (mut ppu, mut ppu_bus) = create_background_scene();
+    assert_eq!(ppu.position(), (0, 261, 0));
+
+    ppu.tick(&mut ppu_bus, 2);
+    assert_eq!(ppu.position(), (0, 261, 6));
+
+    // A whole scanline is 341 dots, 0-340
+    ppu.tick(&mut ppu_bus, 112);
+    assert_eq!(ppu.position(), (1, 0, 1));
+}
+
+#[test]
+fn test_frame_is_341_by_262_dots() {
+    let (mut ppu, mut ppu_bus) = create_background_scene();
+    run_to(&mut ppu, &mut ppu_bus, 0, 0);
+
+    assert_eq!(dots_in_frame(&mut ppu, &mut ppu_bus), 341 * 262);
+    assert_eq!(dots_in_frame(&mut ppu, &mut ppu_bus), 341 * 262);
+}
+
+#[test]
+fn test_odd_frames_skip_a_dot_when_rendering() {
+    let (mut ppu, mut ppu_bus) = create_background_scene();
+    run_to(&mut ppu, &mut ppu_bus, 0, 0);
+    ppu_bus.write_register(0x2001, 0x08);
+
+    // Frame 1 is odd, so its pre-render line ends at dot 339
+    assert_eq!(ppu.frame(), 1);
+    run_to(&mut ppu, &mut ppu_bus, 261, 339);
+    ppu.step(&mut ppu_bus);
+    assert_eq!(ppu.position(), (2, 0, 0));
+
+    assert_eq!(dots_in_frame(&mut ppu, &mut ppu_bus), 341 * 262);
+    assert_eq!(dots_in_frame(&mut ppu, &mut ppu_bus), 341 * 262 - 1);
+}
+
+/// Sets up the background scene on a PAL or Dendy console.
+fn create_region_scene(region: Region) -> (PPU, PPUBus) {
+    let (mut ppu, mut ppu_bus) = create_background_scene();
+    ppu.set_region(&mut ppu_bus, region);
+    (ppu, ppu_bus)
+}
+
+#[test]
+fn test_pal_frame_timing() {
+    let (mut ppu, mut ppu_bus) = create_region_scene(Region::Pal);
+    assert_eq!(ppu.position(), (0, 311, 0));
+
+    // 3.2 dots per CPU cycle
+    ppu.tick(&mut ppu_bus, 5);
+    assert_eq!(ppu.position(), (0, 311, 16));
+    ppu.tick(&mut ppu_bus, 1);
+    ppu.tick(&mut ppu_bus, 1);
+    ppu.tick(&mut ppu_bus, 1);
+    assert_eq!(ppu.dot(), 25);
+
+    // 312 scanlines, and no odd frame is shortened
+    run_to(&mut ppu, &mut ppu_bus, 0, 0);
+    ppu_bus.write_register(0x2001, 0x08);
+    assert_eq!(dots_in_frame(&mut ppu, &mut ppu_bus), 341 * 312);
+    assert_eq!(dots_in_frame(&mut ppu, &mut ppu_bus), 341 * 312);
+}
+
+#[test]
+fn test_vblank_length_by_region() {
+    // PAL VBlank starts on scanline 241 and lasts 70 scanlines
+    let (mut ppu, mut ppu_bus) = create_region_scene(Region::Pal);
+    run_to(&mut ppu, &mut ppu_bus, 241, 10);
+    assert!(vblank_flag(&mut ppu_bus));
+    run_to(&mut ppu, &mut ppu_bus, 310, 340);
+    assert_eq!(ppu_bus.read_register(0x2002) & 0x80, 0x00);
+
+    // The Dendy idles for 50 scanlines first, leaving a 20 scanline VBlank
+    let (mut ppu, mut ppu_bus) = create_region_scene(Region::Dendy);
+    ppu_bus.write_register(0x2000, 0x80);
+    run_to(&mut ppu, &mut ppu_bus, 241, 10);
+    assert!(!ppu_bus.take_nmi());
+    run_to(&mut ppu, &mut ppu_bus, 291, 10);
+    assert!(ppu_bus.take_nmi());
+    assert!(vblank_flag(&mut ppu_bus));
+}
+
+#[test]
+fn test_pal_swaps_red_and_green_emphasis() {
+    let (_, mut ppu_bus) = create_background_scene();
+    ppu_bus.write_register(0x2001, 0xA0); // Bits 5 and 7
+    assert_eq!(ppu_bus.emphasis(), 0b101);
+
+    for region in [Region::Pal, Region::Dendy] {
+        ppu_bus.set_region(region);
+        assert_eq!(ppu_bus.emphasis(), 0b110);
+    }
+}
+
+/// Helper function to read the I/O latch through a write-only register.
+fn io_latch(ppu_bus: &mut PPUBus) -> u8 {
+    ppu_bus.read_register(0x2005)
+}
+
+#[test]
+fn test_write_only_registers_return_io_latch() {
+    let (_ppu, mut ppu_bus) = create_background_scene();
+
+    ppu_bus.write_register(0x2003, 0xA5);
+    for address in [0x2000, 0x2001, 0x2003, 0x2005, 0x2006] {
+        assert_eq!(ppu_bus.read_register(address), 0xA5);
+    }
+
+    // Writes to $2002 still fill the latch, which supplies its low 5 bits
+    ppu_bus.set_status_flag(PPUBus::STATUS_SPRITE_OVERFLOW, false);
+    ppu_bus.set_status_flag(PPUBus::STATUS_VBLANK, true);
+    ppu_bus.write_register(0x2002, 0x1F);
+    assert_eq!(ppu_bus.read_register(0x2002), 0x9F);
+    assert_eq!(io_latch(&mut ppu_bus), 0x9F);
+}
+
+#[test]
+fn test_reads_refresh_io_latch() {
+    let (_ppu, mut ppu_bus) = create_background_scene();
+
+    ppu_bus.write_register(0x2003, 0x00);
+    ppu_bus.write_register(0x2004, 0x5A);
+    ppu_bus.write_register(0x2003, 0x00);
+    assert_eq!(ppu_bus.read_register(0x2004), 0x5A);
+    assert_eq!(io_latch(&mut ppu_bus), 0x5A);
+
+    // Palette reads only drive 6 bits, the top 2 come from the latch
+    write_vram(&mut ppu_bus, 0x3F00, &[0x0F]);
+    ppu_bus.write_register(0x2006, 0x3F);
+    ppu_bus.write_register(0x2006, 0x00);
+    ppu_bus.write_register(0x2003, 0xC0);
+    assert_eq!(ppu_bus.read_register(0x2007), 0xCF);
+    assert_eq!(io_latch(&mut ppu_bus), 0xCF);
+}
+
+#[test]
+fn test_io_latch_decays() {
+    let (_ppu, mut ppu_bus) = create_background_scene();
+
+    // Bits last about 600ms, 36 NTSC frames
+    ppu_bus.write_register(0x2003, 0xFF);
+    for _ in 0..35 {
+        ppu_bus.decay_io_latch();
+    }
+    assert_eq!(io_latch(&mut ppu_bus), 0xFF);
+    ppu_bus.decay_io_latch();
+    assert_eq!(io_latch(&mut ppu_bus), 0x00);
+
+    // Each bit decays from when it was last driven
+    write_vram(&mut ppu_bus, 0x3F00, &[0x0F]);
+    ppu_bus.write_register(0x2006, 0x3F);
+    ppu_bus.write_register(0x2006, 0x00);
+    ppu_bus.write_register(0x2003, 0xFF);
+    for _ in 0..20 {
+        ppu_bus.decay_io_latch();
+    }
+    ppu_bus.read_register(0x2007);
+    for _ in 0..16 {
+        ppu_bus.decay_io_latch();
+    }
+    assert_eq!(io_latch(&mut ppu_bus), 0x0F);
+}
+
+#[test]
+fn test_io_latch_decays_with_frames() {
+    let (mut ppu, mut ppu_bus) = create_region_scene(Region::Pal);
+    ppu_bus.write_register(0x2003, 0xFF);
+
+    // 30 frames at 50Hz
+    while ppu.frame() < 29 {
+        ppu.step(&mut ppu_bus);
+    }
+    assert_eq!(io_latch(&mut ppu_bus), 0xFF);
+    dots_in_frame(&mut ppu, &mut ppu_bus);
+    assert_eq!(io_latch(&mut ppu_bus), 0x00);
+}
+